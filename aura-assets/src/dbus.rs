@@ -0,0 +1,378 @@
+//! A minimal D-Bus client: just enough of the wire protocol to call methods on the
+//! session bus and wait for their reply, for activating apps through
+//! `org.freedesktop.Application`.
+//!
+//! Messages are written little-endian; replies are read in either byte order.
+
+use std::env;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixStream};
+use std::time::Duration;
+
+/// How long to wait for a reply; the same as `gdbus call`
+const CALL_TIMEOUT: Duration = Duration::from_secs(25);
+
+/// Longest message and header field array the spec allows, so a broken or hostile peer
+/// can't make us allocate gigabytes
+const MAX_MESSAGE_LEN: usize = 128 * 1024 * 1024;
+const MAX_ARRAY_LEN: usize = 64 * 1024 * 1024;
+
+const METHOD_CALL: u8 = 1;
+const METHOD_RETURN: u8 = 2;
+const ERROR: u8 = 3;
+
+// Header field codes
+const PATH: u8 = 1;
+const INTERFACE: u8 = 2;
+const MEMBER: u8 = 3;
+const ERROR_NAME: u8 = 4;
+const REPLY_SERIAL: u8 = 5;
+const DESTINATION: u8 = 6;
+const SIGNATURE: u8 = 8;
+
+/// An argument of a method call
+#[derive(Debug, Clone, Copy)]
+pub enum Arg<'a> {
+    /// `s`
+    Str(&'a str),
+    /// `as`
+    StrArray(&'a [String]),
+    /// An empty array of the given element type, e.g. `{sv}` for the platform data
+    /// `org.freedesktop.Application` methods take
+    EmptyArray(&'a str),
+}
+
+/// A connection to a message bus
+pub struct Bus {
+    stream: UnixStream,
+    serial: u32,
+}
+
+impl Bus {
+    /// Connects to `$DBUS_SESSION_BUS_ADDRESS`, or `$XDG_RUNTIME_DIR/bus` without one
+    pub fn session() -> Result<Self, Box<dyn Error>> {
+        if let Ok(address) = env::var("DBUS_SESSION_BUS_ADDRESS")
+            && !address.is_empty()
+        {
+            return Self::connect(&address);
+        }
+        let runtime_dir = env::var("XDG_RUNTIME_DIR").map_err(|_| "No session bus address")?;
+        Self::connect(&format!("unix:path={}/bus", runtime_dir))
+    }
+
+    /// Connects to a bus address such as `unix:path=/run/user/1000/bus`, trying each
+    /// `;`-separated entry in turn
+    pub fn connect(address: &str) -> Result<Self, Box<dyn Error>> {
+        let mut last_error: Box<dyn Error> = format!("No usable bus address in {}", address).into();
+        for entry in address.split(';').filter(|entry| !entry.is_empty()) {
+            match Self::connect_entry(entry) {
+                Ok(bus) => return Ok(bus),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    fn connect_entry(entry: &str) -> Result<Self, Box<dyn Error>> {
+        let (transport, params) = entry.split_once(':').ok_or("Invalid bus address")?;
+        if transport != "unix" {
+            return Err(format!("Unsupported bus transport {}", transport).into());
+        }
+
+        let mut stream = None;
+        for param in params.split(',') {
+            let Some((key, value)) = param.split_once('=') else { continue };
+            let value = unescape(value)?;
+            stream = match key {
+                "path" => Some(UnixStream::connect(String::from_utf8_lossy(&value).as_ref())?),
+                "abstract" => Some(UnixStream::connect_addr(&SocketAddr::from_abstract_name(&value)?)?),
+                _ => continue,
+            };
+        }
+        let stream = stream.ok_or("Bus address has no socket path")?;
+        stream.set_read_timeout(Some(CALL_TIMEOUT))?;
+        stream.set_write_timeout(Some(CALL_TIMEOUT))?;
+
+        let mut bus = Self { stream, serial: 0 };
+        bus.authenticate()?;
+        bus.call("org.freedesktop.DBus", "/org/freedesktop/DBus", "org.freedesktop.DBus", "Hello", &[])?;
+        Ok(bus)
+    }
+
+    /// SASL `EXTERNAL`: the bus checks our credentials on the socket
+    fn authenticate(&mut self) -> Result<(), Box<dyn Error>> {
+        let uid = rustix::process::getuid().as_raw().to_string();
+        let hex_uid: String = uid.bytes().map(|byte| format!("{:02x}", byte)).collect();
+        self.stream.write_all(format!("\0AUTH EXTERNAL {}\r\n", hex_uid).as_bytes())?;
+
+        let mut line = Vec::new();
+        let mut byte = [0u8];
+        while !line.ends_with(b"\r\n") {
+            self.stream.read_exact(&mut byte)?;
+            line.push(byte[0]);
+        }
+        if !line.starts_with(b"OK ") {
+            return Err(format!("Bus rejected authentication: {}", String::from_utf8_lossy(&line).trim_end()).into());
+        }
+        self.stream.write_all(b"BEGIN\r\n")?;
+        Ok(())
+    }
+
+    /// Calls a method and waits for its reply; an error reply becomes the error
+    pub fn call(
+        &mut self,
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        args: &[Arg<'_>],
+    ) -> Result<(), Box<dyn Error>> {
+        self.serial += 1;
+        let serial = self.serial;
+        self.stream.write_all(&method_call(serial, destination, path, interface, member, args))?;
+
+        // Skip signals such as `NameAcquired` until the reply arrives
+        loop {
+            let reply = read_message(&mut self.stream)?;
+            if reply.reply_serial != Some(serial) {
+                continue;
+            }
+            return match reply.kind {
+                METHOD_RETURN => Ok(()),
+                ERROR => {
+                    let name = reply.error_name.unwrap_or_else(|| "unknown error".to_string());
+                    Err(match reply.error_message {
+                        Some(message) => format!("{}: {}", name, message),
+                        None => name,
+                    }.into())
+                }
+                _ => continue,
+            };
+        }
+    }
+}
+
+/// Undoes the `%xx` escaping of address values
+fn unescape(value: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail.get(..2).ok_or("Invalid escape in bus address")?;
+            bytes.push(u8::from_str_radix(std::str::from_utf8(hex)?, 16)?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    Ok(bytes)
+}
+
+/// Builds little-endian message data; alignment is relative to the start of the buffer
+#[derive(Default)]
+struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn pad(&mut self, align: usize) {
+        while !self.buf.len().is_multiple_of(align) {
+            self.buf.push(0);
+        }
+    }
+
+    fn byte(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.pad(4);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.buf.extend_from_slice(value.as_bytes());
+        self.buf.push(0);
+    }
+
+    fn signature(&mut self, value: &str) {
+        self.byte(value.len() as u8);
+        self.buf.extend_from_slice(value.as_bytes());
+        self.buf.push(0);
+    }
+
+    /// Writes an array whose elements `write` adds, aligned to `align`
+    fn array(&mut self, align: usize, write: impl FnOnce(&mut Self)) {
+        self.u32(0);
+        let len_at = self.buf.len() - 4;
+        // The padding to the first element doesn't count towards the length
+        self.pad(align);
+        let start = self.buf.len();
+        write(self);
+        let len = (self.buf.len() - start) as u32;
+        self.buf[len_at..len_at + 4].copy_from_slice(&len.to_le_bytes());
+    }
+
+    /// A header field: a `(yv)` struct
+    fn field(&mut self, code: u8, signature: &str, write: impl FnOnce(&mut Self)) {
+        self.pad(8);
+        self.byte(code);
+        self.signature(signature);
+        write(self);
+    }
+}
+
+fn method_call(serial: u32, destination: &str, path: &str, interface: &str, member: &str, args: &[Arg<'_>]) -> Vec<u8> {
+    let mut body = Writer::default();
+    let mut signature = String::new();
+    for arg in args {
+        match arg {
+            Arg::Str(value) => {
+                signature.push('s');
+                body.string(value);
+            }
+            Arg::StrArray(values) => {
+                signature.push_str("as");
+                body.array(4, |body| values.iter().for_each(|value| body.string(value)));
+            }
+            Arg::EmptyArray(element) => {
+                signature.push('a');
+                signature.push_str(element);
+                // Dict entries and structs are 8-aligned, the other types used here less
+                let align = if element.starts_with('{') || element.starts_with('(') { 8 } else { 4 };
+                body.array(align, |_| {});
+            }
+        }
+    }
+
+    let mut message = Writer::default();
+    message.byte(b'l');
+    message.byte(METHOD_CALL);
+    message.byte(0);
+    message.byte(1);
+    message.u32(body.buf.len() as u32);
+    message.u32(serial);
+    message.array(8, |fields| {
+        fields.field(PATH, "o", |w| w.string(path));
+        fields.field(INTERFACE, "s", |w| w.string(interface));
+        fields.field(MEMBER, "s", |w| w.string(member));
+        fields.field(DESTINATION, "s", |w| w.string(destination));
+        if !signature.is_empty() {
+            fields.field(SIGNATURE, "g", |w| w.signature(&signature));
+        }
+    });
+    message.pad(8);
+    message.buf.extend_from_slice(&body.buf);
+    message.buf
+}
+
+/// What matters about a received message
+struct Reply {
+    kind: u8,
+    reply_serial: Option<u32>,
+    error_name: Option<String>,
+    error_message: Option<String>,
+}
+
+/// Reads message data in the sender's byte order
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl Reader<'_> {
+    fn align(&mut self, align: usize) {
+        self.pos = self.pos.next_multiple_of(align);
+    }
+
+    fn take(&mut self, len: usize) -> Result<&[u8], Box<dyn Error>> {
+        let end = self.pos.checked_add(len).ok_or("Truncated D-Bus message")?;
+        let bytes = self.buf.get(self.pos..end).ok_or("Truncated D-Bus message")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, Box<dyn Error>> {
+        self.align(4);
+        let big_endian = self.big_endian;
+        let bytes: [u8; 4] = self.take(4)?.try_into()?;
+        Ok(if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+
+    fn string(&mut self) -> Result<String, Box<dyn Error>> {
+        let len = self.u32()? as usize;
+        let value = String::from_utf8_lossy(self.take(len)?).to_string();
+        self.take(1)?;
+        Ok(value)
+    }
+
+    fn signature(&mut self) -> Result<String, Box<dyn Error>> {
+        let len = self.take(1)?[0] as usize;
+        let value = String::from_utf8_lossy(self.take(len)?).to_string();
+        self.take(1)?;
+        Ok(value)
+    }
+}
+
+fn read_message(stream: &mut UnixStream) -> Result<Reply, Box<dyn Error>> {
+    let mut fixed = [0u8; 16];
+    stream.read_exact(&mut fixed)?;
+    let big_endian = match fixed[0] {
+        b'l' => false,
+        b'B' => true,
+        _ => return Err("Invalid D-Bus message".into()),
+    };
+    let read_u32 = |bytes: &[u8]| {
+        let bytes: [u8; 4] = bytes.try_into().unwrap_or_default();
+        if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }
+    };
+    let body_len = read_u32(&fixed[4..8]) as usize;
+    let fields_len = read_u32(&fixed[12..16]) as usize;
+
+    if fields_len > MAX_ARRAY_LEN {
+        return Err(format!("D-Bus header fields too long ({} bytes)", fields_len).into());
+    }
+    let header_len = (16 + fields_len).next_multiple_of(8);
+    if header_len + body_len > MAX_MESSAGE_LEN {
+        return Err(format!("D-Bus message too long ({} bytes)", header_len + body_len).into());
+    }
+    let mut buf = fixed.to_vec();
+    buf.resize(header_len + body_len, 0);
+    stream.read_exact(&mut buf[16..])?;
+
+    let mut reply = Reply { kind: fixed[1], reply_serial: None, error_name: None, error_message: None };
+    let mut body_signature = String::new();
+    let mut reader = Reader { buf: &buf, pos: 16, big_endian };
+    while reader.pos < 16 + fields_len {
+        reader.align(8);
+        let code = reader.take(1)?[0];
+        let signature = reader.signature()?;
+        match (code, signature.as_str()) {
+            (REPLY_SERIAL, "u") => reply.reply_serial = Some(reader.u32()?),
+            (ERROR_NAME, "s") => reply.error_name = Some(reader.string()?),
+            (SIGNATURE, "g") => body_signature = reader.signature()?,
+            (_, "s" | "o") => {
+                reader.string()?;
+            }
+            (_, "g") => {
+                reader.signature()?;
+            }
+            (_, "u") => {
+                reader.u32()?;
+            }
+            _ => return Err(format!("Unexpected D-Bus header field type {}", signature).into()),
+        }
+    }
+
+    // Errors carry a message as their first argument
+    if reply.kind == ERROR && body_signature.starts_with('s') {
+        let mut body = Reader { buf: &buf[header_len..], pos: 0, big_endian };
+        reply.error_message = body.string().ok();
+    }
+    Ok(reply)
+}
@@ -0,0 +1,276 @@
+//! The `Exec` key of desktop entries: quoting, escaping and field codes.
+//!
+//! A value goes through two layers. The general string escapes (`\s`, `\n`, `\t`,
+//! `\r`, `\\`) are undone first, then the result is split into arguments using the
+//! Exec quoting rules, where a quoted argument escapes `"`, `` ` ``, `$` and `\`
+//! with a backslash. A literal backslash inside quotes is therefore `\\\\` in the file.
+
+use std::error::Error;
+use std::path::PathBuf;
+
+/// Characters that force an argument to be quoted
+const RESERVED: &[char] = &[
+    ' ', '\t', '\n', '"', '\'', '\\', '>', '<', '~', '|', '&', ';', '$', '*', '?', '#', '(', ')', '`',
+];
+
+/// Field codes the spec deprecates; they expand to nothing
+const DEPRECATED_CODES: &[char] = &['d', 'D', 'n', 'N', 'v', 'm'];
+
+/// What an application is launched with, used to fill in field codes
+#[derive(Debug, Clone, Default)]
+pub struct LaunchContext {
+    /// Files (paths or `file://` URIs) and URLs to open
+    pub targets: Vec<String>,
+    /// The `Icon` key, for `%i`
+    pub icon: Option<String>,
+    /// The translated `Name`, for `%c`
+    pub name: Option<String>,
+    /// Where the desktop entry lives, for `%k`
+    pub desktop_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Field(char),
+}
+
+/// A parsed `Exec` value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exec {
+    args: Vec<Vec<Piece>>,
+}
+
+impl Exec {
+    /// Parses the value as written in the desktop file
+    pub fn parse(value: &str) -> Result<Self, Box<dyn Error>> {
+        let unescaped = unescape_string(value);
+        let mut chars = unescaped.chars();
+
+        let mut args = Vec::new();
+        let mut current: Option<Vec<Piece>> = None;
+        let mut in_quotes = false;
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    in_quotes = !in_quotes;
+                    // `""` is an empty argument, so the quote itself starts one
+                    current.get_or_insert_with(Vec::new);
+                }
+                ' ' | '\t' | '\n' if !in_quotes => {
+                    if let Some(arg) = current.take() {
+                        args.push(arg);
+                    }
+                }
+                '\\' => {
+                    let arg = current.get_or_insert_with(Vec::new);
+                    match chars.clone().next() {
+                        // Inside quotes only these are escapes, anything else keeps its backslash
+                        Some(next) if !in_quotes || matches!(next, '"' | '`' | '$' | '\\') => {
+                            chars.next();
+                            push_text(arg, next);
+                        }
+                        _ => push_text(arg, '\\'),
+                    }
+                }
+                '%' => {
+                    let arg = current.get_or_insert_with(Vec::new);
+                    match chars.next() {
+                        Some('%') => push_text(arg, '%'),
+                        Some(code @ ('f' | 'F' | 'u' | 'U' | 'i' | 'c' | 'k')) => arg.push(Piece::Field(code)),
+                        Some(code) if DEPRECATED_CODES.contains(&code) => arg.push(Piece::Field(code)),
+                        Some(code) => return Err(format!("Unknown field code %{} in Exec '{}'", code, value).into()),
+                        None => return Err(format!("Exec '{}' ends with a lone %", value).into()),
+                    }
+                }
+                _ => push_text(current.get_or_insert_with(Vec::new), c),
+            }
+        }
+
+        if in_quotes {
+            return Err(format!("Unterminated quote in Exec '{}'", value).into());
+        }
+        args.extend(current);
+        if args.is_empty() {
+            return Err("Exec is empty".into());
+        }
+        Ok(Self { args })
+    }
+
+    /// The program to run, unless the first argument is a field code
+    pub fn program(&self) -> Option<String> {
+        let first = self.args.first()?;
+        first.iter()
+            .map(|piece| match piece {
+                Piece::Text(text) => Some(text.as_str()),
+                Piece::Field(_) => None,
+            })
+            .collect()
+    }
+
+    /// The command lines to run. `%f` and `%u` take a single target, so several
+    /// targets without `%F` or `%U` start one instance per target.
+    pub fn expand(&self, context: &LaunchContext) -> Vec<Vec<String>> {
+        let single = self.uses(&['f', 'u']) && !self.uses(&['F', 'U']);
+        if single && context.targets.len() > 1 {
+            return context.targets
+                .iter()
+                .map(|target| self.expand_with(context, std::slice::from_ref(target)))
+                .collect();
+        }
+        vec![self.expand_with(context, &context.targets)]
+    }
+
+    fn uses(&self, codes: &[char]) -> bool {
+        self.args.iter().flatten().any(|piece| matches!(piece, Piece::Field(code) if codes.contains(code)))
+    }
+
+    fn expand_with(&self, context: &LaunchContext, targets: &[String]) -> Vec<String> {
+        let mut argv = Vec::new();
+        for arg in &self.args {
+            // A code on its own may become several arguments, or none
+            if let [Piece::Field(code)] = arg.as_slice() {
+                argv.extend(field_values(*code, context, targets));
+                continue;
+            }
+
+            let mut expanded = String::new();
+            for piece in arg {
+                match piece {
+                    Piece::Text(text) => expanded.push_str(text),
+                    Piece::Field(code) => {
+                        if let Some(value) = field_values(*code, context, targets).into_iter().next() {
+                            expanded.push_str(&value);
+                        }
+                    }
+                }
+            }
+            argv.push(expanded);
+        }
+        argv
+    }
+}
+
+/// Quotes an argument for an Exec line if needed; `%` is doubled so it stays literal
+pub fn quote_arg(arg: &str) -> String {
+    let arg = arg.replace('%', "%%");
+    if !arg.is_empty() && !arg.contains(RESERVED) {
+        return arg;
+    }
+
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Builds an Exec value, as written in a desktop file, that runs exactly `args`
+pub fn to_exec_value(args: &[String]) -> String {
+    let line = args.iter().map(|arg| quote_arg(arg)).collect::<Vec<_>>().join(" ");
+    escape_string(&line)
+}
+
+/// Undoes the general string escapes of desktop entry values
+pub fn unescape_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => result.push(' '),
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('\\') => result.push('\\'),
+            // Not a string escape, leave it for the Exec quoting rules
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            }
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// Applies the general string escapes, the inverse of `unescape_string`
+pub fn escape_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+fn push_text(arg: &mut Vec<Piece>, c: char) {
+    if let Some(Piece::Text(text)) = arg.last_mut() {
+        text.push(c);
+    } else {
+        arg.push(Piece::Text(c.to_string()));
+    }
+}
+
+fn field_values(code: char, context: &LaunchContext, targets: &[String]) -> Vec<String> {
+    let files = || targets.iter().filter_map(|target| local_path(target));
+    match code {
+        'f' => files().take(1).collect(),
+        'F' => files().collect(),
+        // Local paths are fine where URLs are expected
+        'u' => targets.iter().take(1).cloned().collect(),
+        'U' => targets.to_vec(),
+        'i' => context.icon.iter()
+            .filter(|icon| !icon.is_empty())
+            .flat_map(|icon| ["--icon".to_string(), icon.clone()])
+            .collect(),
+        'c' => context.name.iter().cloned().collect(),
+        'k' => context.desktop_file.iter().map(|path| path.to_string_lossy().to_string()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The local path behind a target; remote URLs have none
+fn local_path(target: &str) -> Option<String> {
+    let Some(rest) = target.strip_prefix("file://") else {
+        return (!target.contains("://")).then(|| target.to_string());
+    };
+    // file:///path or file://localhost/path
+    let path = if rest.starts_with('/') { rest } else { rest.strip_prefix("localhost")? };
+    Some(percent_decode(path))
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
@@ -0,0 +1,387 @@
+//! The installed applications and icon data, reloaded when software changes.
+//!
+//! `current` hands out the latest `AppIndex` without waiting for one to be read: the
+//! first is read in the background and announced to `subscribe`rs like every later
+//! one. `watch` follows the application and icon directories with inotify and swaps
+//! in a fresh index once changes have settled, so installing or removing software
+//! needs no dock restart.
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::fd::OwnedFd;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, Once, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rustix::event::{poll, PollFd, PollFlags, Timespec};
+use rustix::fs::inotify::{self, CreateFlags, ReadFlags, WatchFlags};
+use rustix::io::Errno;
+
+use crate::locale::Locale;
+use crate::xdg::{self, XdgEnv};
+use crate::exec::Exec;
+use crate::{detect_icon_theme, find_in_theme, read_entries, AppInfo};
+
+/// Quiet time after the last change before reloading; package managers write many files
+const SETTLE_TIME: Duration = Duration::from_millis(750);
+
+/// What a change to a watched directory looks like
+const WATCH_FLAGS: WatchFlags = WatchFlags::CREATE
+    .union(WatchFlags::DELETE)
+    .union(WatchFlags::MOVED_FROM)
+    .union(WatchFlags::MOVED_TO)
+    .union(WatchFlags::CLOSE_WRITE)
+    .union(WatchFlags::DELETE_SELF)
+    .union(WatchFlags::MOVE_SELF);
+
+/// How deep icon directories are watched: `<theme>/48x48/apps`
+const ICON_DIR_DEPTH: usize = 3;
+
+static CURRENT: RwLock<Option<Arc<AppIndex>>> = RwLock::new(None);
+
+/// What `current` hands out while the first index is being read
+static EMPTY: LazyLock<Arc<AppIndex>> = LazyLock::new(|| Arc::new(AppIndex {
+    icon_theme: "hicolor".to_string(),
+    ..Default::default()
+}));
+
+static PRELOAD: Once = Once::new();
+
+type Listener = Box<dyn FnMut(&Arc<AppIndex>) -> bool + Send>;
+
+static LISTENERS: Mutex<Vec<Listener>> = Mutex::new(Vec::new());
+
+/// Programs that launch the actual app, so their name says nothing about it
+const LAUNCHER_PROGRAMS: &[&str] = &["env", "sh", "bash", "flatpak", "snap", "python", "python3", "java", "wine"];
+
+/// The desktop entries, parsed once, with lookup tables for finding the app behind a window
+#[derive(Debug, Clone, Default)]
+pub struct AppIndex {
+    // every application entry in order of precedence, including ones not listed here
+    entries: Vec<AppInfo>,
+    // the entries to list, sorted by name
+    listed: Vec<usize>,
+    // lowercased keys to entries; the first entry with a key wins
+    by_desktop_id: HashMap<String, usize>,
+    by_wm_class: HashMap<String, usize>,
+    by_file_stem: HashMap<String, usize>,
+    by_exec: HashMap<String, usize>,
+    by_name: HashMap<String, usize>,
+    icon_theme: String,
+    // where icon themes are searched, as linicon takes them
+    icon_dirs: Vec<String>,
+}
+
+impl AppIndex {
+    /// Reads the applications and icon settings of the current environment
+    pub fn load() -> Self {
+        let entries = read_entries(&xdg::application_dirs(), &Locale::from_env());
+        Self::new(entries, &xdg::current_desktops(), detect_icon_theme())
+    }
+
+    /// Indexes `entries`, given in order of precedence, for a session running `desktops`
+    pub fn new(entries: Vec<AppInfo>, desktops: &[String], icon_theme: String) -> Self {
+        let mut listed: Vec<usize> = (0..entries.len()).filter(|&i| entries[i].is_listed(desktops)).collect();
+        listed.sort_by_key(|&i| entries[i].localized_name.to_lowercase());
+
+        // linicon only knows $XDG_DATA_DIRS, and nothing when it is unset
+        let icon_dirs: Vec<String> = xdg::icon_dirs()
+            .iter()
+            .map(|dir| dir.to_string_lossy().to_string())
+            .collect();
+
+        let mut index = Self { listed, icon_theme, icon_dirs, ..Default::default() };
+        for (i, app) in entries.iter().enumerate() {
+            let add = |table: &mut HashMap<String, usize>, key: &str| {
+                if !key.is_empty() {
+                    table.entry(key.to_lowercase()).or_insert(i);
+                }
+            };
+            add(&mut index.by_desktop_id, app.app_id());
+            if let Some(class) = &app.startup_wm_class {
+                add(&mut index.by_wm_class, class);
+            }
+            if let Some(stem) = app.desktop_file.file_stem() {
+                add(&mut index.by_file_stem, &stem.to_string_lossy());
+            }
+            if let Some(program) = exec_program(&app.exec) {
+                add(&mut index.by_exec, &program);
+            }
+            add(&mut index.by_name, &app.name);
+            add(&mut index.by_name, &app.localized_name);
+        }
+        index.entries = entries;
+        index
+    }
+
+    /// The applications to list, sorted by name
+    pub fn apps(&self) -> impl Iterator<Item = &AppInfo> {
+        self.listed.iter().map(|&i| &self.entries[i])
+    }
+
+    /// The entry with a desktop file ID, given with or without `.desktop`
+    pub fn by_desktop_id(&self, desktop_id: &str) -> Option<&AppInfo> {
+        let app_id = desktop_id.strip_suffix(".desktop").unwrap_or(desktop_id);
+        self.lookup(&self.by_desktop_id, app_id)
+    }
+
+    /// The app a window belongs to, from its class and, if known, the file name of its
+    /// executable. `StartupWMClass` is most specific, the display name least.
+    pub fn app_for_window(&self, class: &str, exe: Option<&str>) -> Option<&AppInfo> {
+        self.lookup(&self.by_wm_class, class)
+            .or_else(|| self.lookup(&self.by_desktop_id, class))
+            .or_else(|| self.lookup(&self.by_file_stem, class))
+            .or_else(|| self.lookup(&self.by_exec, class))
+            .or_else(|| exe.and_then(|exe| self.lookup(&self.by_exec, exe)))
+            .or_else(|| self.lookup(&self.by_name, class))
+    }
+
+    /// The user's icon theme, `hicolor` if unknown
+    pub fn icon_theme(&self) -> &str {
+        &self.icon_theme
+    }
+
+    /// The file of the icon `name` closest to `size` pixels in the user's theme or `hicolor`
+    pub fn find_icon(&self, name: &str, size: u16) -> Option<PathBuf> {
+        find_in_theme(name, size, &self.icon_theme, &self.icon_dirs)
+    }
+
+    fn lookup(&self, table: &HashMap<String, usize>, key: &str) -> Option<&AppInfo> {
+        table.get(&key.to_lowercase()).map(|&i| &self.entries[i])
+    }
+}
+
+/// The file name of the program an `Exec` value runs, unless it is a generic launcher
+fn exec_program(exec: &str) -> Option<String> {
+    let program = Exec::parse(exec).ok()?.program()?;
+    let name = Path::new(&program).file_name()?.to_string_lossy().to_string();
+    (!LAUNCHER_PROGRAMS.contains(&name.as_str())).then_some(name)
+}
+
+/// The latest index. Until the first one has been read in the background, which this
+/// starts unless `preload` did, it is an empty index that matches nothing; `subscribe`
+/// to hear when the real one arrives.
+pub fn current() -> Arc<AppIndex> {
+    if let Some(index) = loaded() {
+        return index;
+    }
+    preload();
+    EMPTY.clone()
+}
+
+/// Whether `current` hands out a real index yet
+pub fn is_loaded() -> bool {
+    loaded().is_some()
+}
+
+/// The latest index, reading it on this thread if there is none yet; for one-shot
+/// tools like `--diagnose` that have nothing else to do meanwhile
+pub fn current_or_load() -> Arc<AppIndex> {
+    match loaded() {
+        Some(index) => index,
+        None => install_first(Arc::new(AppIndex::load())),
+    }
+}
+
+/// Starts reading the index in the background, once
+pub fn preload() {
+    PRELOAD.call_once(|| {
+        let spawned = thread::Builder::new()
+            .name("aura-app-index".to_string())
+            .spawn(|| {
+                install_first(Arc::new(AppIndex::load()));
+            });
+        if let Err(e) = spawned {
+            eprintln!("Failed to load installed apps in the background: {}", e);
+        }
+    });
+}
+
+/// Reloads the index from disk and makes it the current one
+pub fn refresh() -> Arc<AppIndex> {
+    let index = Arc::new(AppIndex::load());
+    *CURRENT.write().unwrap_or_else(|e| e.into_inner()) = Some(index.clone());
+    notify(&index);
+    index
+}
+
+/// Calls `listener` with every index that becomes current, the first one as well as
+/// reloads, from the thread that read it; returning `false` unsubscribes
+pub fn subscribe(listener: impl FnMut(&Arc<AppIndex>) -> bool + Send + 'static) {
+    LISTENERS.lock().unwrap_or_else(|e| e.into_inner()).push(Box::new(listener));
+}
+
+fn loaded() -> Option<Arc<AppIndex>> {
+    CURRENT.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Makes `index` current unless another one got there first
+fn install_first(index: Arc<AppIndex>) -> Arc<AppIndex> {
+    let mut current = CURRENT.write().unwrap_or_else(|e| e.into_inner());
+    if let Some(existing) = current.as_ref() {
+        return existing.clone();
+    }
+    *current = Some(index.clone());
+    drop(current);
+    notify(&index);
+    index
+}
+
+fn notify(index: &Arc<AppIndex>) {
+    LISTENERS.lock().unwrap_or_else(|e| e.into_inner()).retain_mut(|listener| listener(index));
+}
+
+/// Reloads the index whenever application or icon directories change; `subscribe`
+/// to get the new ones
+pub fn watch() -> Result<(), Box<dyn Error>> {
+    let mut watcher = DirWatcher::new(&watch_targets(&XdgEnv::from_env()))?;
+    thread::Builder::new()
+        .name("aura-app-index".to_string())
+        .spawn(move || {
+            loop {
+                match watcher.wait(None) {
+                    Ok(true) => {}
+                    Ok(false) => continue,
+                    Err(e) => {
+                        eprintln!("Stopped watching for installed apps: {}", e);
+                        return;
+                    }
+                }
+                while watcher.wait(Some(SETTLE_TIME)).unwrap_or(false) {}
+
+                // Watch the new directory layout before reading it, so no change slips through
+                match DirWatcher::new(&watch_targets(&XdgEnv::from_env())) {
+                    Ok(new_watcher) => watcher = new_watcher,
+                    Err(e) => eprintln!("Failed to update app directory watches: {}", e),
+                }
+                refresh();
+            }
+        })?;
+    Ok(())
+}
+
+/// The directories whose changes affect the index: applications directories with
+/// their vendor subdirectories, icon themes down to their `<size>/<context>` directories
+/// (or `<context>/<size>`), where apps install icons without touching the theme itself,
+/// and GTK's settings
+pub fn watch_targets(env: &XdgEnv) -> Vec<PathBuf> {
+    let subdirs = |dir: &Path, max_depth: usize| -> Vec<PathBuf> {
+        walkdir::WalkDir::new(dir)
+            .follow_links(true)
+            .max_depth(max_depth)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_dir())
+            .map(|entry| entry.into_path())
+            .collect()
+    };
+
+    let mut targets = Vec::new();
+    for dir in env.application_dirs() {
+        let found = subdirs(&dir, usize::MAX);
+        if found.is_empty() { targets.push(dir) } else { targets.extend(found) }
+    }
+    for dir in env.icon_dirs() {
+        let found = subdirs(&dir, ICON_DIR_DEPTH);
+        if found.is_empty() { targets.push(dir) } else { targets.extend(found) }
+    }
+    if let Some(config_home) = env.config_dir() {
+        targets.push(config_home.join("gtk-3.0"));
+    }
+    targets
+}
+
+/// Waits for changes in a set of directories. A directory that doesn't exist yet is
+/// watched through its closest existing ancestor, so its creation counts as a change.
+pub struct DirWatcher {
+    fd: OwnedFd,
+    // per watch, the entries that matter, or `None` for all of them
+    filters: HashMap<i32, Option<HashSet<OsString>>>,
+}
+
+impl DirWatcher {
+    pub fn new(dirs: &[PathBuf]) -> io::Result<Self> {
+        let fd = inotify::init(CreateFlags::CLOEXEC | CreateFlags::NONBLOCK)?;
+        let mut filters: HashMap<i32, Option<HashSet<OsString>>> = HashMap::new();
+
+        for dir in dirs {
+            let mut target = dir.as_path();
+            let mut child = None;
+            while !target.is_dir() {
+                child = target.file_name();
+                let Some(parent) = target.parent() else { break };
+                target = parent;
+            }
+
+            let Ok(wd) = inotify::add_watch(&fd, target, WATCH_FLAGS) else { continue };
+            match (filters.get_mut(&wd), child) {
+                (Some(None), _) => {}
+                (Some(Some(names)), Some(child)) => {
+                    names.insert(child.to_os_string());
+                }
+                (Some(filter), None) => *filter = None,
+                (None, child) => {
+                    filters.insert(wd, child.map(|child| HashSet::from([child.to_os_string()])));
+                }
+            }
+        }
+        Ok(Self { fd, filters })
+    }
+
+    /// Blocks until something changed, or `timeout` passes without a change (`false`)
+    pub fn wait(&mut self, timeout: Option<Duration>) -> io::Result<bool> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let remaining = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Ok(false);
+                    }
+                    Some(Timespec::try_from(deadline - now).map_err(|_| Errno::INVAL)?)
+                }
+                None => None,
+            };
+
+            let mut fds = [PollFd::new(&self.fd, PollFlags::IN)];
+            match poll(&mut fds, remaining.as_ref()) {
+                Ok(_) | Err(Errno::INTR) => {}
+                Err(e) => return Err(e.into()),
+            }
+            if self.drain()? {
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Reads the pending events, telling whether any of them matters
+    fn drain(&mut self) -> io::Result<bool> {
+        let mut buf = [MaybeUninit::uninit(); 4096];
+        let mut reader = inotify::Reader::new(&self.fd, &mut buf);
+        let mut changed = false;
+        loop {
+            let event = match reader.next() {
+                Ok(event) => event,
+                Err(Errno::WOULDBLOCK) => return Ok(changed),
+                Err(Errno::INTR) => continue,
+                Err(e) => return Err(e.into()),
+            };
+            // Events were dropped, or a watched directory went away
+            if event.events().intersects(ReadFlags::QUEUE_OVERFLOW | ReadFlags::IGNORED) {
+                changed = true;
+                continue;
+            }
+            changed |= match self.filters.get(&event.wd()) {
+                Some(None) => true,
+                Some(Some(names)) => event.file_name()
+                    .is_some_and(|name| names.contains(OsStr::from_bytes(name.to_bytes()))),
+                None => false,
+            };
+        }
+    }
+}
@@ -0,0 +1,391 @@
+//! Starting applications the way their desktop entries ask for.
+//!
+//! Apps run in a session of their own with their output in a per-app log under
+//! `$XDG_STATE_HOME/aura/logs`. A single background thread waits for all of them
+//! so exited apps don't stay around as zombies.
+
+use std::env;
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::os::fd::OwnedFd;
+use std::process::{Child, Command, Stdio};
+use std::sync::OnceLock;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use rustix::event::{eventfd, poll, EventfdFlags, PollFd, PollFlags, Timespec};
+use rustix::io::Errno;
+use rustix::process::{pidfd_open, Pid, PidfdFlags};
+
+use crate::dbus::{Arg, Bus};
+use crate::exec::{Exec, LaunchContext};
+use crate::{AppInfo, DesktopAction};
+
+/// Terminal emulators tried when `$TERMINAL` is unset, with the arguments that make them run a command
+const TERMINALS: &[(&str, &[&str])] = &[
+    ("x-terminal-emulator", &["-e"]),
+    ("gnome-terminal", &["--"]),
+    ("konsole", &["-e"]),
+    ("xfce4-terminal", &["-x"]),
+    ("kitty", &[]),
+    ("alacritty", &["-e"]),
+    ("foot", &[]),
+    ("wezterm", &["start", "--"]),
+    ("xterm", &["-e"]),
+];
+
+/// Logs above this size start over at the next launch
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+
+/// How often children without a pidfd (kernels before 5.3) are checked for having exited
+const REAP_INTERVAL: Duration = Duration::from_secs(1);
+
+static REAPER: OnceLock<Result<Reaper, String>> = OnceLock::new();
+
+/// Starts applications from their `AppInfo`
+#[derive(Debug, Clone)]
+pub struct Launcher {
+    // terminal emulator command that the app's command line is appended to
+    terminal: Option<Vec<String>>,
+    // bus to activate apps on, the session bus when `None`
+    bus_address: Option<String>,
+}
+
+/// Which `org.freedesktop.Application` method starts the app
+enum Activation {
+    Activate,
+    /// URIs of the files to open
+    Open(Vec<String>),
+    /// The ID of a desktop action
+    Action(String),
+}
+
+impl Launcher {
+    /// Uses `$TERMINAL` or the first known terminal emulator found in `PATH`
+    pub fn new() -> Self {
+        Self::with_terminal(detect_terminal())
+    }
+
+    /// Runs `Terminal=true` apps as `terminal` followed by their command line, e.g. `["foot"]`
+    pub fn with_terminal(terminal: Option<Vec<String>>) -> Self {
+        Self { terminal, bus_address: None }
+    }
+
+    /// Activates `app` on this bus instead of the session bus, e.g. `unix:path=/tmp/bus`
+    pub fn with_bus_address(mut self, address: impl Into<String>) -> Self {
+        self.bus_address = Some(address.into());
+        self
+    }
+
+    /// Starts `app` with the given files or URLs.
+    ///
+    /// D-Bus activation blocks until the app has handled the request, which may take as
+    /// long as the app needs to start; if it fails the app's `Exec` is run instead.
+    pub fn launch(&self, app: &AppInfo, targets: &[String]) -> Result<(), Box<dyn Error>> {
+        let activation = if targets.is_empty() {
+            Activation::Activate
+        } else {
+            Activation::Open(targets.iter().map(|target| to_uri(target)).collect())
+        };
+        self.activate_or_exec(app, &app.exec, activation, targets)
+    }
+
+    /// Runs one of the app's desktop actions, the same way `launch` starts the app
+    pub fn launch_action(&self, app: &AppInfo, action: &DesktopAction, targets: &[String]) -> Result<(), Box<dyn Error>> {
+        self.activate_or_exec(app, &action.exec, Activation::Action(action.id.clone()), targets)
+    }
+
+    /// The processes `launch` starts for an app run through its `Exec` key
+    pub fn commands(&self, app: &AppInfo, targets: &[String]) -> Result<Vec<Command>, Box<dyn Error>> {
+        self.exec_commands(app, &app.exec, targets)
+    }
+
+    /// The processes `launch_action` starts for an action run through its `Exec` key
+    pub fn action_commands(&self, app: &AppInfo, action: &DesktopAction, targets: &[String]) -> Result<Vec<Command>, Box<dyn Error>> {
+        self.exec_commands(app, &action.exec, targets)
+    }
+
+    fn activate_or_exec(
+        &self,
+        app: &AppInfo,
+        exec: &str,
+        activation: Activation,
+        targets: &[String],
+    ) -> Result<(), Box<dyn Error>> {
+        if !app.dbus_activatable {
+            return self.spawn_exec(app, exec, targets);
+        }
+
+        let Err(e) = self.activate(app, &activation) else { return Ok(()) };
+        if exec.trim().is_empty() {
+            return Err(format!("D-Bus activation failed: {}", e).into());
+        }
+        self.spawn_exec(app, exec, targets)
+            .map_err(|exec_error| format!("{} (D-Bus activation failed: {})", exec_error, e).into())
+    }
+
+    /// Calls the app's `org.freedesktop.Application` interface, which starts it if needed
+    fn activate(&self, app: &AppInfo, activation: &Activation) -> Result<(), Box<dyn Error>> {
+        let bus_name = app.app_id();
+        if !is_bus_name(bus_name) {
+            return Err(format!("{} is no valid D-Bus name", bus_name).into());
+        }
+        let mut bus = match &self.bus_address {
+            Some(address) => Bus::connect(address)?,
+            None => Bus::session()?,
+        };
+
+        let path = dbus_object_path(bus_name);
+        let platform_data = Arg::EmptyArray("{sv}");
+        let (method, args) = match activation {
+            Activation::Activate => ("Activate", vec![platform_data]),
+            Activation::Open(uris) => ("Open", vec![Arg::StrArray(uris), platform_data]),
+            Activation::Action(id) => ("ActivateAction", vec![Arg::Str(id), Arg::EmptyArray("v"), platform_data]),
+        };
+        bus.call(bus_name, &path, "org.freedesktop.Application", method, &args)
+    }
+
+    fn exec_commands(&self, app: &AppInfo, exec: &str, targets: &[String]) -> Result<Vec<Command>, Box<dyn Error>> {
+        let exec = Exec::parse(exec)?;
+        let context = LaunchContext {
+            targets: targets.to_vec(),
+            icon: Some(app.icon_name.clone()),
+            name: Some(app.localized_name.clone()),
+            desktop_file: Some(app.desktop_file.clone()),
+        };
+
+        exec.expand(&context)
+            .into_iter()
+            .map(|argv| {
+                let argv = if app.terminal {
+                    let terminal = self.terminal.as_ref().ok_or("No terminal emulator found")?;
+                    terminal.iter().cloned().chain(argv).collect()
+                } else {
+                    argv
+                };
+                let (program, args) = argv.split_first().ok_or("Exec expands to an empty command")?;
+
+                let mut command = Command::new(program);
+                command.args(args);
+                if let Some(dir) = &app.working_dir {
+                    command.current_dir(dir);
+                }
+                Ok(command)
+            })
+            .collect()
+    }
+
+    fn spawn_exec(&self, app: &AppInfo, exec: &str, targets: &[String]) -> Result<(), Box<dyn Error>> {
+        // Named after the desktop ID, which is unique unlike the display name
+        let log = log_path(app.app_id());
+
+        for mut command in self.exec_commands(app, exec, targets)? {
+            spawn_detached(&mut command, log.as_deref())
+                .map_err(|e| format!("{}: {}", command.get_program().to_string_lossy(), e))?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Launcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Starts `command` in a new session, appending its output to `log` (discarded without one),
+/// and reaps it in the background. Returns the PID.
+pub fn spawn_detached(command: &mut Command, log: Option<&Path>) -> Result<u32, Box<dyn Error>> {
+    command.stdin(Stdio::null());
+    match log.and_then(|path| open_log(path, command)) {
+        Some(file) => {
+            command.stdout(file.try_clone()?);
+            command.stderr(file);
+        }
+        None => {
+            command.stdout(Stdio::null());
+            command.stderr(Stdio::null());
+        }
+    }
+
+    // Leave the dock's session and process group, so signals aimed at the dock don't reach the app
+    // SAFETY: setsid is async-signal-safe and touches no memory of the parent
+    unsafe {
+        command.pre_exec(|| {
+            rustix::process::setsid()?;
+            Ok(())
+        });
+    }
+
+    let reaper = REAPER.get_or_init(|| Reaper::start().map_err(|e| e.to_string())).as_ref().map_err(|e| e.as_str())?;
+    let child = command.spawn()?;
+    let pid = child.id();
+    reaper.adopt(child);
+    Ok(pid)
+}
+
+/// The thread that waits for every child `spawn_detached` started, woken by their pidfds
+struct Reaper {
+    children: Sender<Child>,
+    // written after each new child, so the thread picks it up
+    wake: OwnedFd,
+}
+
+impl Reaper {
+    fn start() -> Result<Self, Box<dyn Error>> {
+        let wake = eventfd(0, EventfdFlags::CLOEXEC | EventfdFlags::NONBLOCK)?;
+        let thread_wake = wake.try_clone()?;
+        let (children, adopted) = mpsc::channel();
+        thread::Builder::new()
+            .name("aura-reaper".to_string())
+            .spawn(move || reap(adopted, thread_wake))?;
+        Ok(Self { children, wake })
+    }
+
+    fn adopt(&self, child: Child) {
+        if self.children.send(child).is_ok() {
+            let _ = rustix::io::write(&self.wake, &1u64.to_ne_bytes());
+        }
+    }
+}
+
+fn reap(adopted: Receiver<Child>, wake: OwnedFd) {
+    let mut running: Vec<(Child, Option<OwnedFd>)> = Vec::new();
+    loop {
+        for child in adopted.try_iter() {
+            let pidfd = Pid::from_raw(child.id() as i32).and_then(|pid| pidfd_open(pid, PidfdFlags::empty()).ok());
+            running.push((child, pidfd));
+        }
+        running.retain_mut(|(child, _)| matches!(child.try_wait(), Ok(None)));
+
+        // A pidfd turns readable once its process exits
+        let timeout = running.iter()
+            .any(|(_, pidfd)| pidfd.is_none())
+            .then(|| Timespec::try_from(REAP_INTERVAL).expect("REAP_INTERVAL fits a timespec"));
+        let mut fds: Vec<PollFd> = std::iter::once(&wake)
+            .chain(running.iter().filter_map(|(_, pidfd)| pidfd.as_ref()))
+            .map(|fd| PollFd::new(fd, PollFlags::IN))
+            .collect();
+        match poll(&mut fds, timeout.as_ref()) {
+            Ok(_) | Err(Errno::INTR) => {}
+            Err(e) => {
+                eprintln!("Failed to wait for launched apps, exited ones stay zombies: {}", e);
+                return;
+            }
+        }
+        let _ = rustix::io::read(&wake, &mut [0; 8]);
+    }
+}
+
+/// Where the output of apps launched under `name` goes, if there is a state directory
+pub fn log_path(name: &str) -> Option<PathBuf> {
+    let state_home = env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+
+    // Keep the name a single, visible path component
+    let file_name: String = name.trim_start_matches('.')
+        .chars()
+        .map(|c| if c == '/' || c.is_control() { '_' } else { c })
+        .collect();
+    if file_name.is_empty() {
+        return None;
+    }
+    Some(state_home.join("aura/logs").join(format!("{}.log", file_name)))
+}
+
+/// Opens the log for appending and marks where this launch starts
+fn open_log(path: &Path, command: &Command) -> Option<File> {
+    fs::create_dir_all(path.parent()?).ok()?;
+    let too_big = fs::metadata(path).is_ok_and(|meta| meta.len() > MAX_LOG_BYTES);
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(!too_big)
+        .write(true)
+        .truncate(too_big)
+        .open(path)
+        .ok()?;
+
+    let args: Vec<String> = command.get_args().map(|arg| arg.to_string_lossy().to_string()).collect();
+    writeln!(file, "--- {} {}", command.get_program().to_string_lossy(), args.join(" ")).ok()?;
+    Some(file)
+}
+
+/// Resolves a program name through `PATH`, or checks a path; only executables count
+pub fn find_program(program: &str) -> Option<PathBuf> {
+    let is_executable = |path: &Path| {
+        path.metadata().is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+    };
+
+    if program.contains('/') {
+        let path = PathBuf::from(program);
+        return is_executable(&path).then_some(path);
+    }
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| is_executable(path))
+}
+
+/// The object path an application exports on D-Bus, derived from its bus name
+pub fn dbus_object_path(bus_name: &str) -> String {
+    format!("/{}", bus_name.replace('.', "/").replace('-', "_"))
+}
+
+fn detect_terminal() -> Option<Vec<String>> {
+    let args_for = |name: &str| {
+        TERMINALS.iter()
+            .find(|(known, _)| *known == name)
+            .map(|(_, args)| args.iter().map(|arg| arg.to_string()).collect())
+            .unwrap_or_else(|| vec!["-e".to_string()])
+    };
+
+    if let Ok(terminal) = env::var("TERMINAL")
+        && let Some(program) = terminal.split_whitespace().next()
+        && find_program(program).is_some()
+    {
+        let name = Path::new(program).file_name()?.to_string_lossy().to_string();
+        let mut command: Vec<String> = terminal.split_whitespace().map(str::to_string).collect();
+        command.extend(args_for(&name));
+        return Some(command);
+    }
+
+    TERMINALS.iter()
+        .find(|(name, _)| find_program(name).is_some())
+        .map(|(name, args)| std::iter::once(name.to_string()).chain(args.iter().map(|arg| arg.to_string())).collect())
+}
+
+/// Whether `name` is a well-known bus name apps can own: at least two dot-separated
+/// elements of letters, digits, `_` and `-`, none starting with a digit
+fn is_bus_name(name: &str) -> bool {
+    let elements: Vec<&str> = name.split('.').collect();
+    name.len() <= 255
+        && elements.len() >= 2
+        && elements.iter().all(|element| {
+            !element.is_empty()
+                && !element.starts_with(|c: char| c.is_ascii_digit())
+                && element.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        })
+}
+
+/// Paths become `file://` URIs, URLs are kept
+fn to_uri(target: &str) -> String {
+    if target.contains("://") {
+        return target.to_string();
+    }
+    let mut uri = String::from("file://");
+    for byte in target.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
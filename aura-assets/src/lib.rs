@@ -1,125 +1,257 @@
-use std::path::PathBuf;
+pub mod dbus;
+pub mod exec;
+pub mod index;
+pub mod launch;
+pub mod locale;
+pub mod xdg;
+
+use std::path::{Path, PathBuf};
 use linicon::IconType;
 use std::fs;
-use std::env;
 use std::process::Command;
-use std::sync::OnceLock;
-use std::collections::HashMap;
-use walkdir::WalkDir;
+use std::collections::HashSet;
 use freedesktop_entry_parser::parse_entry;
+use index::AppIndex;
+use locale::Locale;
+
+/// An additional way to start an app from its `[Desktop Action <id>]` group,
+/// such as "New Private Window"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DesktopAction {
+    /// The ID listed under `Actions`
+    pub id: String,
+    /// `Name` as written
+    pub name: String,
+    /// `Name` translated for the user's locale, `name` if there is no translation
+    pub localized_name: String,
+    pub icon: Option<String>,
+    /// The raw `Exec` value; may be empty for D-Bus activatable apps
+    pub exec: String,
+}
 
 /// Information about an installed application
 #[derive(Debug, Clone)]
 pub struct AppInfo {
+    /// `Name` as written
     pub name: String,
+    /// `Name` translated for the user's locale, `name` if there is no translation
+    pub localized_name: String,
+    /// `GenericName`, translated, e.g. "Web Browser"
+    pub generic_name: Option<String>,
+    /// `Comment`, translated: a tooltip-sized description
+    pub comment: Option<String>,
+    /// `Keywords`, translated: extra search terms
+    pub keywords: Vec<String>,
+    /// The raw `Exec` value, see `exec::Exec::parse`
     pub exec: String,
     pub icon_name: String,
+    /// The icon file when `Icon` is an absolute path; theme icons are looked up by name,
+    /// see `index::AppIndex::find_icon`
     pub icon_path: Option<PathBuf>,
     pub desktop_file: PathBuf,
+    /// The desktop file ID, e.g. `org.gnome.Nautilus.desktop`, see `xdg::desktop_id`
+    pub desktop_id: String,
     pub categories: Vec<String>,
+    /// `Terminal=true`: a command line program to run inside a terminal emulator
+    pub terminal: bool,
+    /// `Path`: the working directory to start in
+    pub working_dir: Option<PathBuf>,
+    /// `DBusActivatable=true`: started through `org.freedesktop.Application`
+    pub dbus_activatable: bool,
+    /// `StartupWMClass`: the class of the app's windows, if it differs from the desktop ID
+    pub startup_wm_class: Option<String>,
+    /// `NoDisplay=true`: installed, but not meant for menus
+    pub no_display: bool,
+    /// `OnlyShowIn` and `NotShowIn`, see `xdg::shown_in`
+    pub only_show_in: Option<String>,
+    pub not_show_in: Option<String>,
+    /// The `Actions` the entry lists, in order
+    pub actions: Vec<DesktopAction>,
 }
 
-/// Returns a list of all installed applications
+/// Returns a list of all installed applications, see `index::current`
 pub fn get_all_apps() -> Vec<AppInfo> {
-    let home = env::var("HOME").unwrap_or_else(|_| ".".into());
-    let local_apps = std::path::Path::new(&home).join(".local/share/applications");
-    
-    let dirs = [
-        PathBuf::from("/usr/share/applications"),
-        PathBuf::from("/usr/local/share/applications"),
-        local_apps,
-    ];
+    index::current().apps().cloned().collect()
+}
 
-    let mut apps = Vec::new();
-    let mut seen_names: HashMap<String, bool> = HashMap::new();
+/// Reads the applications in `dirs` to list on `desktops`, sorted by name
+pub fn read_apps(dirs: &[PathBuf], locale: &Locale, desktops: &[String]) -> Vec<AppInfo> {
+    let mut apps: Vec<AppInfo> = read_entries(dirs, locale)
+        .into_iter()
+        .filter(|app| app.is_listed(desktops))
+        .collect();
+
+    // Sort alphabetically by the name users see
+    apps.sort_by_key(|a| a.localized_name.to_lowercase());
+    apps
+}
+
+/// Reads every application entry in `dirs`, including ones not meant for menus, in
+/// order of precedence. An entry shadows every entry with the same desktop file ID
+/// in later directories, even when it is `Hidden`; that is how users remove system entries.
+pub fn read_entries(dirs: &[PathBuf], locale: &Locale) -> Vec<AppInfo> {
+    let mut entries = Vec::new();
+    let mut seen_ids = HashSet::new();
 
     for dir in dirs {
-        if !dir.exists() { continue; }
-        
-        for entry in WalkDir::new(dir).max_depth(1) {
-            let Ok(entry) = entry else { continue };
-            let path = entry.path();
-            
-            if path.extension().and_then(|s| s.to_str()) != Some("desktop") {
+        for (desktop_id, path) in xdg::desktop_files(dir) {
+            if !seen_ids.insert(desktop_id.clone()) {
                 continue;
             }
-
-            if let Ok(desktop_entry) = parse_entry(path) {
-                let section = desktop_entry.section("Desktop Entry");
-                
-                // Skip if NoDisplay or Hidden
-                if section.attr("NoDisplay").map(|v| v == "true").unwrap_or(false) {
-                    continue;
-                }
-                if section.attr("Hidden").map(|v| v == "true").unwrap_or(false) {
-                    continue;
-                }
-                
-                // Only include Application type
-                let entry_type = section.attr("Type").unwrap_or("Application");
-                if entry_type != "Application" {
-                    continue;
-                }
-
-                let name = section.attr("Name").unwrap_or("Unknown").to_string();
-                let exec_raw = section.attr("Exec").unwrap_or("").to_string();
-                let icon_name = section.attr("Icon").unwrap_or("application-x-executable").to_string();
-                let categories_str = section.attr("Categories").unwrap_or("");
-                
-                // Skip duplicates (prefer earlier entries)
-                if seen_names.contains_key(&name) {
-                    continue;
-                }
-                seen_names.insert(name.clone(), true);
-
-                // Clean up exec command - remove field codes
-                let exec = clean_exec_command(&exec_raw);
-                
-                if exec.is_empty() {
-                    continue;
-                }
-
-                // Parse categories
-                let categories: Vec<String> = categories_str
-                    .split(';')
-                    .filter(|s| !s.is_empty())
-                    .map(|s| s.to_string())
-                    .collect();
-
-                // Resolve icon path
-                let icon_path = find_in_theme(&icon_name);
-
-                apps.push(AppInfo {
-                    name,
-                    exec,
-                    icon_name,
-                    icon_path,
-                    desktop_file: path.to_path_buf(),
-                    categories,
-                });
+            if let Some(app) = read_entry(&path, &desktop_id, locale) {
+                entries.push(app);
             }
         }
     }
+    entries
+}
 
-    // Sort alphabetically by name
-    apps.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
-    apps
+/// Reads one desktop file, `None` if it is no application to show on `desktops`
+pub fn read_app(path: &Path, desktop_id: &str, locale: &Locale, desktops: &[String]) -> Option<AppInfo> {
+    read_entry(path, desktop_id, locale).filter(|app| app.is_listed(desktops))
 }
 
-/// Cleans an Exec command by removing field codes (%f, %F, %u, %U, etc.)
-fn clean_exec_command(exec: &str) -> String {
-    let mut result = exec.to_string();
-    
-    // Remove common field codes
-    for code in &["%f", "%F", "%u", "%U", "%d", "%D", "%n", "%N", "%i", "%c", "%k", "%v", "%m"] {
-        result = result.replace(code, "");
+/// Reads one desktop file, `None` if it is no installed application. Entries
+/// for other desktops or with `NoDisplay` are read; see `AppInfo::is_listed`.
+pub fn read_entry(path: &Path, desktop_id: &str, locale: &Locale) -> Option<AppInfo> {
+    let desktop_entry = parse_entry(path).ok()?;
+    let section = desktop_entry.section("Desktop Entry");
+
+    // Hidden entries count as deleted
+    if section.attr("Hidden").map(|v| v == "true").unwrap_or(false) {
+        return None;
+    }
+
+    // Only include Application type
+    let entry_type = section.attr("Type").unwrap_or("Application");
+    if entry_type != "Application" {
+        return None;
     }
-    
-    // Clean up extra whitespace
-    result.split_whitespace().collect::<Vec<_>>().join(" ")
+
+    let name = section.attr("Name").unwrap_or("Unknown").to_string();
+    let exec = section.attr("Exec").unwrap_or("").to_string();
+    let icon_name = section.attr("Icon").unwrap_or(DEFAULT_APP_ICON).to_string();
+    let categories_str = section.attr("Categories").unwrap_or("");
+
+    let dbus_activatable = section.attr("DBusActivatable") == Some("true");
+
+    // Field codes are filled in at launch
+    if exec.trim().is_empty() && !dbus_activatable {
+        return None;
+    }
+
+    // TryExec names a binary that must be installed for the entry to count
+    if let Some(try_exec) = section.attr("TryExec")
+        && launch::find_program(try_exec).is_none()
+    {
+        return None;
+    }
+
+    // Parse categories
+    let categories: Vec<String> = categories_str
+        .split(';')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect();
+
+    // Actions without a group, a name or a way to run them are left out
+    let actions = section.attr("Actions")
+        .unwrap_or("")
+        .split(';')
+        .filter(|id| !id.is_empty())
+        .filter_map(|id| {
+            let group = desktop_entry.section(format!("Desktop Action {}", id));
+            let name = group.attr("Name")?.to_string();
+            let exec = group.attr("Exec").unwrap_or("").to_string();
+            if exec.trim().is_empty() && !dbus_activatable {
+                return None;
+            }
+            Some(DesktopAction {
+                id: id.to_string(),
+                localized_name: locale.get(&group, "Name").unwrap_or_else(|| name.clone()),
+                name,
+                icon: group.attr("Icon").filter(|icon| !icon.is_empty()).map(str::to_string),
+                exec,
+            })
+        })
+        .collect();
+
+    let translated = |key: &str| locale.get(&section, key).filter(|value| !value.is_empty());
+
+    Some(AppInfo {
+        localized_name: translated("Name").unwrap_or_else(|| name.clone()),
+        name,
+        generic_name: translated("GenericName"),
+        comment: translated("Comment"),
+        keywords: locale.get_list(&section, "Keywords"),
+        exec,
+        icon_path: Some(PathBuf::from(&icon_name)).filter(|path| path.is_absolute()),
+        icon_name,
+        desktop_file: path.to_path_buf(),
+        desktop_id: desktop_id.to_string(),
+        categories,
+        terminal: section.attr("Terminal") == Some("true"),
+        working_dir: section.attr("Path").filter(|dir| !dir.is_empty()).map(PathBuf::from),
+        dbus_activatable,
+        startup_wm_class: section.attr("StartupWMClass").filter(|class| !class.is_empty()).map(str::to_string),
+        no_display: section.attr("NoDisplay") == Some("true"),
+        only_show_in: section.attr("OnlyShowIn").map(str::to_string),
+        not_show_in: section.attr("NotShowIn").map(str::to_string),
+        actions,
+    })
 }
 
+impl AppInfo {
+    /// The desktop file ID without `.desktop`, which is also the D-Bus name of activatable apps
+    pub fn app_id(&self) -> &str {
+        self.desktop_id.strip_suffix(".desktop").unwrap_or(&self.desktop_id)
+    }
+
+    /// Whether the app belongs in the app grid of a session running `desktops`
+    pub fn is_listed(&self, desktops: &[String]) -> bool {
+        !self.no_display && xdg::shown_in(self.only_show_in.as_deref(), self.not_show_in.as_deref(), desktops)
+    }
+
+    /// Whether a search for `query` should list this app: any of the names,
+    /// keywords or categories contain it, ignoring case
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return true;
+        }
+        [Some(&self.localized_name), Some(&self.name), self.generic_name.as_ref()]
+            .into_iter()
+            .flatten()
+            .chain(&self.keywords)
+            .chain(&self.categories)
+            .any(|text| text.to_lowercase().contains(&query))
+    }
+}
+
+/// Default icon size used when the caller has no preference
+pub const DEFAULT_ICON_SIZE: u16 = 48;
+
+/// The `icon_name` of entries without an `Icon` key
+pub const DEFAULT_APP_ICON: &str = "application-x-executable";
+
 pub fn lookup_icon(app_class: &str) -> Option<PathBuf> {
+    lookup_icon_sized(app_class, DEFAULT_ICON_SIZE)
+}
+
+/// Like `lookup_icon`, but picks theme icons closest to `size` pixels
+pub fn lookup_icon_sized(app_class: &str, size: u16) -> Option<PathBuf> {
+    let index = index::current();
+    candidates_in(&index, app_class)
+        .iter()
+        .find_map(|name| index.find_icon(name, size))
+}
+
+/// Icon names `lookup_icon` tries for a window class, in order
+pub fn icon_candidates(app_class: &str) -> Vec<String> {
+    candidates_in(&index::current(), app_class)
+}
+
+fn candidates_in(index: &AppIndex, app_class: &str) -> Vec<String> {
     // 1. Try generic names
     let mut names_to_try = vec![
         app_class.to_string(),
@@ -127,92 +259,32 @@ pub fn lookup_icon(app_class: &str) -> Option<PathBuf> {
     ];
 
     // 2. Try looking up in the desktop file map
-    if let Some(mapped_name) = get_icon_map().get(app_class) {
-        names_to_try.insert(0, mapped_name.clone());
-    } else if let Some(mapped_name) = get_icon_map().get(&app_class.to_lowercase()) {
-        names_to_try.insert(0, mapped_name.clone());
-    }
-
-    for name in names_to_try {
-        if let Some(path) = find_in_theme(&name) {
-            return Some(path);
-        }
+    // Entries without an icon of their own don't beat the window's pixels
+    if let Some(app) = index.app_for_window(app_class, None)
+        && app.icon_name != DEFAULT_APP_ICON
+    {
+        names_to_try.insert(0, app.icon_name.clone());
     }
 
-    None
-}
-
-fn get_icon_map() -> &'static HashMap<String, String> {
-    static MAP: OnceLock<HashMap<String, String>> = OnceLock::new();
-    
-    MAP.get_or_init(|| {
-        let mut map = HashMap::new();
-        
-        let home = env::var("HOME").unwrap_or_else(|_| ".".into());
-        let local_apps = std::path::Path::new(&home).join(".local/share/applications");
-        
-        let dirs = [
-            PathBuf::from("/usr/share/applications"),
-            PathBuf::from("/usr/local/share/applications"),
-            local_apps,
-        ];
-
-        for dir in dirs {
-            if !dir.exists() { continue; }
-            
-            for entry in WalkDir::new(dir).max_depth(1) {
-                let Ok(entry) = entry else { continue };
-                let path = entry.path();
-                
-                if path.extension().and_then(|s| s.to_str()) == Some("desktop") {
-                    if let Ok(entry) = parse_entry(path) {
-                        let section = entry.section("Desktop Entry");
-                        if let Some(icon) = section.attr("Icon") {
-                            let icon_name = icon.to_string();
-                            
-                            // Map StartupWMClass -> Icon
-                            if let Some(wm_class) = section.attr("StartupWMClass") {
-                                map.insert(wm_class.to_string(), icon_name.clone());
-                                map.insert(wm_class.to_lowercase(), icon_name.clone());
-                            }
-                            
-                            // Map Filename (e.g. firefox.desktop -> firefox) -> Icon
-                            if let Some(file_stem) = path.file_stem().and_then(|s| s.to_str()) {
-                                map.insert(file_stem.to_string(), icon_name.clone());
-                                map.insert(file_stem.to_lowercase(), icon_name.clone());
-                            }
-                            
-                            // Map Name -> Icon
-                            if let Some(name) = section.attr("Name") {
-                                map.insert(name.to_string(), icon_name.clone());
-                                map.insert(name.to_lowercase(), icon_name.clone());
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        map
-    })
+    names_to_try
 }
 
-fn find_in_theme(name: &str) -> Option<PathBuf> {
+/// Looks `name` up in `current_theme`, then `hicolor`, below the `search_paths`
+pub(crate) fn find_in_theme(name: &str, size: u16, current_theme: &str, search_paths: &[String]) -> Option<PathBuf> {
     // Check if the name is already an absolute path (some desktop files point to /path/to/icon.png)
     let path = PathBuf::from(name);
     if path.is_absolute() && path.exists() {
         return Some(path);
     }
 
-    let current_theme = get_current_icon_theme();
     let themes = [current_theme, "hicolor"];
-    let size: u16 = 48;
     let scale: u16 = 1;
 
     for theme_name in themes {
-        let found = linicon::lookup_icon(theme_name, name, size, scale)
+        let found = linicon::lookup_icon_with_extra_paths(theme_name, name, size, scale, search_paths)
             .ok()
             .into_iter()
-            .flat_map(|iter| iter)
+            .flatten()
             .filter_map(|r| r.ok())
             .find(|icon| matches!(icon.icon_type, IconType::SVG | IconType::PNG))
             .map(|icon| icon.path);
@@ -224,39 +296,36 @@ fn find_in_theme(name: &str) -> Option<PathBuf> {
     None
 }
 
-fn get_current_icon_theme() -> &'static str {
-    static THEME: OnceLock<String> = OnceLock::new();
-    
-    THEME.get_or_init(|| {
-        // Try gsettings first
-        if let Ok(output) = Command::new("gsettings")
-            .args(&["get", "org.gnome.desktop.interface", "icon-theme"])
-            .output() 
-        {
-            if output.status.success() {
-                let s = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                return s.trim_matches('\'').to_string();
-            }
-        }
+/// The user's icon theme, see `index::AppIndex::icon_theme`
+pub fn get_current_icon_theme() -> String {
+    index::current().icon_theme().to_string()
+}
+
+/// Reads the icon theme from gsettings or GTK's settings.ini, `hicolor` if unknown
+pub(crate) fn detect_icon_theme() -> String {
+    // Try gsettings first
+    if let Ok(output) = Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", "icon-theme"])
+        .output()
+        && output.status.success()
+    {
+        let s = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        return s.trim_matches('\'').to_string();
+    }
 
-        // Try to find the config file
-        let config_dir = env::var("XDG_CONFIG_HOME")
-            .map(|p| PathBuf::from(p))
-            .unwrap_or_else(|_| {
-                let home = env::var("HOME").unwrap_or_else(|_| ".".into());
-                std::path::Path::new(&home).join(".config")
-            });
-
-        let settings_path = config_dir.join("gtk-3.0/settings.ini");
-
-        if let Ok(content) = fs::read_to_string(settings_path) {
-            for line in content.lines() {
-                if let Some(val) = line.trim().strip_prefix("gtk-icon-theme-name=") {
-                    return val.trim().to_string();
-                }
+    // Try to find the config file
+    let Some(config_dir) = xdg::XdgEnv::from_env().config_dir() else {
+        return "hicolor".to_string();
+    };
+    let settings_path = config_dir.join("gtk-3.0/settings.ini");
+
+    if let Ok(content) = fs::read_to_string(settings_path) {
+        for line in content.lines() {
+            if let Some(val) = line.trim().strip_prefix("gtk-icon-theme-name=") {
+                return val.trim().to_string();
             }
         }
-        
-        "hicolor".to_string()
-    })
+    }
+
+    "hicolor".to_string()
 }
@@ -0,0 +1,84 @@
+//! Picking translated values of localized keys such as `Name[de_DE]`.
+//!
+//! A locale `lang_COUNTRY.ENCODING@MODIFIER` matches, from best to worst,
+//! `lang_COUNTRY@MODIFIER`, `lang_COUNTRY`, `lang@MODIFIER` and `lang`; the
+//! encoding is ignored. Without a match the untranslated key is used.
+
+use std::env;
+
+use freedesktop_entry_parser::AttrSelector;
+
+/// The user's message locale
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Locale {
+    lang: String,
+    country: Option<String>,
+    modifier: Option<String>,
+}
+
+impl Locale {
+    /// Reads `LC_ALL`, `LC_MESSAGES` and `LANG`, the first one set wins
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .map(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
+
+    /// Parses a POSIX locale name; `C` and `POSIX` have no translations
+    pub fn parse(name: &str) -> Self {
+        let (rest, modifier) = match name.split_once('@') {
+            Some((rest, modifier)) => (rest, Some(modifier.to_string())),
+            None => (name, None),
+        };
+        let rest = rest.split('.').next().unwrap_or_default();
+        let (lang, country) = match rest.split_once('_') {
+            Some((lang, country)) => (lang, Some(country.to_string())),
+            None => (rest, None),
+        };
+        if lang.is_empty() || lang == "C" || lang == "POSIX" {
+            return Self::default();
+        }
+        Self { lang: lang.to_string(), country, modifier }
+    }
+
+    /// The `[...]` suffixes to look for, best match first
+    pub fn candidates(&self) -> Vec<String> {
+        if self.lang.is_empty() {
+            return Vec::new();
+        }
+        let mut candidates = Vec::new();
+        if let Some(country) = &self.country {
+            if let Some(modifier) = &self.modifier {
+                candidates.push(format!("{}_{}@{}", self.lang, country, modifier));
+            }
+            candidates.push(format!("{}_{}", self.lang, country));
+        }
+        if let Some(modifier) = &self.modifier {
+            candidates.push(format!("{}@{}", self.lang, modifier));
+        }
+        candidates.push(self.lang.clone());
+        candidates
+    }
+
+    /// The value of `key` in `section` for this locale, falling back to the untranslated one
+    pub fn get<T: AsRef<str>>(&self, section: &AttrSelector<'_, T>, key: &str) -> Option<String> {
+        self.candidates()
+            .iter()
+            .find_map(|candidate| section.attr_with_param(key, candidate).map(str::to_string))
+            .or_else(|| section.attr(key).map(str::to_string))
+    }
+
+    /// A `;`-separated list such as `Keywords`, translated like `get`
+    pub fn get_list<T: AsRef<str>>(&self, section: &AttrSelector<'_, T>, key: &str) -> Vec<String> {
+        self.get(section, key)
+            .unwrap_or_default()
+            .split(';')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
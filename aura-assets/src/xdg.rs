@@ -0,0 +1,162 @@
+//! Where desktop entries and icons live, following the XDG Base Directory spec.
+//!
+//! `$XDG_DATA_HOME` comes first, then `$XDG_DATA_DIRS`. Flatpak, Snap and NixOS
+//! export their apps to directories that a well set up session already lists
+//! there; they are appended in case it doesn't, e.g. when the dock is started
+//! from a bare X session.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+/// Used when `$XDG_DATA_DIRS` is unset or empty
+const DEFAULT_DATA_DIRS: &str = "/usr/local/share:/usr/share";
+
+/// System-wide directories that package managers export apps to
+const SYSTEM_EXPORT_DIRS: &[&str] = &[
+    "/var/lib/flatpak/exports/share",
+    "/var/lib/snapd/desktop",
+    "/run/current-system/sw/share",
+];
+
+/// The variables the search paths derive from; empty values count as unset
+#[derive(Debug, Clone, Default)]
+pub struct XdgEnv {
+    pub home: Option<PathBuf>,
+    pub user: Option<String>,
+    pub data_home: Option<PathBuf>,
+    pub data_dirs: Option<String>,
+    pub config_home: Option<PathBuf>,
+}
+
+impl XdgEnv {
+    /// Reads `HOME`, `USER`, `XDG_DATA_HOME`, `XDG_DATA_DIRS` and `XDG_CONFIG_HOME`
+    pub fn from_env() -> Self {
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+        Self {
+            home: var("HOME").map(PathBuf::from),
+            user: var("USER"),
+            data_home: var("XDG_DATA_HOME").map(PathBuf::from),
+            data_dirs: var("XDG_DATA_DIRS"),
+            config_home: var("XDG_CONFIG_HOME").map(PathBuf::from),
+        }
+    }
+
+    /// Where user settings live, `~/.config` by default
+    pub fn config_dir(&self) -> Option<PathBuf> {
+        self.config_home.clone().or_else(|| self.home.as_ref().map(|home| home.join(".config")))
+    }
+
+    /// The base data directories in order of precedence, without duplicates
+    pub fn data_dirs(&self) -> Vec<PathBuf> {
+        let data_home = self.data_home.clone()
+            .or_else(|| self.home.as_ref().map(|home| home.join(".local/share")));
+        let data_dirs = self.data_dirs.as_deref().unwrap_or(DEFAULT_DATA_DIRS);
+
+        let mut dirs: Vec<PathBuf> = data_home.into_iter()
+            .chain(data_dirs.split(':').filter(|dir| !dir.is_empty()).map(PathBuf::from))
+            .collect();
+
+        // Per-user exports take precedence over system-wide ones, but not over the session's dirs
+        if let Some(home) = &self.home {
+            dirs.push(home.join(".local/share/flatpak/exports/share"));
+            dirs.push(home.join(".nix-profile/share"));
+        }
+        if let Some(user) = &self.user {
+            dirs.push(PathBuf::from("/etc/profiles/per-user").join(user).join("share"));
+        }
+        dirs.extend(SYSTEM_EXPORT_DIRS.iter().map(PathBuf::from));
+
+        let mut unique = Vec::new();
+        for dir in dirs {
+            // Relative entries are invalid per the spec
+            if dir.is_absolute() && !unique.contains(&dir) {
+                unique.push(dir);
+            }
+        }
+        unique
+    }
+
+    /// The `applications` directories desktop entries are read from
+    pub fn application_dirs(&self) -> Vec<PathBuf> {
+        self.data_dirs().into_iter().map(|dir| dir.join("applications")).collect()
+    }
+
+    /// The directories icon themes are searched in, per the icon theme spec
+    pub fn icon_dirs(&self) -> Vec<PathBuf> {
+        let legacy = self.home.as_ref().map(|home| home.join(".icons"));
+        legacy.into_iter()
+            .chain(self.data_dirs().into_iter().map(|dir| dir.join("icons")))
+            .chain(std::iter::once(PathBuf::from("/usr/share/pixmaps")))
+            .collect()
+    }
+}
+
+/// The desktop environments of the session, most specific first: `$AURA_CURRENT_DESKTOP`
+/// if set (for desktops that don't set the standard variable, or set it to something
+/// entries don't know), otherwise `$XDG_CURRENT_DESKTOP`
+pub fn current_desktops() -> Vec<String> {
+    ["AURA_CURRENT_DESKTOP", "XDG_CURRENT_DESKTOP"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .map(|value| parse_list(&value, ':'))
+        .find(|desktops| !desktops.is_empty())
+        .unwrap_or_default()
+}
+
+/// Whether an entry with these `OnlyShowIn` and `NotShowIn` values belongs in the menus of
+/// `desktops`. Without a known desktop only entries restricted by `OnlyShowIn` are left out.
+pub fn shown_in(only_show_in: Option<&str>, not_show_in: Option<&str>, desktops: &[String]) -> bool {
+    let listed = |value: Option<&str>| {
+        value.is_some_and(|value| {
+            parse_list(value, ';').iter().any(|listed| desktops.iter().any(|desktop| desktop.eq_ignore_ascii_case(listed)))
+        })
+    };
+    if listed(not_show_in) {
+        return false;
+    }
+    only_show_in.is_none() || listed(only_show_in)
+}
+
+fn parse_list(value: &str, separator: char) -> Vec<String> {
+    value.split(separator)
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// `application_dirs` of the current environment
+pub fn application_dirs() -> Vec<PathBuf> {
+    XdgEnv::from_env().application_dirs()
+}
+
+/// `icon_dirs` of the current environment
+pub fn icon_dirs() -> Vec<PathBuf> {
+    XdgEnv::from_env().icon_dirs()
+}
+
+/// The desktop file ID of `path` inside the applications directory `dir`:
+/// its relative path with `/` turned into `-`, e.g. `kde-konsole.desktop`
+pub fn desktop_id(dir: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(dir).ok()?;
+    let parts: Vec<String> = relative.components()
+        .map(|part| part.as_os_str().to_string_lossy().to_string())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join("-"))
+}
+
+/// Every `.desktop` file below `dir`, including vendor subdirectories, with its desktop file ID
+pub fn desktop_files(dir: &Path) -> Vec<(String, PathBuf)> {
+    // Snap and Nix link their entries into place
+    WalkDir::new(dir)
+        .follow_links(true)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| entry.path().extension().and_then(|s| s.to_str()) == Some("desktop"))
+        .filter_map(|entry| Some((desktop_id(dir, entry.path())?, entry.into_path())))
+        .collect()
+}
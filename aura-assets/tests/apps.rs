@@ -0,0 +1,186 @@
+use aura_assets::locale::Locale;
+use aura_assets::index::AppIndex;
+use aura_assets::{read_app, read_apps, read_entries};
+use std::path::PathBuf;
+
+/// Writes `contents` to `name` in a directory of its own for `test`
+fn write_entry(test: &str, name: &str, contents: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("aura-apps-{}-{}", test, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+const BROWSER: &str = "\
+[Desktop Entry]
+Type=Application
+Name=Browser
+Name[de]=Netzbrowser
+GenericName=Web Browser
+GenericName[de]=Webbrowser
+Comment=Browse the web
+Keywords=Internet;WWW;
+Keywords[de]=Internet;Netz;
+Categories=Network;WebBrowser;
+Exec=browser %u
+Icon=browser
+StartupWMClass=BrowserMain
+Actions=new-window;new-private-window;missing;
+
+[Desktop Action new-window]
+Name=New Window
+Name[de]=Neues Fenster
+Exec=browser --new-window
+
+[Desktop Action new-private-window]
+Name=New Private Window
+Name[de_CH]=Neues privates Fenster (CH)
+Name[de]=Neues privates Fenster
+Icon=browser-private
+Exec=browser --private-window
+";
+
+#[test]
+fn matches_locales_from_most_to_least_specific() {
+    assert_eq!(
+        Locale::parse("sr_RS.UTF-8@latin").candidates(),
+        vec!["sr_RS@latin", "sr_RS", "sr@latin", "sr"]
+    );
+    assert_eq!(Locale::parse("de_DE.UTF-8").candidates(), vec!["de_DE", "de"]);
+    assert!(Locale::parse("C.UTF-8").candidates().is_empty());
+    assert!(Locale::parse("POSIX").candidates().is_empty());
+}
+
+#[test]
+fn reads_desktop_actions() {
+    let path = write_entry("actions", "org.example.Browser.desktop", BROWSER);
+
+    let app = read_app(&path, "org.example.Browser.desktop", &Locale::parse("de_CH.UTF-8"), &[]).unwrap();
+    let actions: Vec<(&str, &str, &str)> = app.actions
+        .iter()
+        .map(|action| (action.id.as_str(), action.name.as_str(), action.localized_name.as_str()))
+        .collect();
+    // Listed actions without a group are left out
+    assert_eq!(actions, vec![
+        ("new-window", "New Window", "Neues Fenster"),
+        ("new-private-window", "New Private Window", "Neues privates Fenster (CH)"),
+    ]);
+    assert_eq!(app.actions[0].exec, "browser --new-window");
+    assert_eq!(app.actions[0].icon, None);
+    assert_eq!(app.actions[1].icon.as_deref(), Some("browser-private"));
+
+    // Untranslated without a matching locale
+    let app = read_app(&path, "org.example.Browser.desktop", &Locale::parse("fr_FR"), &[]).unwrap();
+    assert_eq!(app.actions[0].localized_name, "New Window");
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn translates_names_comments_and_keywords() {
+    let path = write_entry("localized", "org.example.Browser.desktop", BROWSER);
+
+    let app = read_app(&path, "org.example.Browser.desktop", &Locale::parse("de_AT.UTF-8"), &[]).unwrap();
+    assert_eq!(app.name, "Browser");
+    assert_eq!(app.localized_name, "Netzbrowser");
+    assert_eq!(app.generic_name.as_deref(), Some("Webbrowser"));
+    // Untranslated keys fall back to the original
+    assert_eq!(app.comment.as_deref(), Some("Browse the web"));
+    assert_eq!(app.keywords, vec!["Internet", "Netz"]);
+
+    let app = read_app(&path, "org.example.Browser.desktop", &Locale::default(), &[]).unwrap();
+    assert_eq!(app.localized_name, "Browser");
+    assert_eq!(app.keywords, vec!["Internet", "WWW"]);
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn searches_names_keywords_and_categories() {
+    let path = write_entry("search", "org.example.Browser.desktop", BROWSER);
+    let app = read_app(&path, "org.example.Browser.desktop", &Locale::parse("de_DE"), &[]).unwrap();
+
+    for query in ["netzb", "BROWSER", "webbrowser", "netz", "network", "  "] {
+        assert!(app.matches(query), "{:?} should match", query);
+    }
+    assert!(!app.matches("editor"));
+    // Only the keywords of the user's locale count
+    assert!(!app.matches("www"));
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn finds_apps_by_window_class() {
+    let path = write_entry("class", "org.example.Browser.desktop", BROWSER);
+    let dir = path.parent().unwrap().to_path_buf();
+    write_entry("class", "kde/konsole.desktop", "[Desktop Entry]\nType=Application\nName=Konsole\nExec=konsole\n");
+    write_entry("class", "helper.desktop", "[Desktop Entry]\nType=Application\nName=Helper\nExec=/usr/libexec/helper-daemon --fork\nNoDisplay=true\n");
+    write_entry("class", "org.example.Flat.desktop", "[Desktop Entry]\nType=Application\nName=Flat\nExec=flatpak run org.example.Flat\n");
+
+    let index = AppIndex::new(read_entries(std::slice::from_ref(&dir), &Locale::default()), &[], "hicolor".to_string());
+    let found = |class: &str, exe: Option<&str>| index.app_for_window(class, exe).map(|app| app.desktop_id.as_str());
+
+    assert_eq!(found("browsermain", None), Some("org.example.Browser.desktop"));
+    assert_eq!(found("org.example.Browser", None), Some("org.example.Browser.desktop"));
+    // The file name, not only the desktop ID, of entries in vendor directories
+    assert_eq!(found("konsole", None), Some("kde-konsole.desktop"));
+    assert_eq!(found("kde-konsole", None), Some("kde-konsole.desktop"));
+    // Unlisted entries still describe their windows
+    assert_eq!(found("Unknown", Some("helper-daemon")), Some("helper.desktop"));
+    assert_eq!(found("Browser", None), Some("org.example.Browser.desktop"));
+    // A launcher's name matches nothing
+    assert_eq!(found("flatpak", Some("flatpak")), None);
+    assert_eq!(found("Editor", None), None);
+
+    assert_eq!(index.by_desktop_id("kde-konsole.desktop").map(|app| app.name.as_str()), Some("Konsole"));
+    assert_eq!(index.by_desktop_id("org.example.browser").map(|app| app.name.as_str()), Some("Browser"));
+    let listed: Vec<&str> = index.apps().map(|app| app.name.as_str()).collect();
+    assert_eq!(listed, vec!["Browser", "Flat", "Konsole"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn lets_earlier_dirs_override_entries_by_desktop_id() {
+    let user = write_entry("override-user", "org.example.Browser.desktop", &BROWSER.replace("Name=Browser", "Name=My Browser"));
+    write_entry("override-user", "org.example.Editor.desktop", "[Desktop Entry]\nType=Application\nName=Editor\nHidden=true\n");
+    let system = write_entry("override-system", "org.example.Browser.desktop", BROWSER);
+    write_entry("override-system", "org.example.Editor.desktop", "[Desktop Entry]\nType=Application\nName=Editor\nExec=editor\n");
+    // Same name, different apps
+    write_entry("override-system", "org.example.Terminal.desktop", "[Desktop Entry]\nType=Application\nName=Terminal\nExec=term\n");
+    write_entry("override-system", "org.other.Terminal.desktop", "[Desktop Entry]\nType=Application\nName=Terminal\nExec=other-term\n");
+
+    let dirs = vec![user.parent().unwrap().to_path_buf(), system.parent().unwrap().to_path_buf()];
+    let apps = read_apps(&dirs, &Locale::default(), &[]);
+    let listed: Vec<(&str, &str)> = apps.iter().map(|app| (app.desktop_id.as_str(), app.name.as_str())).collect();
+    assert_eq!(listed, vec![
+        ("org.example.Browser.desktop", "My Browser"),
+        ("org.example.Terminal.desktop", "Terminal"),
+        ("org.other.Terminal.desktop", "Terminal"),
+    ]);
+
+    for dir in dirs {
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
+
+#[test]
+fn lists_only_apps_for_the_current_desktop() {
+    let dir = write_entry("show-in", "settings.desktop", "[Desktop Entry]\nType=Application\nName=Settings\nExec=settings\nOnlyShowIn=GNOME;Unity;\n");
+    write_entry("show-in", "tray.desktop", "[Desktop Entry]\nType=Application\nName=Tray\nExec=tray\nNotShowIn=KDE;\n");
+    let dirs = vec![dir.parent().unwrap().to_path_buf()];
+
+    let listed = |desktops: &[&str]| -> Vec<String> {
+        let desktops: Vec<String> = desktops.iter().map(|desktop| desktop.to_string()).collect();
+        read_apps(&dirs, &Locale::default(), &desktops).into_iter().map(|app| app.name).collect()
+    };
+    assert_eq!(listed(&["ubuntu", "GNOME"]), vec!["Settings", "Tray"]);
+    assert_eq!(listed(&["KDE"]), Vec::<String>::new());
+    assert_eq!(listed(&["XFCE"]), vec!["Tray"]);
+    assert_eq!(listed(&[]), vec!["Tray"]);
+
+    std::fs::remove_dir_all(&dirs[0]).unwrap();
+}
@@ -0,0 +1,190 @@
+use aura_assets::dbus::{Arg, Bus};
+use aura_assets::launch::Launcher;
+use aura_assets::{AppInfo, DesktopAction};
+use std::io::{BufRead, BufReader, Write};
+use std::net::Shutdown;
+use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+
+/// A private message bus; dropping it stops the daemon
+struct TestBus {
+    process: Child,
+    address: String,
+    dir: PathBuf,
+}
+
+impl TestBus {
+    /// Starts `dbus-daemon`, or returns `None` if it is not installed
+    fn start(test: &str) -> Option<Self> {
+        let dir = std::env::temp_dir().join(format!("aura-dbus-{}-{}", test, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("bus.conf");
+        std::fs::write(&config, format!(r#"<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-Bus Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <type>session</type>
+  <listen>unix:path={}/bus</listen>
+  <policy context="default">
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>
+"#, dir.display())).unwrap();
+
+        let Ok(mut process) = Command::new("dbus-daemon")
+            .arg(format!("--config-file={}", config.display()))
+            .args(["--nofork", "--print-address=1"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            eprintln!("dbus-daemon not found, skipping D-Bus test");
+            std::fs::remove_dir_all(&dir).unwrap();
+            return None;
+        };
+
+        let mut address = String::new();
+        BufReader::new(process.stdout.take().unwrap()).read_line(&mut address).unwrap();
+        Some(Self { process, address: address.trim().to_string(), dir })
+    }
+}
+
+impl Drop for TestBus {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Connects to a socket that authenticates like a bus and answers `Hello` with `reply`
+fn connect_to_fake_bus(test: &str, reply: Vec<u8>) -> Result<(), String> {
+    let dir = std::env::temp_dir().join(format!("aura-dbus-{}-{}", test, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("bus");
+    let listener = UnixListener::bind(&path).unwrap();
+
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = Vec::new();
+        reader.read_until(b'\n', &mut line).unwrap();
+        (&stream).write_all(b"OK 0123456789abcdef0123456789abcdef\r\n").unwrap();
+        reader.read_until(b'\n', &mut line).unwrap();
+        (&stream).write_all(&reply).unwrap();
+        // The client sees the end of the data, but can still finish sending `Hello`
+        stream.shutdown(Shutdown::Write).unwrap();
+        let _ = std::io::copy(&mut reader, &mut std::io::sink());
+    });
+
+    // Dropping the connection lets the server finish
+    let result = Bus::connect(&format!("unix:path={}", path.display())).map(drop).map_err(|e| e.to_string());
+    server.join().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    result
+}
+
+/// The fixed part of a little-endian method return header
+fn reply_header(body_len: u32, fields_len: u32) -> Vec<u8> {
+    let mut header = vec![b'l', 2, 0, 1];
+    header.extend_from_slice(&body_len.to_le_bytes());
+    header.extend_from_slice(&1u32.to_le_bytes());
+    header.extend_from_slice(&fields_len.to_le_bytes());
+    header
+}
+
+fn dbus_app(desktop_id: &str) -> AppInfo {
+    AppInfo {
+        name: "Test".to_string(),
+        localized_name: "Test".to_string(),
+        generic_name: None,
+        comment: None,
+        keywords: Vec::new(),
+        exec: String::new(),
+        icon_name: "test".to_string(),
+        icon_path: None,
+        desktop_file: PathBuf::from("/usr/share/applications").join(desktop_id),
+        desktop_id: desktop_id.to_string(),
+        categories: Vec::new(),
+        terminal: false,
+        working_dir: None,
+        dbus_activatable: true,
+        startup_wm_class: None,
+        no_display: false,
+        only_show_in: None,
+        not_show_in: None,
+        actions: Vec::new(),
+    }
+}
+
+#[test]
+fn calls_methods_and_reports_error_replies() {
+    let Some(test_bus) = TestBus::start("calls") else { return };
+    let mut bus = Bus::connect(&test_bus.address).unwrap();
+
+    bus.call("org.freedesktop.DBus", "/org/freedesktop/DBus", "org.freedesktop.DBus.Peer", "Ping", &[]).unwrap();
+    bus.call("org.freedesktop.DBus", "/org/freedesktop/DBus", "org.freedesktop.DBus", "NameHasOwner", &[Arg::Str("org.aura.Test")]).unwrap();
+
+    let error = bus.call("org.freedesktop.DBus", "/org/freedesktop/DBus", "org.freedesktop.DBus", "GetNameOwner", &[Arg::Str("org.aura.Test")])
+        .unwrap_err()
+        .to_string();
+    assert!(error.starts_with("org.freedesktop.DBus.Error.NameHasNoOwner: "), "{}", error);
+
+    // The connection stays usable after an error
+    bus.call("org.freedesktop.DBus", "/org/freedesktop/DBus", "org.freedesktop.DBus.Peer", "Ping", &[]).unwrap();
+}
+
+#[test]
+fn rejects_unusable_addresses() {
+    assert!(Bus::connect("tcp:host=localhost,port=1").is_err());
+    assert!(Bus::connect("unix:path=/nonexistent/aura/bus").is_err());
+    assert!(Bus::connect("").is_err());
+}
+
+#[test]
+fn reports_failed_activation_to_the_caller() {
+    let Some(test_bus) = TestBus::start("activation") else { return };
+    let launcher = Launcher::with_terminal(None).with_bus_address(&test_bus.address);
+    let app = dbus_app("org.aura.Missing.desktop");
+
+    // Each method is well-formed enough for the bus to route it, and nobody answers
+    let error = launcher.launch(&app, &[]).unwrap_err().to_string();
+    assert!(error.contains("org.freedesktop.DBus.Error.ServiceUnknown"), "{}", error);
+
+    let error = launcher.launch(&app, &["/tmp/notes 1.txt".to_string()]).unwrap_err().to_string();
+    assert!(error.contains("org.freedesktop.DBus.Error.ServiceUnknown"), "{}", error);
+
+    let action = DesktopAction {
+        id: "new-window".to_string(),
+        name: "New Window".to_string(),
+        localized_name: "New Window".to_string(),
+        icon: None,
+        exec: String::new(),
+    };
+    let error = launcher.launch_action(&app, &action, &[]).unwrap_err().to_string();
+    assert!(error.contains("org.freedesktop.DBus.Error.ServiceUnknown"), "{}", error);
+}
+
+#[test]
+fn needs_a_bus_name_as_desktop_id() {
+    let launcher = Launcher::with_terminal(None).with_bus_address("unix:path=/nonexistent/aura/bus");
+    let error = launcher.launch(&dbus_app("firefox.desktop"), &[]).unwrap_err().to_string();
+    assert!(error.contains("firefox is no valid D-Bus name"), "{}", error);
+}
+
+#[test]
+fn rejects_oversized_and_truncated_replies() {
+    // Lengths past the spec's limits fail before anything is allocated for them
+    let error = connect_to_fake_bus("body", reply_header(u32::MAX - 7, 0)).expect_err("An oversized body is an error");
+    assert!(error.contains("too long"), "{}", error);
+    let error = connect_to_fake_bus("fields", reply_header(0, u32::MAX - 7)).expect_err("Oversized fields are an error");
+    assert!(error.contains("too long"), "{}", error);
+
+    // The peer stops in the middle of the header fields
+    let mut reply = reply_header(0, 32);
+    reply.extend_from_slice(&[5, 1, b'u', 0, 1, 0, 0, 0]);
+    assert!(connect_to_fake_bus("truncated", reply).is_err());
+}
@@ -0,0 +1,112 @@
+use aura_assets::exec::{quote_arg, to_exec_value, Exec, LaunchContext};
+use proptest::prelude::*;
+use std::path::PathBuf;
+
+fn expand(value: &str, context: &LaunchContext) -> Vec<Vec<String>> {
+    Exec::parse(value).unwrap().expand(context)
+}
+
+fn argv(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+fn targets(targets: &[&str]) -> LaunchContext {
+    LaunchContext { targets: argv(targets), ..Default::default() }
+}
+
+#[test]
+fn splits_quoted_arguments() {
+    let none = LaunchContext::default();
+    assert_eq!(expand("\"/opt/My App/bin/app\" --new-window", &none), vec![argv(&["/opt/My App/bin/app", "--new-window"])]);
+    assert_eq!(expand("app \"\" last", &none), vec![argv(&["app", "", "last"])]);
+    assert_eq!(expand("  app\t--flag  ", &none), vec![argv(&["app", "--flag"])]);
+}
+
+#[test]
+fn applies_string_escapes_before_quoting() {
+    let none = LaunchContext::default();
+    // `\\$` in the file is `\$` after the string escapes, a literal `$` inside quotes
+    assert_eq!(expand("sh -c \"echo \\\\$HOME\"", &none), vec![argv(&["sh", "-c", "echo $HOME"])]);
+    // A literal backslash inside quotes takes four in the file
+    assert_eq!(expand("printf \"a\\\\\\\\b\"", &none), vec![argv(&["printf", "a\\b"])]);
+    assert_eq!(expand("echo \"say \\\\\"hi\\\\\"\"", &none), vec![argv(&["echo", "say \"hi\""])]);
+    // `\s` is a plain space by the time arguments are split
+    assert_eq!(expand("echo a\\sb", &none), vec![argv(&["echo", "a", "b"])]);
+}
+
+#[test]
+fn expands_file_and_url_codes() {
+    assert_eq!(expand("viewer %f", &targets(&["/tmp/a b.png"])), vec![argv(&["viewer", "/tmp/a b.png"])]);
+    assert_eq!(
+        expand("viewer %F", &targets(&["/tmp/a.png", "file:///tmp/b%20c.png"])),
+        vec![argv(&["viewer", "/tmp/a.png", "/tmp/b c.png"])]
+    );
+    // One instance per file when the app takes a single one
+    assert_eq!(
+        expand("viewer %f", &targets(&["/tmp/a.png", "/tmp/b.png"])),
+        vec![argv(&["viewer", "/tmp/a.png"]), argv(&["viewer", "/tmp/b.png"])]
+    );
+    // Remote URLs are no files
+    assert_eq!(expand("viewer %f", &targets(&["https://example.org/a.png"])), vec![argv(&["viewer"])]);
+    assert_eq!(
+        expand("browser %U", &targets(&["https://example.org", "/tmp/page.html"])),
+        vec![argv(&["browser", "https://example.org", "/tmp/page.html"])]
+    );
+    assert_eq!(expand("browser %u", &LaunchContext::default()), vec![argv(&["browser"])]);
+}
+
+#[test]
+fn expands_entry_codes() {
+    let context = LaunchContext {
+        icon: Some("firefox".to_string()),
+        name: Some("Firefox".to_string()),
+        desktop_file: Some(PathBuf::from("/usr/share/applications/firefox.desktop")),
+        ..Default::default()
+    };
+    assert_eq!(
+        expand("firefox %i --class=%c %k", &context),
+        vec![argv(&["firefox", "--icon", "firefox", "--class=Firefox", "/usr/share/applications/firefox.desktop"])]
+    );
+    assert_eq!(expand("firefox %i %c", &LaunchContext::default()), vec![argv(&["firefox"])]);
+    // Deprecated codes are dropped, `%%` is a literal percent sign
+    assert_eq!(expand("app %d %D %n %N %v %m 100%%", &context), vec![argv(&["app", "100%"])]);
+}
+
+#[test]
+fn rejects_malformed_values() {
+    assert!(Exec::parse("app \"unterminated").is_err());
+    assert!(Exec::parse("app %z").is_err());
+    assert!(Exec::parse("app %").is_err());
+    assert!(Exec::parse("   ").is_err());
+}
+
+#[test]
+fn finds_the_program() {
+    assert_eq!(Exec::parse("\"/opt/My App/app\" %U").unwrap().program().as_deref(), Some("/opt/My App/app"));
+    assert_eq!(Exec::parse("%k").unwrap().program(), None);
+}
+
+#[test]
+fn quotes_only_when_needed() {
+    assert_eq!(quote_arg("--flag"), "--flag");
+    assert_eq!(quote_arg(""), "\"\"");
+    assert_eq!(quote_arg("a b"), "\"a b\"");
+    assert_eq!(quote_arg("$HOME"), "\"\\$HOME\"");
+    assert_eq!(quote_arg("50%"), "50%%");
+}
+
+proptest! {
+    #[test]
+    fn round_trips_arbitrary_arguments(args in prop::collection::vec(any::<String>(), 1..6)) {
+        let value = to_exec_value(&args);
+        let exec = Exec::parse(&value).unwrap();
+        prop_assert_eq!(exec.expand(&LaunchContext::default()), vec![args]);
+    }
+
+    #[test]
+    fn never_panics_on_arbitrary_values(value in any::<String>()) {
+        if let Ok(exec) = Exec::parse(&value) {
+            exec.expand(&targets(&["/tmp/a", "https://example.org"]));
+        }
+    }
+}
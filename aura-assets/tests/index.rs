@@ -0,0 +1,90 @@
+use aura_assets::index::{self, watch_targets, DirWatcher};
+use aura_assets::xdg::XdgEnv;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::mpsc;
+use std::time::Duration;
+
+const CHANGE_TIMEOUT: Duration = Duration::from_secs(5);
+const QUIET_TIMEOUT: Duration = Duration::from_millis(200);
+
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("aura-index-{}-{}", test, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn notices_new_and_removed_entries() {
+    let dir = temp_dir("entries");
+    let mut watcher = DirWatcher::new(std::slice::from_ref(&dir)).unwrap();
+    assert!(!watcher.wait(Some(QUIET_TIMEOUT)).unwrap());
+
+    std::fs::write(dir.join("app.desktop"), "[Desktop Entry]\n").unwrap();
+    assert!(watcher.wait(Some(CHANGE_TIMEOUT)).unwrap());
+
+    std::fs::remove_file(dir.join("app.desktop")).unwrap();
+    assert!(watcher.wait(Some(CHANGE_TIMEOUT)).unwrap());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn notices_missing_directories_appearing() {
+    let dir = temp_dir("missing");
+    let mut watcher = DirWatcher::new(&[dir.join("share/applications")]).unwrap();
+
+    // Only the path towards the missing directory counts
+    std::fs::write(dir.join("recently-used.xbel"), "").unwrap();
+    assert!(!watcher.wait(Some(QUIET_TIMEOUT)).unwrap());
+
+    std::fs::create_dir(dir.join("share")).unwrap();
+    assert!(watcher.wait(Some(CHANGE_TIMEOUT)).unwrap());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn notices_icons_installed_into_a_theme() {
+    let dir = temp_dir("icons");
+    let apps = dir.join("share/icons/hicolor/48x48/apps");
+    std::fs::create_dir_all(&apps).unwrap();
+    let env = XdgEnv {
+        home: None,
+        user: None,
+        data_home: Some(dir.join("share")),
+        data_dirs: Some(dir.join("system").display().to_string()),
+        config_home: Some(dir.join("config")),
+    };
+    let mut watcher = DirWatcher::new(&watch_targets(&env)).unwrap();
+    assert!(!watcher.wait(Some(QUIET_TIMEOUT)).unwrap());
+
+    std::fs::write(apps.join("org.example.App.png"), b"").unwrap();
+    assert!(watcher.wait(Some(CHANGE_TIMEOUT)).unwrap());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn hands_out_an_empty_index_until_the_first_one_is_read() {
+    let (tx, rx) = mpsc::channel();
+    index::subscribe(move |index| tx.send(index.clone()).is_ok());
+
+    // Doesn't wait for the desktop entries to be parsed
+    let was_loaded = index::is_loaded();
+    let first = index::current();
+    if !was_loaded {
+        assert_eq!(first.apps().count(), 0);
+        assert_eq!(first.icon_theme(), "hicolor");
+        let loaded = rx.recv_timeout(CHANGE_TIMEOUT).expect("The first index is announced");
+        assert!(Arc::ptr_eq(&loaded, &index::current()));
+    }
+    assert!(index::is_loaded());
+
+    // Reloads are announced too
+    let reloaded = index::refresh();
+    let announced = std::iter::from_fn(|| rx.recv_timeout(CHANGE_TIMEOUT).ok())
+        .find(|index| Arc::ptr_eq(index, &reloaded));
+    assert!(announced.is_some());
+    assert!(Arc::ptr_eq(&index::current_or_load(), &reloaded));
+}
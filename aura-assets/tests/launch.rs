@@ -0,0 +1,160 @@
+use aura_assets::launch::{dbus_object_path, find_program, spawn_detached, Launcher};
+use aura_assets::{AppInfo, DesktopAction};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+fn app(exec: &str) -> AppInfo {
+    AppInfo {
+        name: "Test".to_string(),
+        localized_name: "Test".to_string(),
+        generic_name: None,
+        comment: None,
+        keywords: Vec::new(),
+        exec: exec.to_string(),
+        icon_name: "test".to_string(),
+        icon_path: None,
+        desktop_file: PathBuf::from("/usr/share/applications/org.example.Test.desktop"),
+        desktop_id: "org.example.Test.desktop".to_string(),
+        categories: Vec::new(),
+        terminal: false,
+        working_dir: None,
+        dbus_activatable: false,
+        startup_wm_class: None,
+        no_display: false,
+        only_show_in: None,
+        not_show_in: None,
+        actions: Vec::new(),
+    }
+}
+
+fn command_line(launcher: &Launcher, app: &AppInfo) -> Vec<String> {
+    let commands = launcher.commands(app, &[]).unwrap();
+    assert_eq!(commands.len(), 1);
+    let command = &commands[0];
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| arg.to_string_lossy().to_string())
+        .collect()
+}
+
+#[test]
+fn runs_terminal_apps_inside_the_terminal() {
+    let launcher = Launcher::with_terminal(Some(vec!["xterm".to_string(), "-e".to_string()]));
+    let htop = AppInfo { terminal: true, ..app("htop --tree") };
+    assert_eq!(command_line(&launcher, &htop), vec!["xterm", "-e", "htop", "--tree"]);
+
+    // Graphical apps are left alone
+    assert_eq!(command_line(&launcher, &app("gedit %F")), vec!["gedit"]);
+
+    let without_terminal = Launcher::with_terminal(None);
+    assert!(without_terminal.commands(&htop, &[]).is_err());
+}
+
+#[test]
+fn runs_actions_like_the_app() {
+    let launcher = Launcher::with_terminal(Some(vec!["xterm".to_string(), "-e".to_string()]));
+    let action = DesktopAction {
+        id: "top".to_string(),
+        name: "Top".to_string(),
+        localized_name: "Top".to_string(),
+        icon: None,
+        exec: "htop --sort-key PERCENT_CPU".to_string(),
+    };
+    let htop = AppInfo { terminal: true, working_dir: Some(PathBuf::from("/tmp")), ..app("htop") };
+    let commands = launcher.action_commands(&htop, &action, &[]).unwrap();
+    let argv: Vec<String> = std::iter::once(commands[0].get_program())
+        .chain(commands[0].get_args())
+        .map(|arg| arg.to_string_lossy().to_string())
+        .collect();
+    assert_eq!(argv, vec!["xterm", "-e", "htop", "--sort-key", "PERCENT_CPU"]);
+    assert_eq!(commands[0].get_current_dir(), Some(Path::new("/tmp")));
+}
+
+#[test]
+fn starts_in_the_entry_path() {
+    let launcher = Launcher::with_terminal(None);
+    let project = AppInfo { working_dir: Some(PathBuf::from("/srv/project")), ..app("make run") };
+    let commands = launcher.commands(&project, &[]).unwrap();
+    assert_eq!(commands[0].get_current_dir(), Some(Path::new("/srv/project")));
+}
+
+#[test]
+fn reports_unlaunchable_entries() {
+    let launcher = Launcher::with_terminal(None);
+    assert!(launcher.launch(&app("\"unterminated"), &[]).is_err());
+    assert!(launcher.launch(&app("/nonexistent/aura-test-program"), &[]).is_err());
+}
+
+#[test]
+fn finds_programs_like_try_exec() {
+    assert!(find_program("sh").is_some());
+    assert!(find_program("/bin/sh").is_some());
+    assert!(find_program("aura-no-such-program").is_none());
+    // Present but not executable
+    let plain = std::env::temp_dir().join(format!("aura-launch-test-{}", std::process::id()));
+    std::fs::write(&plain, "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(&plain, std::fs::Permissions::from_mode(0o644)).unwrap();
+    assert!(find_program(plain.to_str().unwrap()).is_none());
+    std::fs::remove_file(&plain).unwrap();
+}
+
+#[test]
+fn derives_dbus_object_paths() {
+    assert_eq!(dbus_object_path("org.gnome.Nautilus"), "/org/gnome/Nautilus");
+    assert_eq!(dbus_object_path("org.example.my-app"), "/org/example/my_app");
+}
+
+fn wait_for(what: &str, mut condition: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !condition() {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn detaches_logs_and_reaps_children() {
+    let log = std::env::temp_dir().join(format!("aura-launch-log-{}/app.log", std::process::id()));
+    let mut command = Command::new("sh");
+    command.args(["-c", "echo out; echo err >&2; sleep 0.2"]);
+    let pid = spawn_detached(&mut command, Some(&log)).unwrap();
+
+    // Its own session: the session ID (field 6 of stat) is its PID
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap();
+    let fields: Vec<&str> = stat.rsplit_once(')').unwrap().1.split_whitespace().collect();
+    assert_eq!(fields[3], pid.to_string());
+
+    // Reaped children disappear from /proc instead of lingering as zombies
+    wait_for("the child to be reaped", || !Path::new(&format!("/proc/{}", pid)).exists());
+    let output = std::fs::read_to_string(&log).unwrap();
+    assert_eq!(output, "--- sh -c echo out; echo err >&2; sleep 0.2\nout\nerr\n");
+    std::fs::remove_dir_all(log.parent().unwrap()).unwrap();
+}
+
+/// Threads of this process with the given name
+fn threads_named(name: &str) -> usize {
+    std::fs::read_dir("/proc/self/task").unwrap()
+        .filter_map(|task| std::fs::read_to_string(task.ok()?.path().join("comm")).ok())
+        .filter(|comm| comm.trim_end() == name)
+        .count()
+}
+
+#[test]
+fn reaps_every_child_on_one_thread() {
+    let pids: Vec<u32> = (0..8)
+        .map(|i| spawn_detached(Command::new("sleep").arg(format!("0.{}", i)), None).unwrap())
+        .collect();
+    assert_eq!(threads_named("aura-reaper"), 1);
+
+    for pid in pids {
+        wait_for("the child to be reaped", || !Path::new(&format!("/proc/{}", pid)).exists());
+    }
+    assert_eq!(threads_named("aura-reaper"), 1);
+
+    // Still reaping after being idle
+    let pid = spawn_detached(&mut Command::new("true"), None).unwrap();
+    wait_for("the child to be reaped", || !Path::new(&format!("/proc/{}", pid)).exists());
+}
+
@@ -0,0 +1,62 @@
+use aura_assets::xdg::{desktop_files, desktop_id, XdgEnv};
+use std::path::{Path, PathBuf};
+
+fn paths(paths: &[&str]) -> Vec<PathBuf> {
+    paths.iter().map(PathBuf::from).collect()
+}
+
+#[test]
+fn orders_data_dirs_by_precedence() {
+    let env = XdgEnv {
+        home: Some(PathBuf::from("/home/ada")),
+        user: Some("ada".to_string()),
+        data_home: None,
+        data_dirs: Some("/opt/share::relative/share:/usr/share:/var/lib/flatpak/exports/share".to_string()),
+        config_home: None,
+    };
+    assert_eq!(env.data_dirs(), paths(&[
+        "/home/ada/.local/share",
+        "/opt/share",
+        "/usr/share",
+        "/var/lib/flatpak/exports/share",
+        "/home/ada/.local/share/flatpak/exports/share",
+        "/home/ada/.nix-profile/share",
+        "/etc/profiles/per-user/ada/share",
+        "/var/lib/snapd/desktop",
+        "/run/current-system/sw/share",
+    ]));
+}
+
+#[test]
+fn falls_back_to_the_default_data_dirs() {
+    let env = XdgEnv { data_home: Some(PathBuf::from("/data")), ..Default::default() };
+    assert_eq!(env.application_dirs()[..3], paths(&[
+        "/data/applications",
+        "/usr/local/share/applications",
+        "/usr/share/applications",
+    ]));
+    assert_eq!(env.icon_dirs()[0], Path::new("/data/icons"));
+    assert_eq!(env.icon_dirs().last().unwrap(), Path::new("/usr/share/pixmaps"));
+}
+
+#[test]
+fn derives_desktop_ids_from_vendor_subdirectories() {
+    let dir = Path::new("/usr/share/applications");
+    assert_eq!(desktop_id(dir, &dir.join("firefox.desktop")).as_deref(), Some("firefox.desktop"));
+    assert_eq!(desktop_id(dir, &dir.join("kde/konsole.desktop")).as_deref(), Some("kde-konsole.desktop"));
+    assert_eq!(desktop_id(dir, Path::new("/elsewhere/app.desktop")), None);
+}
+
+#[test]
+fn scans_subdirectories_for_desktop_files() {
+    let dir = std::env::temp_dir().join(format!("aura-xdg-test-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("vendor")).unwrap();
+    std::fs::write(dir.join("top.desktop"), "").unwrap();
+    std::fs::write(dir.join("vendor/app.desktop"), "").unwrap();
+    std::fs::write(dir.join("vendor/readme.txt"), "").unwrap();
+
+    let ids: Vec<String> = desktop_files(&dir).into_iter().map(|(id, _)| id).collect();
+    assert_eq!(ids, vec!["top.desktop", "vendor-app.desktop"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -10,6 +10,23 @@ pub struct Atoms {
     pub utf8_string: Atom,
     pub string: Atom,
     pub net_wm_icon: Atom,
+    pub net_wm_name: Atom,
+    pub net_supported: Atom,
+    pub net_supporting_wm_check: Atom,
+    pub net_wm_window_type: Atom,
+    pub net_wm_state: Atom,
+    pub wm_state: Atom,
+    pub wm_client_leader: Atom,
+    pub net_wm_window_type_dialog: Atom,
+    pub net_wm_state_modal: Atom,
+    pub net_wm_state_fullscreen: Atom,
+    pub net_wm_state_hidden: Atom,
+    pub net_wm_state_maximized_vert: Atom,
+    pub net_wm_state_maximized_horz: Atom,
+    pub net_frame_extents: Atom,
+    pub net_wm_pid: Atom,
+    pub wm_protocols: Atom,
+    pub net_wm_ping: Atom,
 }
 
 impl Atoms {
@@ -22,6 +39,23 @@ impl Atoms {
         let utf8_string = conn.intern_atom(false, b"UTF8_STRING")?;
         let string_cookie = conn.intern_atom(false, b"STRING")?;
         let net_wm_icon = conn.intern_atom(false, b"_NET_WM_ICON")?;
+        let net_wm_name = conn.intern_atom(false, b"_NET_WM_NAME")?;
+        let net_supported = conn.intern_atom(false, b"_NET_SUPPORTED")?;
+        let net_supporting_wm_check = conn.intern_atom(false, b"_NET_SUPPORTING_WM_CHECK")?;
+        let net_wm_window_type = conn.intern_atom(false, b"_NET_WM_WINDOW_TYPE")?;
+        let net_wm_state = conn.intern_atom(false, b"_NET_WM_STATE")?;
+        let wm_state = conn.intern_atom(false, b"WM_STATE")?;
+        let wm_client_leader = conn.intern_atom(false, b"WM_CLIENT_LEADER")?;
+        let net_wm_window_type_dialog = conn.intern_atom(false, b"_NET_WM_WINDOW_TYPE_DIALOG")?;
+        let net_wm_state_modal = conn.intern_atom(false, b"_NET_WM_STATE_MODAL")?;
+        let net_wm_state_fullscreen = conn.intern_atom(false, b"_NET_WM_STATE_FULLSCREEN")?;
+        let net_wm_state_hidden = conn.intern_atom(false, b"_NET_WM_STATE_HIDDEN")?;
+        let net_wm_state_maximized_vert = conn.intern_atom(false, b"_NET_WM_STATE_MAXIMIZED_VERT")?;
+        let net_wm_state_maximized_horz = conn.intern_atom(false, b"_NET_WM_STATE_MAXIMIZED_HORZ")?;
+        let net_frame_extents = conn.intern_atom(false, b"_NET_FRAME_EXTENTS")?;
+        let net_wm_pid = conn.intern_atom(false, b"_NET_WM_PID")?;
+        let wm_protocols = conn.intern_atom(false, b"WM_PROTOCOLS")?;
+        let net_wm_ping = conn.intern_atom(false, b"_NET_WM_PING")?;
         
         Ok(Box::new( Self{
             client_list: client_list.reply()?.atom,
@@ -31,6 +65,23 @@ impl Atoms {
             utf8_string: utf8_string.reply()?.atom,
             string: string_cookie.reply()?.atom,
             net_wm_icon: net_wm_icon.reply()?.atom,
+            net_wm_name: net_wm_name.reply()?.atom,
+            net_supported: net_supported.reply()?.atom,
+            net_supporting_wm_check: net_supporting_wm_check.reply()?.atom,
+            net_wm_window_type: net_wm_window_type.reply()?.atom,
+            net_wm_state: net_wm_state.reply()?.atom,
+            wm_state: wm_state.reply()?.atom,
+            wm_client_leader: wm_client_leader.reply()?.atom,
+            net_wm_window_type_dialog: net_wm_window_type_dialog.reply()?.atom,
+            net_wm_state_modal: net_wm_state_modal.reply()?.atom,
+            net_wm_state_fullscreen: net_wm_state_fullscreen.reply()?.atom,
+            net_wm_state_hidden: net_wm_state_hidden.reply()?.atom,
+            net_wm_state_maximized_vert: net_wm_state_maximized_vert.reply()?.atom,
+            net_wm_state_maximized_horz: net_wm_state_maximized_horz.reply()?.atom,
+            net_frame_extents: net_frame_extents.reply()?.atom,
+            net_wm_pid: net_wm_pid.reply()?.atom,
+            wm_protocols: wm_protocols.reply()?.atom,
+            net_wm_ping: net_wm_ping.reply()?.atom,
         }))
     }
 }
\ No newline at end of file
@@ -0,0 +1,170 @@
+use std::error::Error;
+use std::io::{ErrorKind, Write};
+use std::os::unix::net::UnixStream;
+use std::sync::mpsc as std_mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::Stream;
+use tokio_stream::wrappers::ReceiverStream;
+use x11rb::rust_connection::RustConnection;
+
+use crate::filter::should_skip_window;
+use crate::notification_loop::{Command, Control, SensorLoop};
+use crate::{SensorEvent, WindowInfo};
+
+/// Name of the thread `SensorBuilder::build` starts
+pub const SENSOR_THREAD_NAME: &str = "aura-sensor";
+
+/// Predicate deciding which windows are hidden from the event stream (`true` = skip)
+pub type WindowFilter = Arc<dyn Fn(&WindowInfo) -> bool + Send + Sync>;
+
+/// Options controlling what the sensor reports
+#[derive(Clone)]
+pub struct SensorConfig {
+    /// Preferred icon edge length in pixels, used for theme lookups and `_NET_WM_ICON` selection
+    pub icon_size: u32,
+    /// Windows matching this predicate are never reported
+    pub filter: WindowFilter,
+    /// Report `_NET_ACTIVE_WINDOW` changes as `FocusChange`
+    pub track_focus: bool,
+    /// Report title changes of open windows as `WindowUpdate`
+    pub track_titles: bool,
+    /// Number of events buffered before the sensor starts coalescing
+    pub channel_capacity: usize,
+    /// How often to `_NET_WM_PING` windows to detect hung applications, `None` to never ping
+    pub ping_interval: Option<Duration>,
+}
+
+impl Default for SensorConfig {
+    fn default() -> Self {
+        Self {
+            icon_size: 48,
+            filter: Arc::new(should_skip_window),
+            track_focus: true,
+            track_titles: true,
+            channel_capacity: 32,
+            ping_interval: None,
+        }
+    }
+}
+
+/// Configures and starts a sensor that is consumed as an async stream
+///
+/// ```no_run
+/// use aura_core::SensorBuilder;
+///
+/// let (events, handle) = SensorBuilder::new()
+///     .icon_size(64)
+///     .track_titles(false)
+///     .build()
+///     .expect("no X server");
+/// ```
+#[derive(Default)]
+pub struct SensorBuilder {
+    config: SensorConfig,
+}
+
+impl SensorBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn icon_size(mut self, size: u32) -> Self {
+        self.config.icon_size = size;
+        self
+    }
+
+    /// Replaces the default filter (`should_skip_window`)
+    pub fn filter(mut self, filter: impl Fn(&WindowInfo) -> bool + Send + Sync + 'static) -> Self {
+        self.config.filter = Arc::new(filter);
+        self
+    }
+
+    pub fn track_focus(mut self, enabled: bool) -> Self {
+        self.config.track_focus = enabled;
+        self
+    }
+
+    pub fn track_titles(mut self, enabled: bool) -> Self {
+        self.config.track_titles = enabled;
+        self
+    }
+
+    pub fn channel_capacity(mut self, capacity: usize) -> Self {
+        self.config.channel_capacity = capacity.max(1);
+        self
+    }
+
+    pub fn ping_interval(mut self, interval: Option<Duration>) -> Self {
+        self.config.ping_interval = interval;
+        self
+    }
+
+    /// Connects to the X server and starts the sensor thread.
+    ///
+    /// The stream ends when the sensor stops, either through `SensorHandle::stop`
+    /// or because the X connection was lost.
+    pub fn build(self) -> Result<(impl Stream<Item = SensorEvent> + Unpin + Send + 'static, SensorHandle), Box<dyn Error>> {
+        let (conn, screen_num) = RustConnection::connect(None)?;
+        let (tx, rx) = mpsc::channel(self.config.channel_capacity);
+        let (command_tx, command_rx) = std_mpsc::channel();
+        let (wake_tx, wake_rx) = UnixStream::pair()?;
+        wake_rx.set_nonblocking(true)?;
+        wake_tx.set_nonblocking(true)?;
+
+        let control = Control {
+            commands: command_rx,
+            wake: wake_rx,
+        };
+        let sensor = SensorLoop::new(conn, screen_num, self.config, tx, Some(control))?;
+
+        thread::Builder::new()
+            .name(SENSOR_THREAD_NAME.to_string())
+            .spawn(move || {
+                if let Err(e) = sensor.run() {
+                    eprintln!("CRITICAL: Aura Sensor died! Reason: {}", e);
+                }
+            })?;
+
+        let handle = SensorHandle {
+            commands: command_tx,
+            wake: Arc::new(wake_tx),
+        };
+        Ok((ReceiverStream::new(rx), handle))
+    }
+}
+
+/// Controls a sensor started with `SensorBuilder`
+#[derive(Clone)]
+pub struct SensorHandle {
+    commands: std_mpsc::Sender<Command>,
+    wake: Arc<UnixStream>,
+}
+
+impl SensorHandle {
+    /// Re-reads every window and emits a `FullScan` followed by the current focus
+    pub fn resync(&self) -> Result<(), Box<dyn Error>> {
+        self.send(Command::Resync)
+    }
+
+    /// Stops the sensor thread, which ends the event stream
+    pub fn stop(&self) -> Result<(), Box<dyn Error>> {
+        self.send(Command::Stop)
+    }
+
+    /// Swaps the window filter; windows that change visibility are opened or closed accordingly
+    pub fn set_filter(&self, filter: impl Fn(&WindowInfo) -> bool + Send + Sync + 'static) -> Result<(), Box<dyn Error>> {
+        self.send(Command::SetFilter(Arc::new(filter)))
+    }
+
+    fn send(&self, command: Command) -> Result<(), Box<dyn Error>> {
+        self.commands.send(command).map_err(|_| "Sensor is not running")?;
+        // Interrupt the sensor's wait for X events. A full socket already has a wake-up pending.
+        match (&*self.wake).write(&[0]) {
+            Err(e) if e.kind() != ErrorKind::WouldBlock => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt};
+use x11rb::rust_connection::RustConnection;
+
+use crate::atoms::Atoms;
+use crate::utils::{get_string_property, get_u32_vector_property};
+
+/// EWMH features the dock can make use of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Capability {
+    ClientList,
+    ClientListStacking,
+    ActiveWindow,
+    NumberOfDesktops,
+    CurrentDesktop,
+    WmDesktop,
+    WmState,
+    StateAbove,
+    StateSkipTaskbar,
+    StateFullscreen,
+    WmWindowType,
+    FrameExtents,
+    WmPid,
+    WmPing,
+    CloseWindow,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 15] = [
+        Capability::ClientList,
+        Capability::ClientListStacking,
+        Capability::ActiveWindow,
+        Capability::NumberOfDesktops,
+        Capability::CurrentDesktop,
+        Capability::WmDesktop,
+        Capability::WmState,
+        Capability::StateAbove,
+        Capability::StateSkipTaskbar,
+        Capability::StateFullscreen,
+        Capability::WmWindowType,
+        Capability::FrameExtents,
+        Capability::WmPid,
+        Capability::WmPing,
+        Capability::CloseWindow,
+    ];
+
+    /// The atom a window manager lists in `_NET_SUPPORTED` for this feature
+    pub fn atom_name(self) -> &'static str {
+        match self {
+            Capability::ClientList => "_NET_CLIENT_LIST",
+            Capability::ClientListStacking => "_NET_CLIENT_LIST_STACKING",
+            Capability::ActiveWindow => "_NET_ACTIVE_WINDOW",
+            Capability::NumberOfDesktops => "_NET_NUMBER_OF_DESKTOPS",
+            Capability::CurrentDesktop => "_NET_CURRENT_DESKTOP",
+            Capability::WmDesktop => "_NET_WM_DESKTOP",
+            Capability::WmState => "_NET_WM_STATE",
+            Capability::StateAbove => "_NET_WM_STATE_ABOVE",
+            Capability::StateSkipTaskbar => "_NET_WM_STATE_SKIP_TASKBAR",
+            Capability::StateFullscreen => "_NET_WM_STATE_FULLSCREEN",
+            Capability::WmWindowType => "_NET_WM_WINDOW_TYPE",
+            Capability::FrameExtents => "_NET_FRAME_EXTENTS",
+            Capability::WmPid => "_NET_WM_PID",
+            Capability::WmPing => "_NET_WM_PING",
+            Capability::CloseWindow => "_NET_CLOSE_WINDOW",
+        }
+    }
+}
+
+/// The subset of `Capability` the running window manager advertises
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Capabilities {
+    /// Name from the `_NET_SUPPORTING_WM_CHECK` window, if the WM has one
+    pub wm_name: Option<String>,
+    pub supported: BTreeSet<Capability>,
+}
+
+impl Capabilities {
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.supported.contains(&capability)
+    }
+
+    /// Whether the window manager advertises any EWMH support at all
+    pub fn is_ewmh(&self) -> bool {
+        !self.supported.is_empty()
+    }
+
+    /// Connects to the X server and reads the current capabilities
+    pub fn query() -> Result<Self, Box<dyn Error>> {
+        let (conn, screen_num) = RustConnection::connect(None)?;
+        let root = conn.setup().roots[screen_num].root;
+        let atoms = Atoms::new(&conn)?;
+        Self::read(&conn, root, &atoms)
+    }
+
+    pub(crate) fn read(conn: &RustConnection, root: u32, atoms: &Atoms) -> Result<Self, Box<dyn Error>> {
+        let advertised = get_u32_vector_property(conn, root, atoms.net_supported, AtomEnum::ATOM.into())
+            .unwrap_or_default();
+
+        // Intern every known atom in one round trip
+        let cookies = Capability::ALL.iter()
+            .map(|capability| conn.intern_atom(true, capability.atom_name().as_bytes()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut by_atom = HashMap::new();
+        for (capability, cookie) in Capability::ALL.iter().zip(cookies) {
+            let atom = cookie.reply()?.atom;
+            // only_if_exists returns NONE for atoms nobody ever interned
+            if atom != x11rb::NONE {
+                by_atom.insert(atom, *capability);
+            }
+        }
+
+        let supported = advertised.iter()
+            .filter_map(|atom| by_atom.get(atom).cloned())
+            .collect();
+
+        let wm_name = get_u32_vector_property(conn, root, atoms.net_supporting_wm_check, AtomEnum::WINDOW.into())
+            .ok()
+            .and_then(|ids| ids.first().cloned())
+            .and_then(|check| get_string_property(conn, check, atoms.net_wm_name, atoms.utf8_string).ok());
+
+        Ok(Self { wm_name, supported })
+    }
+}
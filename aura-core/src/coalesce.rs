@@ -0,0 +1,135 @@
+use std::collections::VecDeque;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::mpsc::error::TrySendError;
+
+use crate::SensorEvent;
+
+/// How many events may wait for a stalled consumer before they are given up for a `FullScan`
+pub const MAX_PENDING: usize = 256;
+
+/// Outgoing sensor events that have not been delivered yet.
+///
+/// Redundant entries are folded together as they are queued, so a slow
+/// consumer only ever sees the net effect of a burst instead of every step.
+/// Past its limit the queue is emptied and its owner has to queue a `FullScan`.
+#[derive(Debug)]
+pub struct EventQueue {
+    events: VecDeque<SensorEvent>,
+    limit: usize,
+    overflowed: bool,
+}
+
+impl Default for EventQueue {
+    fn default() -> Self {
+        Self::with_limit(MAX_PENDING)
+    }
+}
+
+impl EventQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A queue that overflows once more than `limit` events are pending
+    pub fn with_limit(limit: usize) -> Self {
+        Self { events: VecDeque::new(), limit, overflowed: false }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Whether events were discarded since the last call; the owner then owes a `FullScan`
+    pub fn take_overflow(&mut self) -> bool {
+        std::mem::take(&mut self.overflowed)
+    }
+
+    /// Queues an event, dropping whatever it makes obsolete
+    pub fn push(&mut self, event: SensorEvent) {
+        self.fold(event);
+        if self.events.len() > self.limit {
+            self.events.clear();
+            self.overflowed = true;
+        }
+    }
+
+    fn fold(&mut self, event: SensorEvent) {
+        match &event {
+            // A full scan describes the complete state, nothing before it matters
+            SensorEvent::FullScan(_) => {
+                self.events.clear();
+                self.overflowed = false;
+            }
+
+            // Only the most recent focus is interesting
+            SensorEvent::FocusChange(_) => {
+                self.events.retain(|e| !matches!(e, SensorEvent::FocusChange(_)));
+            }
+
+            // Same for the window manager's capabilities
+            SensorEvent::Capabilities(_) => {
+                self.events.retain(|e| !matches!(e, SensorEvent::Capabilities(_)));
+            }
+
+            // A window that opened and closed before anyone saw it never existed
+            SensorEvent::WindowClose(id) => {
+                // Pending updates for a closing window are pointless either way, and
+                // its focus becomes no focus so the previous one is not left standing
+                self.events.retain(|e| !matches!(e, SensorEvent::WindowUpdate(info) if info.xid == *id));
+                for queued in self.events.iter_mut() {
+                    if let SensorEvent::FocusChange(xid) = queued
+                        && xid == id
+                    {
+                        *xid = 0;
+                    }
+                }
+                let opened = self.events.iter().position(|e| {
+                    matches!(e, SensorEvent::WindowOpen(info) if info.xid == *id)
+                });
+                if let Some(pos) = opened {
+                    self.events.remove(pos);
+                    return;
+                }
+            }
+
+            // Fold updates into an undelivered open, or replace an older update
+            SensorEvent::WindowUpdate(info) => {
+                for queued in self.events.iter_mut() {
+                    match queued {
+                        SensorEvent::WindowOpen(old) if old.xid == info.xid => {
+                            *old = info.clone();
+                            return;
+                        }
+                        SensorEvent::WindowUpdate(old) if old.xid == info.xid => {
+                            *old = info.clone();
+                            return;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            SensorEvent::WindowOpen(_) => {}
+        }
+
+        self.events.push_back(event);
+    }
+
+    /// Hands queued events to the channel without blocking.
+    ///
+    /// Events the channel has no room for stay queued for the next attempt.
+    /// Returns `false` once the receiving side has been dropped.
+    pub fn flush(&mut self, tx: &Sender<SensorEvent>) -> bool {
+        while let Some(event) = self.events.pop_front() {
+            match tx.try_send(event) {
+                Ok(()) => {}
+                Err(TrySendError::Full(event)) => {
+                    self.events.push_front(event);
+                    return true;
+                }
+                Err(TrySendError::Closed(_)) => return false,
+            }
+        }
+        true
+    }
+}
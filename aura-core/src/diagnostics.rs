@@ -0,0 +1,138 @@
+use std::error::Error;
+use std::path::PathBuf;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{Atom, AtomEnum, ConnectionExt};
+use x11rb::rust_connection::RustConnection;
+
+use crate::atoms::Atoms;
+use crate::fallback::scan_client_windows;
+use crate::filter::should_skip_window;
+use crate::notification_loop::fetch_window_info;
+use crate::process::ProcessInfo;
+use crate::utils::{get_string_property, get_u32_vector_property};
+
+use aura_assets::{icon_candidates, DEFAULT_ICON_SIZE};
+
+/// What the window manager advertises and how the dock sees each client window
+#[derive(Debug, Clone)]
+pub struct DiagnosticReport {
+    /// `_NET_WM_NAME` of the `_NET_SUPPORTING_WM_CHECK` window
+    pub wm_name: Option<String>,
+    pub wm_check_window: Option<u32>,
+    /// The check window does not point back to itself, so the WM info is stale
+    pub wm_check_stale: bool,
+    /// Atom names listed in `_NET_SUPPORTED`
+    pub supported: Vec<String>,
+    pub clients: Vec<ClientReport>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClientReport {
+    pub xid: u32,
+    pub title: String,
+    pub instance: String,
+    pub class: String,
+    /// Atom names in `_NET_WM_WINDOW_TYPE`
+    pub window_types: Vec<String>,
+    /// Atom names in `_NET_WM_STATE`
+    pub states: Vec<String>,
+    /// Hidden from the dock by `should_skip_window`
+    pub skipped: bool,
+    /// Icon names tried by `lookup_icon`, in order
+    pub icon_candidates: Vec<String>,
+    pub icon_path: Option<PathBuf>,
+    /// The window provides `_NET_WM_ICON` pixels (only checked when no themed icon was found)
+    pub has_net_wm_icon: bool,
+    /// `_NET_WM_PID` of a local client
+    pub pid: Option<u32>,
+    pub exe: Option<PathBuf>,
+}
+
+/// Queries the X server for everything in `DiagnosticReport`
+pub fn collect() -> Result<DiagnosticReport, Box<dyn Error>> {
+    let (conn, screen_num) = RustConnection::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+    let atoms = Atoms::new(&conn)?;
+
+    let supported_atoms = get_u32_vector_property(&conn, root, atoms.net_supported, AtomEnum::ATOM.into())
+        .unwrap_or_default();
+    let mut supported = atom_names(&conn, &supported_atoms);
+    supported.sort();
+
+    let wm_check_window = get_u32_vector_property(&conn, root, atoms.net_supporting_wm_check, AtomEnum::WINDOW.into())
+        .ok()
+        .and_then(|ids| ids.first().cloned());
+
+    let (wm_name, wm_check_stale) = match wm_check_window {
+        Some(check) => {
+            let points_back = get_u32_vector_property(&conn, check, atoms.net_supporting_wm_check, AtomEnum::WINDOW.into())
+                .map(|ids| ids.first() == Some(&check))
+                .unwrap_or(false);
+            let name = get_string_property(&conn, check, atoms.net_wm_name, atoms.utf8_string).ok();
+            (name, !points_back)
+        }
+        None => (None, false),
+    };
+
+    // Same source of clients as the sensor
+    let client_ids = if supported_atoms.contains(&atoms.client_list) {
+        get_u32_vector_property(&conn, root, atoms.client_list, AtomEnum::WINDOW.into())
+            .unwrap_or_default()
+    } else {
+        scan_client_windows(&conn, root, &atoms).unwrap_or_default()
+    };
+
+    // A one-shot report can wait for the app index the sensor would match windows with
+    aura_assets::index::current_or_load();
+
+    let mut clients = Vec::new();
+    for id in client_ids {
+        let Ok(info) = fetch_window_info(&conn, &atoms, id, u32::from(DEFAULT_ICON_SIZE)) else { continue };
+
+        let raw_class = get_string_property(&conn, id, atoms.wm_class, AtomEnum::STRING.into())
+            .unwrap_or_default();
+        let instance = raw_class.split('\0').next().unwrap_or("").to_string();
+
+        let window_types = get_u32_vector_property(&conn, id, atoms.net_wm_window_type, AtomEnum::ATOM.into())
+            .unwrap_or_default();
+        let states = get_u32_vector_property(&conn, id, atoms.net_wm_state, AtomEnum::ATOM.into())
+            .unwrap_or_default();
+
+        clients.push(ClientReport {
+            xid: id,
+            skipped: should_skip_window(&info),
+            icon_candidates: icon_candidates(&info.class),
+            has_net_wm_icon: info.icon_data.is_some(),
+            window_types: atom_names(&conn, &window_types),
+            states: atom_names(&conn, &states),
+            title: info.title,
+            instance,
+            class: info.class,
+            icon_path: info.icon_path,
+            pid: info.pid,
+            exe: info.pid.and_then(|pid| ProcessInfo::read(pid).ok()).and_then(|process| process.exe),
+        });
+    }
+
+    Ok(DiagnosticReport {
+        wm_name,
+        wm_check_window,
+        wm_check_stale,
+        supported,
+        clients,
+    })
+}
+
+/// Resolves atoms to their names, sending all requests before waiting for replies
+fn atom_names(conn: &RustConnection, atoms: &[Atom]) -> Vec<String> {
+    let cookies: Vec<_> = atoms.iter().map(|&atom| conn.get_atom_name(atom)).collect();
+    cookies.into_iter()
+        .zip(atoms)
+        .map(|(cookie, atom)| {
+            cookie.ok()
+                .and_then(|cookie| cookie.reply().ok())
+                .map(|reply| String::from_utf8_lossy(&reply.name).to_string())
+                .unwrap_or_else(|| format!("<atom {}>", atom))
+        })
+        .collect()
+}
@@ -0,0 +1,85 @@
+//! Client discovery for window managers that don't maintain `_NET_CLIENT_LIST`.
+//!
+//! ICCCM window managers put `WM_STATE` on every client they manage, either on the
+//! top-level window itself or on a child of the frame they reparented it into.
+
+use std::error::Error;
+use x11rb::protocol::xproto::{ChangeWindowAttributesAux, ConnectionExt, EventMask, MapState};
+use x11rb::rust_connection::RustConnection;
+
+use crate::atoms::Atoms;
+
+/// `WM_STATE` values from ICCCM 4.1.3.1
+const WITHDRAWN_STATE: u32 = 0;
+const ICONIC_STATE: u32 = 3;
+
+/// How deep to look below a top-level window for the client (frame → client is usually 1-2)
+const MAX_FRAME_DEPTH: u32 = 3;
+
+/// Finds the managed client windows, bottom to top in stacking order.
+///
+/// A client counts while its top-level is mapped, or while it is iconified.
+/// Top-level windows without a client get `PROPERTY_CHANGE` selected, so the
+/// sensor notices when the window manager sets `WM_STATE` on them later.
+pub(crate) fn scan_client_windows(conn: &RustConnection, root: u32, atoms: &Atoms) -> Result<Vec<u32>, Box<dyn Error>> {
+    let top_levels = conn.query_tree(root)?.reply()?.children;
+
+    // Pipeline the attribute requests for all top-level windows
+    let cookies = top_levels.iter()
+        .map(|&window| conn.get_window_attributes(window))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut clients = Vec::new();
+    for (&top_level, cookie) in top_levels.iter().zip(cookies) {
+        // The window may be gone already
+        let Ok(attributes) = cookie.reply() else { continue };
+        if attributes.override_redirect {
+            continue;
+        }
+
+        match find_client(conn, atoms, top_level, MAX_FRAME_DEPTH) {
+            Some((client, state)) => {
+                let mapped = attributes.map_state != MapState::UNMAPPED;
+                if state != WITHDRAWN_STATE && (mapped || state == ICONIC_STATE) {
+                    clients.push(client);
+                }
+            }
+            None => watch_unmanaged(conn, top_level),
+        }
+    }
+    Ok(clients)
+}
+
+/// Returns the first window at or below `window` carrying `WM_STATE`, with its state
+fn find_client(conn: &RustConnection, atoms: &Atoms, window: u32, depth: u32) -> Option<(u32, u32)> {
+    if let Some(state) = wm_state(conn, atoms, window) {
+        return Some((window, state));
+    }
+    if depth == 0 {
+        return None;
+    }
+
+    let children = conn.query_tree(window).ok()?.reply().ok()?.children;
+    // Frames stack the client above decorations, so search topmost first
+    children.iter().rev().find_map(|&child| find_client(conn, atoms, child, depth - 1))
+}
+
+fn wm_state(conn: &RustConnection, atoms: &Atoms, window: u32) -> Option<u32> {
+    let reply = conn.get_property(false, window, atoms.wm_state, atoms.wm_state, 0, 2).ok()?.reply().ok()?;
+    if reply.type_ != atoms.wm_state {
+        return None;
+    }
+    reply.value32()?.next()
+}
+
+/// Watches a window and its direct children (a frame's client) for `WM_STATE` appearing
+fn watch_unmanaged(conn: &RustConnection, top_level: u32) {
+    let aux = ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE);
+    let _ = conn.change_window_attributes(top_level, &aux);
+
+    if let Ok(Ok(tree)) = conn.query_tree(top_level).map(|cookie| cookie.reply()) {
+        for child in tree.children {
+            let _ = conn.change_window_attributes(child, &aux);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Folds dialogs and other secondary windows under the window that owns them.
+//!
+//! A window is owned by its `WM_TRANSIENT_FOR` parent when that is a known window.
+//! Dialogs transient for the root (or for nothing) fall back to the first ordinary
+//! window sharing their client leader. Everything else is a dock item of its own.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{SensorEvent, WindowInfo};
+
+/// The open windows, remembering the order they appeared in and were focused in
+#[derive(Debug, Clone, Default)]
+pub struct WindowGroups {
+    windows: HashMap<u32, WindowInfo>,
+    order: Vec<u32>,
+    // items by focus, most recent first
+    recent: Vec<u32>,
+}
+
+impl WindowGroups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the model from a sensor event; returns `false` for events without window changes
+    pub fn apply(&mut self, event: &SensorEvent) -> bool {
+        match event {
+            SensorEvent::FullScan(windows) => {
+                // Keep the order and focus history of windows that survive the scan
+                let scanned: HashSet<u32> = windows.iter().map(|info| info.xid).collect();
+                self.order.retain(|id| scanned.contains(id));
+                self.recent.retain(|id| scanned.contains(id));
+                self.windows.clear();
+                for info in windows {
+                    self.insert(info.clone());
+                }
+            }
+            SensorEvent::WindowOpen(info) | SensorEvent::WindowUpdate(info) => self.insert(info.clone()),
+            SensorEvent::WindowClose(id) => self.remove(*id),
+            SensorEvent::FocusChange(id) => {
+                self.focus(*id);
+                return false;
+            }
+            SensorEvent::Capabilities(_) => return false,
+        }
+        true
+    }
+
+    /// Adds a window or replaces its previous state
+    pub fn insert(&mut self, info: WindowInfo) {
+        if !self.windows.contains_key(&info.xid) && !self.order.contains(&info.xid) {
+            self.order.push(info.xid);
+        }
+        self.windows.insert(info.xid, info);
+    }
+
+    pub fn remove(&mut self, xid: u32) {
+        self.windows.remove(&xid);
+        self.order.retain(|&id| id != xid);
+        self.recent.retain(|&id| id != xid);
+    }
+
+    /// Records that `xid` got the focus, moving its item to the front of `recent_items`
+    pub fn focus(&mut self, xid: u32) {
+        if !self.windows.contains_key(&xid) {
+            return;
+        }
+        let item = self.item_for(xid);
+        self.recent.retain(|&id| id != item);
+        self.recent.insert(0, item);
+    }
+
+    pub fn get(&self, xid: u32) -> Option<&WindowInfo> {
+        self.windows.get(&xid)
+    }
+
+    /// Every window, folded or not, oldest first
+    pub fn windows(&self) -> impl Iterator<Item = &WindowInfo> {
+        self.ordered()
+    }
+
+    /// Windows that get their own dock item, oldest first
+    pub fn items(&self) -> Vec<&WindowInfo> {
+        self.ordered()
+            .filter(|info| self.owner(info.xid).is_none())
+            .collect()
+    }
+
+    /// Windows with their own dock item, most recently focused first;
+    /// items never focused follow in the order they appeared
+    pub fn recent_items(&self) -> Vec<&WindowInfo> {
+        let items = self.items();
+        let mut recent: Vec<&WindowInfo> = self.recent.iter()
+            .filter_map(|id| items.iter().find(|info| info.xid == *id).copied())
+            .collect();
+        recent.extend(items.iter().filter(|info| !self.recent.contains(&info.xid)));
+        recent
+    }
+
+    /// Windows folded under `owner`, oldest first
+    pub fn children(&self, owner: u32) -> Vec<&WindowInfo> {
+        self.ordered()
+            .filter(|info| self.owner(info.xid) == Some(owner))
+            .collect()
+    }
+
+    /// The item a window is folded under, or `None` if it is an item itself
+    pub fn owner(&self, xid: u32) -> Option<u32> {
+        let mut current = xid;
+        let mut seen = HashSet::from([xid]);
+        while let Some(parent) = self.parent(current) {
+            // Broken clients can build transient cycles, show those windows as items
+            if !seen.insert(parent) {
+                return None;
+            }
+            current = parent;
+        }
+        (current != xid).then_some(current)
+    }
+
+    /// The item representing `xid` in the dock, which is the window itself when it isn't folded
+    pub fn item_for(&self, xid: u32) -> u32 {
+        self.owner(xid).unwrap_or(xid)
+    }
+
+    /// The window to focus when the item `xid` is clicked: its newest modal dialog, if any
+    pub fn activation_target(&self, xid: u32) -> u32 {
+        self.children(xid)
+            .iter()
+            .rev()
+            .find(|info| info.is_modal)
+            .map(|info| info.xid)
+            .unwrap_or(xid)
+    }
+
+    fn ordered(&self) -> impl Iterator<Item = &WindowInfo> {
+        self.order.iter().filter_map(|id| self.windows.get(id))
+    }
+
+    /// The window `xid` directly belongs to
+    fn parent(&self, xid: u32) -> Option<u32> {
+        let info = self.windows.get(&xid)?;
+
+        if let Some(parent) = info.transient_for
+            && parent != xid
+            && self.windows.contains_key(&parent)
+        {
+            return Some(parent);
+        }
+
+        // Group dialogs belong to the application's main window
+        if !(info.is_dialog || info.transient_for.is_some()) {
+            return None;
+        }
+        let leader = info.leader?;
+        self.ordered()
+            .find(|other| {
+                other.xid != xid
+                    && other.leader == Some(leader)
+                    && !other.is_dialog
+                    && other.transient_for.is_none()
+            })
+            .map(|other| other.xid)
+    }
+}
@@ -0,0 +1,442 @@
+//! Global keyboard shortcuts, grabbed on the root window with `XGrabKey`.
+//!
+//! Keysyms are mapped to keycodes through the XKB keyboard map, so a key is found in
+//! any of the configured layouts, and through the core mapping (first layout only)
+//! on servers without XKB. Every grab is repeated with NumLock and CapsLock set so
+//! the shortcuts keep working while either lock is on.
+//!
+//! A hotkey marked `held` (like Alt+Tab) grabs the whole keyboard while its
+//! modifiers stay down, so the listener sees them being released.
+
+use std::error::Error;
+use std::fmt;
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::Stream;
+use tokio_stream::wrappers::ReceiverStream;
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::Event;
+use x11rb::protocol::xkb::{self, ConnectionExt as _};
+use x11rb::protocol::xproto::{ConnectionExt, GrabMode, GrabStatus, Keycode, KeyButMask, ModMask};
+use x11rb::rust_connection::RustConnection;
+
+use crate::utils::wait_for_event_timeout;
+
+/// How often the listener checks whether the receiving side is gone
+const CLOSE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+const NUM_LOCK_KEYSYM: u32 = 0xff7f;
+const ESCAPE_KEYSYM: u32 = 0xff1b;
+
+/// Named keysyms accepted by `Hotkey::parse` besides letters, digits and F1-F35
+const KEYSYM_NAMES: &[(&str, u32)] = &[
+    ("space", 0x0020),
+    ("Tab", 0xff09),
+    ("Return", 0xff0d),
+    ("Escape", 0xff1b),
+    ("BackSpace", 0xff08),
+    ("Delete", 0xffff),
+    ("Home", 0xff50),
+    ("End", 0xff57),
+    ("Left", 0xff51),
+    ("Up", 0xff52),
+    ("Right", 0xff53),
+    ("Down", 0xff54),
+    ("Print", 0xff61),
+    ("grave", 0x0060),
+    ("minus", 0x002d),
+    ("equal", 0x003d),
+    ("comma", 0x002c),
+    ("period", 0x002e),
+    ("slash", 0x002f),
+    ("semicolon", 0x003b),
+];
+
+/// A key combination such as Super+1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hotkey {
+    pub modifiers: ModMask,
+    pub keysym: u32,
+    /// Report `HoldEnded` once the modifiers are released, see `held`
+    pub hold: bool,
+}
+
+impl Hotkey {
+    pub fn new(modifiers: ModMask, keysym: u32) -> Self {
+        Self { modifiers, keysym, hold: false }
+    }
+
+    /// Keeps the keyboard grabbed after a press until the modifiers are released,
+    /// for switchers that cycle while e.g. Alt is held
+    pub fn held(mut self) -> Self {
+        self.hold = true;
+        self
+    }
+
+    /// Parses `Modifier+...+Key`, e.g. `Super+1`, `Ctrl+Alt+space` or `Super+F12`
+    pub fn parse(spec: &str) -> Result<Self, Box<dyn Error>> {
+        let mut parts: Vec<&str> = spec.split('+').map(str::trim).collect();
+        let key = parts.pop().filter(|key| !key.is_empty())
+            .ok_or_else(|| format!("Hotkey '{}' has no key", spec))?;
+
+        let mut modifiers = ModMask::from(0u16);
+        for part in parts {
+            modifiers |= match part.to_lowercase().as_str() {
+                "super" | "mod4" | "win" => ModMask::M4,
+                "alt" | "mod1" => ModMask::M1,
+                "ctrl" | "control" => ModMask::CONTROL,
+                "shift" => ModMask::SHIFT,
+                _ => return Err(format!("Unknown modifier '{}' in hotkey '{}'", part, spec).into()),
+            };
+        }
+
+        let keysym = keysym_from_name(key)
+            .ok_or_else(|| format!("Unknown key '{}' in hotkey '{}'", key, spec))?;
+        Ok(Self::new(modifiers, keysym))
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (mask, name) in [(ModMask::CONTROL, "Ctrl"), (ModMask::M1, "Alt"), (ModMask::SHIFT, "Shift"), (ModMask::M4, "Super")] {
+            if self.modifiers.contains(mask) {
+                write!(f, "{}+", name)?;
+            }
+        }
+        match keysym_name(self.keysym) {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "0x{:x}", self.keysym),
+        }
+    }
+}
+
+/// A grabbed hotkey was pressed or released; carries its index in the list given to `listen`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyEvent {
+    Pressed(usize),
+    Released(usize),
+    /// The modifiers of a held hotkey were released
+    HoldEnded(usize),
+    /// Escape was pressed while a hotkey was held
+    HoldCancelled(usize),
+}
+
+/// Grabs the hotkeys and reports them until the returned stream is dropped.
+///
+/// Combinations another client already grabbed are reported on stderr and skipped.
+pub fn listen(hotkeys: Vec<Hotkey>) -> Result<impl Stream<Item = HotkeyEvent> + Unpin + Send + 'static, Box<dyn Error>> {
+    let (conn, screen_num) = RustConnection::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+    let mut grabber = Grabber::new(conn, root, hotkeys)?;
+    grabber.grab_all()?;
+
+    let (tx, rx) = mpsc::channel(16);
+    thread::spawn(move || {
+        if let Err(e) = grabber.run(&tx) {
+            eprintln!("Hotkey listener stopped: {}", e);
+        }
+        grabber.ungrab_all();
+    });
+
+    Ok(ReceiverStream::new(rx))
+}
+
+/// Keycodes with the keysyms they produce
+type KeyMap = Vec<(Keycode, Vec<u32>)>;
+
+struct Grabber {
+    conn: RustConnection,
+    root: u32,
+    hotkeys: Vec<Hotkey>,
+    // whether the server has XKB, which then provides the keyboard map
+    xkb: bool,
+    // the modifier NumLock is mapped to, usually Mod2
+    num_lock: ModMask,
+    // (keycode, modifiers without lock variants, hotkey index)
+    grabs: Vec<(Keycode, ModMask, usize)>,
+    // which modifier each modifier key sets
+    modifier_keys: Vec<(Keycode, ModMask)>,
+    escape_keys: Vec<Keycode>,
+    // the held hotkey while the keyboard is grabbed
+    holding: Option<usize>,
+}
+
+impl Grabber {
+    fn new(conn: RustConnection, root: u32, hotkeys: Vec<Hotkey>) -> Result<Self, Box<dyn Error>> {
+        // Without XKB key repeat shows up as release/press pairs; hotkeys work regardless
+        let xkb = conn.extension_information(xkb::X11_EXTENSION_NAME)?.is_some()
+            && conn.xkb_use_extension(1, 0)?.reply()?.supported;
+        if xkb {
+            let device = xkb::ID::USE_CORE_KBD.into();
+            let repeat = xkb::PerClientFlag::DETECTABLE_AUTO_REPEAT;
+            conn.xkb_per_client_flags(device, repeat, repeat, 0u32.into(), 0u32.into(), 0u32.into())?.reply()?;
+            // Layout switches send these, the keycodes of our keysyms may change
+            let changes = xkb::EventType::NEW_KEYBOARD_NOTIFY | xkb::EventType::MAP_NOTIFY;
+            conn.xkb_select_events(device, 0u16.into(), changes, 0u16.into(), 0u16.into(), &xkb::SelectEventsAux::new())?;
+        }
+
+        Ok(Self {
+            conn,
+            root,
+            hotkeys,
+            xkb,
+            num_lock: ModMask::from(0u16),
+            grabs: Vec::new(),
+            modifier_keys: Vec::new(),
+            escape_keys: Vec::new(),
+            holding: None,
+        })
+    }
+
+    fn run(&mut self, tx: &mpsc::Sender<HotkeyEvent>) -> Result<(), Box<dyn Error>> {
+        while !tx.is_closed() {
+            let Some(event) = wait_for_event_timeout(&self.conn, &[], Some(CLOSE_CHECK_INTERVAL))? else {
+                continue;
+            };
+
+            let mut hotkey_events = Vec::new();
+            match event {
+                Event::KeyPress(e) => {
+                    if let Some(index) = self.holding
+                        && self.escape_keys.contains(&e.detail)
+                    {
+                        self.end_hold();
+                        hotkey_events.push(HotkeyEvent::HoldCancelled(index));
+                    } else if let Some(index) = self.find(e.detail, e.state) {
+                        hotkey_events.push(HotkeyEvent::Pressed(index));
+                        if self.hotkeys[index].hold && self.holding.is_none() && !self.start_hold(index)? {
+                            // The modifiers are already up (or the grab failed): a quick tap
+                            hotkey_events.push(HotkeyEvent::HoldEnded(index));
+                        }
+                    }
+                }
+                Event::KeyRelease(e) => {
+                    if let Some(index) = self.find(e.detail, e.state) {
+                        hotkey_events.push(HotkeyEvent::Released(index));
+                    }
+                    if let Some(index) = self.holding
+                        && self.releases_hold(index, e.detail, e.state)
+                    {
+                        self.end_hold();
+                        hotkey_events.push(HotkeyEvent::HoldEnded(index));
+                    }
+                }
+                Event::MappingNotify(_) | Event::XkbNewKeyboardNotify(_) | Event::XkbMapNotify(_) => {
+                    self.ungrab_all();
+                    // Keep the listener alive, the next mapping change retries
+                    if let Err(e) = self.grab_all() {
+                        eprintln!("Warning: could not grab hotkeys after a keyboard change: {}", e);
+                    }
+                }
+                _ => {}
+            }
+
+            for hotkey_event in hotkey_events {
+                if tx.blocking_send(hotkey_event).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Grabs the keyboard for a held hotkey; returns `false` if its modifiers are no longer down
+    fn start_hold(&mut self, index: usize) -> Result<bool, Box<dyn Error>> {
+        let modifiers = u16::from(self.hotkeys[index].modifiers);
+        let grab = self.conn.grab_keyboard(false, self.root, x11rb::CURRENT_TIME, GrabMode::ASYNC, GrabMode::ASYNC)?.reply()?;
+        if grab.status != GrabStatus::SUCCESS {
+            return Ok(false);
+        }
+
+        // The modifiers may have been released before the grab took effect
+        let pointer = self.conn.query_pointer(self.root)?.reply()?;
+        if u16::from(pointer.mask) & modifiers == 0 {
+            self.conn.ungrab_keyboard(x11rb::CURRENT_TIME)?;
+            self.conn.flush()?;
+            return Ok(false);
+        }
+
+        self.holding = Some(index);
+        Ok(true)
+    }
+
+    /// Whether releasing `keycode` leaves none of the held hotkey's modifiers down
+    fn releases_hold(&self, index: usize, keycode: Keycode, state: KeyButMask) -> bool {
+        let Some(released) = self.modifier_keys.iter()
+            .find(|(code, _)| *code == keycode)
+            .map(|(_, modifier)| u16::from(*modifier))
+        else {
+            return false;
+        };
+        // `state` is from just before the release
+        let held = u16::from(self.hotkeys[index].modifiers);
+        u16::from(state) & held & !released == 0
+    }
+
+    fn end_hold(&mut self) {
+        self.holding = None;
+        let _ = self.conn.ungrab_keyboard(x11rb::CURRENT_TIME);
+        let _ = self.conn.flush();
+    }
+
+    /// The hotkey for a key event, ignoring lock modifiers and mouse buttons
+    fn find(&self, keycode: Keycode, state: KeyButMask) -> Option<usize> {
+        let relevant = u16::from(ModMask::SHIFT | ModMask::CONTROL | ModMask::M1 | ModMask::M3 | ModMask::M4 | ModMask::M5)
+            & !u16::from(self.num_lock);
+        let modifiers = ModMask::from(u16::from(state) & relevant);
+
+        self.grabs.iter()
+            .find(|(code, mods, _)| *code == keycode && *mods == modifiers)
+            .map(|(_, _, index)| *index)
+    }
+
+    /// The keysyms each keycode produces without modifiers or with Shift, in every layout
+    fn base_keysyms(&self) -> Result<KeyMap, Box<dyn Error>> {
+        let setup = self.conn.setup();
+        let (min, max) = (setup.min_keycode, setup.max_keycode);
+
+        if !self.xkb {
+            let mapping = self.conn.get_keyboard_mapping(min, max - min + 1)?.reply()?;
+            let per_keycode = usize::from(mapping.keysyms_per_keycode).max(1);
+            return Ok(mapping.keysyms.chunks(per_keycode)
+                .enumerate()
+                .map(|(i, syms)| (min + i as u8, syms.iter().take(2).copied().collect()))
+                .collect());
+        }
+
+        let device = xkb::ID::USE_CORE_KBD.into();
+        let map = self.conn.xkb_get_map(
+            device, xkb::MapPart::KEY_SYMS, 0u16.into(),
+            0, 0, min, max - min + 1, 0, 0, 0, 0, 0u16.into(), 0, 0, 0, 0, 0, 0,
+        )?.reply()?;
+        let first = map.first_key_sym;
+        Ok(map.map.syms_rtrn.unwrap_or_default().into_iter()
+            .enumerate()
+            .map(|(i, key)| {
+                // `syms` holds `width` levels for each group
+                let groups = usize::from(key.group_info & 0x0f);
+                let width = usize::from(key.width);
+                let syms = (0..groups)
+                    .flat_map(|group| (0..width.min(2)).map(move |level| group * width + level))
+                    .filter_map(|at| key.syms.get(at).copied())
+                    .collect();
+                (first + i as u8, syms)
+            })
+            .collect())
+    }
+
+    fn grab_all(&mut self) -> Result<(), Box<dyn Error>> {
+        let keysyms = self.base_keysyms()?;
+        let keycodes_for = |keysym: u32| -> Vec<Keycode> {
+            keysyms.iter()
+                .filter(|(_, syms)| syms.contains(&keysym))
+                .map(|(keycode, _)| *keycode)
+                .collect()
+        };
+
+        let modifier_map = self.conn.get_modifier_mapping()?.reply()?;
+        let num_lock_codes = keycodes_for(NUM_LOCK_KEYSYM);
+        let per_modifier = usize::from(modifier_map.keycodes_per_modifier()).max(1);
+        self.num_lock = modifier_map.keycodes.chunks(per_modifier)
+            .position(|codes| codes.iter().any(|code| num_lock_codes.contains(code)))
+            .map(|index| ModMask::from(1u16 << index))
+            .unwrap_or(ModMask::from(0u16));
+        self.modifier_keys = modifier_map.keycodes.chunks(per_modifier)
+            .enumerate()
+            .flat_map(|(index, codes)| {
+                codes.iter()
+                    .filter(|&&code| code != 0)
+                    .map(move |&code| (code, ModMask::from(1u16 << index)))
+            })
+            .collect();
+        self.escape_keys = keycodes_for(ESCAPE_KEYSYM);
+
+        let lock_variants = [
+            ModMask::from(0u16),
+            ModMask::LOCK,
+            self.num_lock,
+            ModMask::LOCK | self.num_lock,
+        ];
+
+        for (index, hotkey) in self.hotkeys.iter().enumerate() {
+            let keycodes = keycodes_for(hotkey.keysym);
+            if keycodes.is_empty() {
+                eprintln!("Warning: hotkey {} is not on the current keyboard layout", hotkey);
+                continue;
+            }
+
+            for keycode in keycodes {
+                let mut grabbed = true;
+                for extra in lock_variants {
+                    let cookie = self.conn.grab_key(
+                        false,
+                        self.root,
+                        hotkey.modifiers | extra,
+                        keycode,
+                        GrabMode::ASYNC,
+                        GrabMode::ASYNC,
+                    )?;
+                    // BadAccess when another client owns the combination
+                    if cookie.check().is_err() {
+                        grabbed = false;
+                    }
+                }
+                if grabbed {
+                    self.grabs.push((keycode, hotkey.modifiers, index));
+                } else {
+                    eprintln!("Warning: hotkey {} is already taken by another application", hotkey);
+                }
+            }
+        }
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    fn ungrab_all(&mut self) {
+        let lock_variants = [
+            ModMask::from(0u16),
+            ModMask::LOCK,
+            self.num_lock,
+            ModMask::LOCK | self.num_lock,
+        ];
+        for (keycode, modifiers, _) in self.grabs.drain(..) {
+            for extra in lock_variants {
+                let _ = self.conn.ungrab_key(keycode, self.root, modifiers | extra);
+            }
+        }
+        let _ = self.conn.flush();
+    }
+}
+
+fn keysym_from_name(name: &str) -> Option<u32> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        // Latin-1 keysyms equal their code point; letters are grabbed by their lowercase form
+        if c.is_ascii_alphanumeric() || c.is_ascii_punctuation() {
+            return Some(u32::from(c.to_ascii_lowercase()));
+        }
+    }
+
+    if let Some(number) = name.strip_prefix('F').and_then(|n| n.parse::<u32>().ok())
+        && (1..=35).contains(&number)
+    {
+        return Some(0xffbe + number - 1);
+    }
+
+    KEYSYM_NAMES.iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(name))
+        .map(|(_, keysym)| *keysym)
+}
+
+fn keysym_name(keysym: u32) -> Option<String> {
+    if let Some((name, _)) = KEYSYM_NAMES.iter().find(|(_, known)| *known == keysym) {
+        return Some(name.to_string());
+    }
+    if (0xffbe..0xffbe + 35).contains(&keysym) {
+        return Some(format!("F{}", keysym - 0xffbe + 1));
+    }
+    char::from_u32(keysym)
+        .filter(|c| c.is_ascii_graphic())
+        .map(|c| c.to_ascii_uppercase().to_string())
+}
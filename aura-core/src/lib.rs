@@ -3,6 +3,22 @@ pub mod atoms;
 pub mod utils;
 pub mod notification_loop;
 pub mod filter;
+pub mod coalesce;
+pub mod builder;
+pub mod diagnostics;
+pub mod capabilities;
+pub mod fallback;
+pub mod groups;
+pub mod process;
+pub mod ping;
+pub mod hotkeys;
+#[cfg(feature = "serde")]
+pub mod schema;
+#[cfg(feature = "serde")]
+pub mod record;
+
+pub use builder::{SensorBuilder, SensorConfig, SensorHandle, WindowFilter};
+pub use capabilities::{Capabilities, Capability};
 
 use tokio::sync::mpsc;
 use std::thread;
@@ -10,16 +26,66 @@ use std::path::PathBuf;
 use std::error::Error;
 use x11rb::rust_connection::RustConnection;
 use x11rb::connection::Connection;
-use x11rb::protocol::xproto::{ConnectionExt, EventMask, ClientMessageEvent};
+use x11rb::protocol::xproto::{
+    ClientMessageEvent, ConfigureWindowAux, ConnectionExt, EventMask, InputFocus, StackMode,
+};
 
-#[derive(Debug, Clone)]
+/// Serialized as PNG, see `schema`
+#[derive(Debug, Clone, PartialEq)]
 pub struct RawIcon {
     pub width: u32,
     pub height: u32,
     pub data: Vec<u8>, // RGBA bytes
 }
 
-#[derive(Debug, Clone)]
+impl RawIcon {
+    /// Whether `data` holds all `width * height` pixels; recordings made with
+    /// `IconEncoding::Omit` keep only the size
+    pub fn has_pixels(&self) -> bool {
+        let len = (self.width as usize).checked_mul(self.height as usize).and_then(|n| n.checked_mul(4));
+        !self.data.is_empty() && len == Some(self.data.len())
+    }
+}
+
+/// A rectangle in root window coordinates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Geometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Geometry {
+    /// Whether this rectangle covers all of `other`
+    pub fn covers(&self, other: &Geometry) -> bool {
+        self.x <= other.x
+            && self.y <= other.y
+            && self.x + self.width as i32 >= other.x + other.width as i32
+            && self.y + self.height as i32 >= other.y + other.height as i32
+    }
+
+    pub fn intersects(&self, other: &Geometry) -> bool {
+        self.x < other.x + other.width as i32
+            && other.x < self.x + self.width as i32
+            && self.y < other.y + other.height as i32
+            && other.y < self.y + self.height as i32
+    }
+}
+
+/// `_NET_FRAME_EXTENTS`: how far the window manager's decorations reach past the client
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameExtents {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WindowInfo {
     pub xid: u32,
     pub title: String,
@@ -27,13 +93,68 @@ pub struct WindowInfo {
     pub is_active: bool, 
     pub icon_path: Option<PathBuf>,
     pub icon_data: Option<RawIcon>,
+    /// `WM_TRANSIENT_FOR`: the window this one is a dialog of (may be the root for group dialogs)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub transient_for: Option<u32>,
+    /// `WM_CLIENT_LEADER`, or the `WM_HINTS` window group, shared by all windows of one app
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub leader: Option<u32>,
+    /// `_NET_WM_WINDOW_TYPE_DIALOG`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub is_dialog: bool,
+    /// `_NET_WM_STATE_MODAL`: blocks input to the window it is transient for
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub is_modal: bool,
+    /// `_NET_WM_PID`, only when the window belongs to a process on this machine
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pid: Option<u32>,
+    /// The window did not answer a `_NET_WM_PING` in time
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unresponsive: bool,
+    /// `_NET_WM_STATE_FULLSCREEN`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub is_fullscreen: bool,
+    /// Where the client area is on screen, without the window manager's frame
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub geometry: Option<Geometry>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub frame_extents: Option<FrameExtents>,
+    /// `_NET_WM_STATE_HIDDEN`: minimized, so not on screen despite its geometry
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub is_hidden: bool,
+    /// The desktop file ID of the installed app the window belongs to, see `aura_assets::index`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub desktop_id: Option<String>,
+    /// `_NET_WM_STATE_MAXIMIZED_VERT` and `_NET_WM_STATE_MAXIMIZED_HORZ` together
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub is_maximized: bool,
+}
+
+impl WindowInfo {
+    /// The area the window covers on screen, including its frame
+    pub fn outer_geometry(&self) -> Option<Geometry> {
+        let geometry = self.geometry?;
+        let frame = self.frame_extents.unwrap_or_default();
+        Some(Geometry {
+            x: geometry.x - frame.left as i32,
+            y: geometry.y - frame.top as i32,
+            width: geometry.width + frame.left + frame.right,
+            height: geometry.height + frame.top + frame.bottom,
+        })
+    }
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "data", rename_all = "snake_case"))]
 pub enum SensorEvent {
     FullScan(Vec<WindowInfo>),
     WindowOpen(WindowInfo),
     WindowClose(u32),
     FocusChange(u32),
+    /// An open window changed (e.g. its title); carries the complete new state
+    WindowUpdate(WindowInfo),
+    /// What the window manager supports; sent first and whenever it changes
+    Capabilities(Capabilities),
 }
 
 pub struct Sensor;
@@ -53,7 +174,17 @@ pub fn activate_window(window_id: u32) -> Result<(), Box<dyn Error>> {
     let screen = &conn.setup().roots[0];
     let root = screen.root;
 
-    let active_window_atom = conn.intern_atom(false, b"_NET_ACTIVE_WINDOW")?.reply()?.atom;
+    let atoms = atoms::Atoms::new(&conn)?;
+    let capabilities = Capabilities::read(&conn, root, &atoms)?;
+    if !capabilities.supports(Capability::ActiveWindow) {
+        // No EWMH activation, raise and focus the window ourselves
+        conn.map_window(window_id)?;
+        conn.configure_window(window_id, &ConfigureWindowAux::new().stack_mode(StackMode::ABOVE))?;
+        conn.set_input_focus(InputFocus::PARENT, window_id, x11rb::CURRENT_TIME)?;
+        conn.flush()?;
+        return Ok(());
+    }
+    let active_window_atom = atoms.active_window;
 
     let event = ClientMessageEvent {
         response_type: x11rb::protocol::xproto::CLIENT_MESSAGE_EVENT,
@@ -76,6 +207,15 @@ pub fn activate_window(window_id: u32) -> Result<(), Box<dyn Error>> {
 
 /// Set a window to be "always on top" using _NET_WM_STATE_ABOVE
 pub fn set_always_on_top(window_id: u32) -> Result<(), Box<dyn Error>> {
+    change_above_state(window_id, true)
+}
+
+/// Drops the "always on top" state again, e.g. so a fullscreen window can cover the dock
+pub fn remove_always_on_top(window_id: u32) -> Result<(), Box<dyn Error>> {
+    change_above_state(window_id, false)
+}
+
+fn change_above_state(window_id: u32, add: bool) -> Result<(), Box<dyn Error>> {
     let (conn, _) = RustConnection::connect(None)?;
     let screen = &conn.setup().roots[0];
     let root = screen.root;
@@ -84,13 +224,14 @@ pub fn set_always_on_top(window_id: u32) -> Result<(), Box<dyn Error>> {
     let net_wm_state_above = conn.intern_atom(false, b"_NET_WM_STATE_ABOVE")?.reply()?.atom;
 
     // _NET_WM_STATE message: [action, first_property, second_property, source_indication, 0]
-    // action: 1 = _NET_WM_STATE_ADD
+    // action: 0 = _NET_WM_STATE_REMOVE, 1 = _NET_WM_STATE_ADD
+    let action = u32::from(add);
     let event = ClientMessageEvent {
         response_type: x11rb::protocol::xproto::CLIENT_MESSAGE_EVENT,
         format: 32,
         window: window_id,
         type_: net_wm_state,
-        data: x11rb::protocol::xproto::ClientMessageData::from([1u32, net_wm_state_above, 0, 1, 0]),
+        data: x11rb::protocol::xproto::ClientMessageData::from([action, net_wm_state_above, 0, 1, 0]),
         sequence: 0,
     };
 
@@ -106,59 +247,12 @@ pub fn set_always_on_top(window_id: u32) -> Result<(), Box<dyn Error>> {
 
 /// Find a window by title and set it to always-on-top
 pub fn set_always_on_top_by_name(title: &str) -> Result<(), Box<dyn Error>> {
-    let (conn, screen_num) = RustConnection::connect(None)?;
-    let screen = &conn.setup().roots[screen_num];
-    let root = screen.root;
-
-    let atoms = atoms::Atoms::new(&conn)?;
-    
-    // First try _NET_CLIENT_LIST
-    let client_list = utils::get_u32_vector_property(
-        &conn, 
-        root, 
-        atoms.client_list, 
-        x11rb::protocol::xproto::AtomEnum::WINDOW.into()
-    ).unwrap_or_default();
-
-    for &window_id in &client_list {
-        let win_title = utils::get_string_property(&conn, window_id, atoms.wm_name, atoms.utf8_string)
-            .or_else(|_| utils::get_string_property(&conn, window_id, atoms.wm_name, atoms.string))
-            .unwrap_or_default();
-
-        if win_title == title {
-            return set_always_on_top(window_id);
-        }
-    }
-
-    // Fallback: query all children of root window (for windows not in client list)
-    if let Ok(reply) = conn.query_tree(root)?.reply() {
-        for &child in &reply.children {
-            // Check direct children
-            let win_title = utils::get_string_property(&conn, child, atoms.wm_name, atoms.utf8_string)
-                .or_else(|_| utils::get_string_property(&conn, child, atoms.wm_name, atoms.string))
-                .unwrap_or_default();
-
-            if win_title == title {
-                return set_always_on_top(child);
-            }
-
-            // Check grandchildren (frame windows often wrap the actual window)
-            if let Ok(child_reply) = conn.query_tree(child)?.reply() {
-                for &grandchild in &child_reply.children {
-                    let win_title = utils::get_string_property(&conn, grandchild, atoms.wm_name, atoms.utf8_string)
-                        .or_else(|_| utils::get_string_property(&conn, grandchild, atoms.wm_name, atoms.string))
-                        .unwrap_or_default();
-
-                    if win_title == title {
-                        // Set on the frame (parent), not the grandchild
-                        return set_always_on_top(child);
-                    }
-                }
-            }
-        }
-    }
+    set_always_on_top(find_window_by_name(title)?)
+}
 
-    Err(format!("Window with title '{}' not found", title).into())
+/// Find a window by title and drop its always-on-top state
+pub fn remove_always_on_top_by_name(title: &str) -> Result<(), Box<dyn Error>> {
+    remove_always_on_top(find_window_by_name(title)?)
 }
 
 /// Set a window to skip taskbar and pager
@@ -211,6 +305,11 @@ pub fn set_skip_taskbar(window_id: u32) -> Result<(), Box<dyn Error>> {
 
 /// Find a window by title and set it to skip taskbar/pager
 pub fn set_skip_taskbar_by_name(title: &str) -> Result<(), Box<dyn Error>> {
+    set_skip_taskbar(find_window_by_name(title)?)
+}
+
+/// Finds a top-level window (or the frame around it) by its title
+fn find_window_by_name(title: &str) -> Result<u32, Box<dyn Error>> {
     let (conn, screen_num) = RustConnection::connect(None)?;
     let screen = &conn.setup().roots[screen_num];
     let root = screen.root;
@@ -231,21 +330,23 @@ pub fn set_skip_taskbar_by_name(title: &str) -> Result<(), Box<dyn Error>> {
             .unwrap_or_default();
 
         if win_title == title {
-            return set_skip_taskbar(window_id);
+            return Ok(window_id);
         }
     }
 
-    // Fallback: query all children of root window
+    // Fallback: query all children of root window (for windows not in client list)
     if let Ok(reply) = conn.query_tree(root)?.reply() {
         for &child in &reply.children {
+            // Check direct children
             let win_title = utils::get_string_property(&conn, child, atoms.wm_name, atoms.utf8_string)
                 .or_else(|_| utils::get_string_property(&conn, child, atoms.wm_name, atoms.string))
                 .unwrap_or_default();
 
             if win_title == title {
-                return set_skip_taskbar(child);
+                return Ok(child);
             }
 
+            // Check grandchildren (frame windows often wrap the actual window)
             if let Ok(child_reply) = conn.query_tree(child)?.reply() {
                 for &grandchild in &child_reply.children {
                     let win_title = utils::get_string_property(&conn, grandchild, atoms.wm_name, atoms.utf8_string)
@@ -253,7 +354,8 @@ pub fn set_skip_taskbar_by_name(title: &str) -> Result<(), Box<dyn Error>> {
                         .unwrap_or_default();
 
                     if win_title == title {
-                        return set_skip_taskbar(child);
+                        // Use the frame (parent), not the grandchild
+                        return Ok(child);
                     }
                 }
             }
@@ -261,4 +363,4 @@ pub fn set_skip_taskbar_by_name(title: &str) -> Result<(), Box<dyn Error>> {
     }
 
     Err(format!("Window with title '{}' not found", title).into())
-}
\ No newline at end of file
+}
@@ -1,112 +1,622 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use tokio::sync::mpsc::Sender;
 use std::error::Error;
+use std::io::{self, Read, Write};
+use std::os::fd::AsFd;
+use std::os::unix::net::UnixStream;
+use std::sync::mpsc::{Receiver, TryRecvError};
 use x11rb::rust_connection::RustConnection;
 use crate::atoms::Atoms;
 use x11rb::connection::Connection;
-use x11rb::protocol::xproto::{ConnectionExt, EventMask};
-use crate::utils::{get_string_property, get_u32_vector_property};
+use x11rb::properties::WmHints;
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt, EventMask};
+use crate::utils::{get_string_property, get_u32_vector_property, wait_for_event_timeout};
 use x11rb::protocol::Event;
-use crate::{SensorEvent, WindowInfo, RawIcon};
-use crate::filter::should_skip_window;
+use crate::{FrameExtents, Geometry, SensorEvent, WindowInfo, RawIcon};
+use crate::capabilities::{Capabilities, Capability};
+use crate::builder::{SensorConfig, WindowFilter};
+use crate::coalesce::EventQueue;
+use crate::fallback::scan_client_windows;
+use crate::process::{is_local_machine, ProcessInfo};
+use crate::ping::Pinger;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use aura_assets::lookup_icon_sized;
+
+/// How long to keep collecting X events after the first one before acting on them
+const COALESCE_WINDOW: Duration = Duration::from_millis(30);
+/// How often to retry delivering queued events while the receiver is full
+const RETRY_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Requests sent from a `SensorHandle` to the running loop
+pub(crate) enum Command {
+    Resync,
+    Stop,
+    SetFilter(WindowFilter),
+}
 
-use aura_assets::lookup_icon;
+/// Receiving side of a `SensorHandle`
+pub(crate) struct Control {
+    pub commands: Receiver<Command>,
+    /// Readable whenever a command was queued, so the loop wakes up without an X event
+    pub wake: UnixStream,
+}
 
-pub fn run_sensor_loop( tx: Sender<SensorEvent>) -> Result<(), Box<dyn Error>> {
+/// Properties that changed during a batch of X events
+#[derive(Default)]
+struct DirtyProperties {
+    capabilities: bool,
+    client_list: bool,
+    active_window: bool,
+    titles: HashSet<u32>,
+    // windows whose transient, group, type or state properties changed
+    relations: HashSet<u32>,
+    // windows whose WM_PROTOCOLS changed
+    protocols: HashSet<u32>,
+    // windows that answered a _NET_WM_PING
+    pongs: HashSet<u32>,
+    // windows that were moved or resized, or got new frame extents
+    geometry: HashSet<u32>,
+}
+
+impl DirtyProperties {
+    fn note(&mut self, event: &Event, atoms: &Atoms, root: u32, fallback: bool) {
+        if let Event::PropertyNotify(e) = event {
+            if e.window == root {
+                // A new window manager announces itself through these
+                if e.atom == atoms.net_supported || e.atom == atoms.net_supporting_wm_check {
+                    self.capabilities = true;
+                }
+                if e.atom == atoms.client_list {
+                    self.client_list = true;
+                }
+                if e.atom == atoms.active_window {
+                    self.active_window = true;
+                }
+            } else if e.atom == atoms.wm_name || e.atom == atoms.net_wm_name {
+                self.titles.insert(e.window);
+            } else if e.atom == u32::from(AtomEnum::WM_TRANSIENT_FOR)
+                || e.atom == u32::from(AtomEnum::WM_HINTS)
+                || e.atom == atoms.wm_client_leader
+                || e.atom == atoms.net_wm_window_type
+                || e.atom == atoms.net_wm_state
+            {
+                self.relations.insert(e.window);
+            } else if e.atom == atoms.wm_protocols {
+                self.protocols.insert(e.window);
+            } else if e.atom == atoms.net_frame_extents {
+                self.geometry.insert(e.window);
+            } else if e.atom == atoms.wm_state {
+                // the window manager started or stopped managing a window
+                self.client_list = true;
+            }
+        }
+
+        // Sent to the client itself; the root's substructure events are about frames
+        if let Event::ConfigureNotify(e) = event
+            && e.event == e.window
+            && e.window != root
+        {
+            self.geometry.insert(e.window);
+        }
+
+        // Answers to _NET_WM_PING come back to the root: [protocol, timestamp, window, ..]
+        if let Event::ClientMessage(e) = event
+            && e.window == root
+            && e.type_ == atoms.wm_protocols
+        {
+            let [protocol, _, window, _, _] = e.data.as_data32();
+            if protocol == atoms.net_wm_ping {
+                self.pongs.insert(window);
+            }
+        }
+
+        // Top-level structure changes only matter while tracking clients without _NET_CLIENT_LIST
+        if fallback {
+            match event {
+                Event::MapNotify(e) if e.event == root => self.client_list = true,
+                Event::UnmapNotify(e) if e.event == root => self.client_list = true,
+                Event::DestroyNotify(e) if e.event == root => self.client_list = true,
+                Event::ReparentNotify(e) if e.event == root => self.client_list = true,
+                _ => {}
+            }
+        }
+    }
+}
 
+pub fn run_sensor_loop( tx: Sender<SensorEvent>) -> Result<(), Box<dyn Error>> {
     // x11 connection
     let (conn, screen_num) = RustConnection::connect(None)?;
-    let screen = &conn.setup().roots[screen_num];
-    let root = screen.root;
+    SensorLoop::new(conn, screen_num, SensorConfig::default(), tx, None)?.run()
+}
+
+/// State of a running sensor
+pub(crate) struct SensorLoop {
+    conn: RustConnection,
+    root: u32,
+    atoms: Box<Atoms>,
+    config: SensorConfig,
+    tx: Sender<SensorEvent>,
+    control: Option<Control>,
+    capabilities: Capabilities,
+    // find clients through WM_STATE because the WM has no _NET_CLIENT_LIST
+    fallback: bool,
+    // every client window seen, including filtered ones
+    windows: HashMap<u32, WindowInfo>,
+    // windows that passed the filter and were reported as open
+    visible: HashSet<u32>,
+    // events waiting for room in the channel
+    queue: EventQueue,
+    // present when pinging is enabled
+    pinger: Option<Pinger>,
+    // visible windows that advertise _NET_WM_PING
+    pingable: HashSet<u32>,
+    // readable once a new app index was read, so windows get matched to apps again
+    apps_changed: UnixStream,
+}
+
+impl SensorLoop {
+    pub(crate) fn new(
+        conn: RustConnection,
+        screen_num: usize,
+        config: SensorConfig,
+        tx: Sender<SensorEvent>,
+        control: Option<Control>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let root = conn.setup().roots[screen_num].root;
+        let atoms = Atoms::new(&conn)?;
+
+        // listen for property changes on the root window
+        conn.change_window_attributes(
+            root,
+            &x11rb::protocol::xproto::ChangeWindowAttributesAux::new()
+                .event_mask(EventMask::PROPERTY_CHANGE),
+        )?;
+        conn.flush()?;
+
+        // Windows seen before the app index was read, or before their app was installed,
+        // are matched again when a new one arrives
+        let (apps_changed, notifier) = UnixStream::pair()?;
+        apps_changed.set_nonblocking(true)?;
+        notifier.set_nonblocking(true)?;
+        aura_assets::index::subscribe(move |_| match (&notifier).write(&[1]) {
+            Ok(_) => true,
+            Err(e) => e.kind() == io::ErrorKind::WouldBlock,
+        });
+
+        Ok(Self {
+            conn,
+            root,
+            atoms,
+            tx,
+            control,
+            capabilities: Capabilities::default(),
+            fallback: false,
+            windows: HashMap::new(),
+            visible: HashSet::new(),
+            queue: EventQueue::new(),
+            pinger: config.ping_interval.map(Pinger::new),
+            pingable: HashSet::new(),
+            apps_changed,
+            config,
+        })
+    }
+
+    pub(crate) fn run(mut self) -> Result<(), Box<dyn Error>> {
+        self.capabilities = Capabilities::read(&self.conn, self.root, &self.atoms)?;
+        self.warn_missing_capabilities();
+        self.update_tracking_mode()?;
+        self.queue.push(SensorEvent::Capabilities(self.capabilities.clone()));
+
+        // get all windows that are open
+        let initial_ids = self.client_list()?;
+        self.sync_client_list(&initial_ids);
 
-    let atoms = Atoms::new(&conn)?;
+        if !self.flush() {
+            return Ok(());
+        }
 
-    // listen for property changes on the root window
-    conn.change_window_attributes(
-        root,
-        &x11rb::protocol::xproto::ChangeWindowAttributesAux::new()
-            .event_mask(EventMask::PROPERTY_CHANGE),
-    )?;
-    conn.flush()?;
+        loop {
+            // Block until something happens, unless undelivered events need another try
+            // or a ping is due
+            let mut timeout = if self.queue.is_empty() { None } else { Some(RETRY_INTERVAL) };
+            if let Some(pinger) = &self.pinger {
+                let until_ping = pinger.deadline().saturating_duration_since(Instant::now());
+                timeout = Some(timeout.map_or(until_ping, |t| t.min(until_ping)));
+            }
+            let first = match self.wait(timeout) {
+                Ok(event) => event,
+                Err(_) => break,
+            };
+
+            // Gather the rest of the burst so each property is only re-read once
+            let mut dirty = DirtyProperties::default();
+            if let Some(event) = first {
+                dirty.note(&event, &self.atoms, self.root, self.fallback);
+
+                let deadline = Instant::now() + COALESCE_WINDOW;
+                loop {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    match self.wait(Some(remaining)) {
+                        Ok(Some(event)) => dirty.note(&event, &self.atoms, self.root, self.fallback),
+                        Ok(None) => break,
+                        Err(_) => return Ok(()),
+                    }
+                }
+            }
 
-    // set of windows that are known to be open
-    let mut known_windows: HashSet<u32> = HashSet::new();
+            if !self.handle_commands() {
+                break;
+            }
 
-    // get all windows that are open
-    let initial_ids = get_u32_vector_property(&conn, root, atoms.client_list, x11rb::protocol::xproto::AtomEnum::WINDOW.into())?;
-    for &id in &initial_ids {
-        if let Ok(info) = fetch_window_info(&conn, &atoms, id) {
-            known_windows.insert(id);
-            // Filter out system windows at the source
-            if !should_skip_window(&info) {
-                let _ = tx.blocking_send(SensorEvent::WindowOpen(info));
+            if self.drain_apps_changed() {
+                self.rematch_apps();
             }
+
+            if dirty.capabilities {
+                let capabilities = Capabilities::read(&self.conn, self.root, &self.atoms).unwrap_or_default();
+                if capabilities != self.capabilities {
+                    self.capabilities = capabilities;
+                    self.warn_missing_capabilities();
+                    if self.update_tracking_mode().is_err() {
+                        break;
+                    }
+                    self.queue.push(SensorEvent::Capabilities(self.capabilities.clone()));
+                    // The window manager was probably replaced, start from its view
+                    dirty.client_list = true;
+                    dirty.active_window = true;
+                }
+            }
+
+            if dirty.client_list {
+                let current_ids = self.client_list().unwrap_or_default();
+                self.sync_client_list(&current_ids);
+            }
+
+            if dirty.active_window && self.config.track_focus {
+                self.push_focus();
+            }
+
+            if !self.config.track_titles {
+                dirty.titles.clear();
+            }
+            if !dirty.titles.is_empty() || !dirty.relations.is_empty() || !dirty.geometry.is_empty() {
+                self.refresh_windows(&dirty.titles, &dirty.relations, &dirty.geometry);
+            }
+
+            for window in &dirty.protocols {
+                self.update_pingable(*window);
+            }
+            self.check_responsiveness(&dirty.pongs);
+
+            if !self.flush() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Delivers what the receiver has room for; returns `false` once it is gone
+    fn flush(&mut self) -> bool {
+        // A stalled receiver made the queue give up, catch it up in one go
+        if self.queue.take_overflow() {
+            self.queue_full_scan();
+        }
+        self.queue.flush(&self.tx)
+    }
+
+    fn warn_missing_capabilities(&self) {
+        if !self.capabilities.supports(Capability::ClientList) {
+            eprintln!(
+                "Warning: window manager {} does not advertise _NET_CLIENT_LIST, tracking windows through WM_STATE",
+                self.capabilities.wm_name.as_deref().unwrap_or("(unknown)")
+            );
         }
     }
 
+    /// Switches between `_NET_CLIENT_LIST` and `WM_STATE` based client tracking
+    fn update_tracking_mode(&mut self) -> Result<(), Box<dyn Error>> {
+        self.fallback = !self.capabilities.supports(Capability::ClientList);
 
-    loop {
-        let event = match conn.wait_for_event() {
-            Ok(event) => event,
-            Err(_) => {
+        let mut mask = EventMask::PROPERTY_CHANGE;
+        if self.fallback || self.pinger.is_some() {
+            // Map, unmap, destroy and reparent of every top-level window, and ping answers
+            mask |= EventMask::SUBSTRUCTURE_NOTIFY;
+        }
+        self.conn.change_window_attributes(
+            self.root,
+            &x11rb::protocol::xproto::ChangeWindowAttributesAux::new().event_mask(mask),
+        )?;
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    /// Waits for an X event; returns `None` on timeout, when a command arrived or when
+    /// the app index changed
+    fn wait(&self, timeout: Option<Duration>) -> Result<Option<Event>, Box<dyn Error>> {
+        let mut wake = vec![self.apps_changed.as_fd()];
+        wake.extend(self.control.as_ref().map(|control| control.wake.as_fd()));
+        wait_for_event_timeout(&self.conn, &wake, timeout)
+    }
+
+    /// Reads the app index notifications, telling whether there were any
+    fn drain_apps_changed(&self) -> bool {
+        let mut buf = [0u8; 64];
+        let mut changed = false;
+        while let Ok(read) = (&self.apps_changed).read(&mut buf) {
+            if read == 0 {
                 break;
             }
-        };
+            changed = true;
+        }
+        changed
+    }
 
-        match event {
-            Event::PropertyNotify(e) => {
-                if e.atom == atoms.client_list {
-                    let current_ids = get_u32_vector_property(&conn, root, atoms.client_list, x11rb::protocol::xproto::AtomEnum::WINDOW.into())
-                        .unwrap_or_default();
-
-                    let current_set: HashSet<u32> = current_ids.iter().cloned().collect();
-
-                    // Detect Opened Windows
-                    for &id in &current_ids {
-                        if !known_windows.contains(&id) {
-                            if let Ok(info) = fetch_window_info(&conn, &atoms, id) {
-                                known_windows.insert(id);
-                                // Filter out system windows at the source
-                                if !should_skip_window(&info) {
-                                    let _ = tx.blocking_send(SensorEvent::WindowOpen(info));
-                                }
-                            }
-                        }
-                    }
+    /// Matches every window to the installed apps again, reporting the ones that got
+    /// another app or a theme icon
+    fn rematch_apps(&mut self) {
+        for (id, info) in &mut self.windows {
+            let (desktop_id, icon_path) = match_app(&info.class, info.pid, self.config.icon_size);
+            let mut changed = desktop_id != info.desktop_id;
+            info.desktop_id = desktop_id;
+            if icon_path.is_some() && icon_path != info.icon_path {
+                info.icon_path = icon_path;
+                info.icon_data = None;
+                changed = true;
+            }
+            if changed && self.visible.contains(id) {
+                self.queue.push(SensorEvent::WindowUpdate(info.clone()));
+            }
+        }
+    }
 
-                    let to_remove: Vec<u32> = known_windows.difference(&current_set).cloned().collect();
-                    for id in to_remove {
-                        known_windows.remove(&id);
-                        let _ = tx.blocking_send(SensorEvent::WindowClose(id));
+    /// Applies queued handle commands; returns `false` when the sensor should stop
+    fn handle_commands(&mut self) -> bool {
+        let mut commands = Vec::new();
+        let mut disconnected = false;
+        if let Some(control) = &self.control {
+            // Drain the wake-up bytes, the command queue is the source of truth
+            let mut buf = [0u8; 64];
+            loop {
+                match (&control.wake).read(&mut buf) {
+                    Ok(0) => {
+                        disconnected = true;
+                        break;
                     }
+                    Ok(_) => {}
+                    Err(_) => break,
                 }
+            }
 
-                if e.atom == atoms.active_window {
-                    let active_ids = get_u32_vector_property(&conn, root, atoms.active_window, x11rb::protocol::xproto::AtomEnum::WINDOW.into())
-                        .unwrap_or_default();
-                    
-                    if let Some(&active_id) = active_ids.first() {
-                        let _ = tx.blocking_send(SensorEvent::FocusChange(active_id));
+            loop {
+                match control.commands.try_recv() {
+                    Ok(command) => commands.push(command),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        disconnected = true;
+                        break;
                     }
                 }
             }
+        }
+        // Every handle was dropped: keep sensing, but stop polling the closed wake-up
+        // socket, it would stay readable forever
+        if disconnected {
+            self.control = None;
+        }
+
+        for command in commands {
+            match command {
+                Command::Stop => return false,
+                Command::Resync => self.resync(),
+                Command::SetFilter(filter) => {
+                    self.config.filter = filter;
+                    self.apply_filter();
+                }
+            }
+        }
+        true
+    }
+
+    fn client_list(&self) -> Result<Vec<u32>, Box<dyn Error>> {
+        if self.fallback {
+            return scan_client_windows(&self.conn, self.root, &self.atoms);
+        }
+        get_u32_vector_property(&self.conn, self.root, self.atoms.client_list, x11rb::protocol::xproto::AtomEnum::WINDOW.into())
+    }
+
+    /// Diffs the current client list against the known windows and queues open/close events
+    fn sync_client_list(&mut self, current_ids: &[u32]) {
+        let current_set: HashSet<u32> = current_ids.iter().cloned().collect();
+
+        // Detect Opened Windows
+        for &id in current_ids {
+            if !self.windows.contains_key(&id)
+                && let Ok(info) = fetch_window_info(&self.conn, &self.atoms, id, self.config.icon_size)
+            {
+                self.track(id);
+                // Filter out system windows at the source
+                if !(self.config.filter)(&info) {
+                    self.visible.insert(id);
+                    self.queue.push(SensorEvent::WindowOpen(info.clone()));
+                }
+                self.windows.insert(id, info);
+                self.update_pingable(id);
+            }
+        }
+
+        let to_remove: Vec<u32> = self.windows.keys()
+            .filter(|id| !current_set.contains(id))
+            .cloned()
+            .collect();
+        for id in to_remove {
+            self.windows.remove(&id);
+            self.pingable.remove(&id);
+            if let Some(pinger) = &mut self.pinger {
+                pinger.forget(id);
+            }
+            if self.visible.remove(&id) {
+                self.queue.push(SensorEvent::WindowClose(id));
+            }
+        }
+    }
+
+    /// Subscribes to the per-window property and geometry changes behind `WindowUpdate`
+    fn track(&self, window: u32) {
+        let _ = self.conn.change_window_attributes(
+            window,
+            &x11rb::protocol::xproto::ChangeWindowAttributesAux::new()
+                .event_mask(EventMask::PROPERTY_CHANGE | EventMask::STRUCTURE_NOTIFY),
+        );
+    }
+
+    fn push_focus(&mut self) {
+        let active_ids = get_u32_vector_property(&self.conn, self.root, self.atoms.active_window, x11rb::protocol::xproto::AtomEnum::WINDOW.into())
+            .unwrap_or_default();
+
+        if let Some(&active_id) = active_ids.first() {
+            self.queue.push(SensorEvent::FocusChange(active_id));
+        }
+    }
 
-            _ => {
-                // we dont care.. 
+    /// Re-reads changed titles, relations and geometry, reporting windows that really changed
+    fn refresh_windows(&mut self, titles: &HashSet<u32>, relations: &HashSet<u32>, geometry: &HashSet<u32>) {
+        let mut ids: Vec<u32> = titles.iter().chain(relations).chain(geometry).cloned().collect();
+        ids.sort_unstable();
+        ids.dedup();
+
+        for id in ids {
+            let Some(info) = self.windows.get_mut(&id) else { continue };
+            let before = info.clone();
+            if titles.contains(&id) {
+                info.title = fetch_title(&self.conn, &self.atoms, id);
+            }
+            if relations.contains(&id) {
+                fetch_relations(&self.conn, &self.atoms, id).apply(info);
+            }
+            if geometry.contains(&id) {
+                info.geometry = fetch_geometry(&self.conn, id);
+                info.frame_extents = fetch_frame_extents(&self.conn, &self.atoms, id);
+            }
+            if *info != before && self.visible.contains(&id) {
+                self.queue.push(SensorEvent::WindowUpdate(info.clone()));
             }
+        }
+    }
+
+    fn update_pingable(&mut self, window: u32) {
+        // Untracked windows are checked by sync_client_list once they show up
+        if !self.windows.contains_key(&window) {
+            return;
+        }
+        let protocols = get_u32_vector_property(&self.conn, window, self.atoms.wm_protocols, AtomEnum::ATOM.into())
+            .unwrap_or_default();
+        if protocols.contains(&self.atoms.net_wm_ping) {
+            self.pingable.insert(window);
+        } else {
+            self.pingable.remove(&window);
+        }
+    }
+
+    /// Handles ping answers and timeouts, reporting windows that hung or recovered
+    fn check_responsiveness(&mut self, pongs: &HashSet<u32>) {
+        let Some(pinger) = &mut self.pinger else { return };
+
+        let mut changed = Vec::new();
+        for &window in pongs {
+            pinger.pong(window);
+            changed.push((window, false));
+        }
+
+        // Only windows in the dock are worth probing
+        let targets: Vec<u32> = self.pingable.iter()
+            .filter(|id| self.visible.contains(id))
+            .cloned()
+            .collect();
+        for window in pinger.tick(&self.conn, &self.atoms, targets.into_iter()) {
+            changed.push((window, true));
+        }
+        let _ = self.conn.flush();
 
+        for (window, unresponsive) in changed {
+            let Some(info) = self.windows.get_mut(&window) else { continue };
+            if info.unresponsive == unresponsive {
+                continue;
+            }
+            info.unresponsive = unresponsive;
+            if self.visible.contains(&window) {
+                self.queue.push(SensorEvent::WindowUpdate(info.clone()));
+            }
         }
     }
 
-    Ok(())
+    /// Re-reads every client window and reports the complete state
+    fn resync(&mut self) {
+        self.windows.clear();
+        self.visible.clear();
+        self.pingable.clear();
+        if let Some(pinger) = &mut self.pinger {
+            pinger.clear();
+        }
+
+        let ids = self.client_list().unwrap_or_default();
+        self.sync_client_list(&ids);
 
+        // Replace the individual opens with one authoritative scan
+        self.queue_full_scan();
+    }
+
+    /// Reports the known state as a whole, replacing everything still queued
+    fn queue_full_scan(&mut self) {
+        let mut scan: Vec<WindowInfo> = self.visible.iter()
+            .filter_map(|id| self.windows.get(id).cloned())
+            .collect();
+        scan.sort_by_key(|info| info.xid);
+        self.queue.push(SensorEvent::FullScan(scan));
+        self.queue.push(SensorEvent::Capabilities(self.capabilities.clone()));
+
+        if self.config.track_focus {
+            self.push_focus();
+        }
+    }
+
+    /// Re-evaluates the filter for every known window
+    fn apply_filter(&mut self) {
+        let mut ids: Vec<u32> = self.windows.keys().cloned().collect();
+        ids.sort_unstable();
+
+        for id in ids {
+            let info = &self.windows[&id];
+            let show = !(self.config.filter)(info);
+            let shown = self.visible.contains(&id);
+
+            if show && !shown {
+                self.visible.insert(id);
+                self.queue.push(SensorEvent::WindowOpen(info.clone()));
+            } else if !show && shown {
+                self.visible.remove(&id);
+                self.queue.push(SensorEvent::WindowClose(id));
+            }
+        }
+    }
+}
+
+/// Reads the window title, preferring the EWMH UTF-8 name over the legacy WM_NAME
+fn fetch_title(conn: &RustConnection, atoms: &Atoms, window: u32) -> String {
+    get_string_property(conn, window, atoms.net_wm_name, atoms.utf8_string)
+        .ok()
+        .filter(|title| !title.is_empty())
+        // WM_NAME may be STRING, UTF8_STRING or COMPOUND_TEXT; a request for another
+        // type than the property has comes back empty
+        .or_else(|| get_string_property(conn, window, atoms.wm_name, AtomEnum::ANY.into()).ok())
+        .unwrap_or_else(|| "Unknown".to_string())
 }
 
-fn fetch_window_info(conn: &RustConnection, atoms: &Atoms, window: u32) -> Result<WindowInfo, Box<dyn Error>> {
+pub(crate) fn fetch_window_info(conn: &RustConnection, atoms: &Atoms, window: u32, icon_size: u32) -> Result<WindowInfo, Box<dyn Error>> {
     // Fetch Title
-    let title = get_string_property(conn, window, atoms.wm_name, atoms.utf8_string)
-        .or_else(|_| get_string_property(conn, window, atoms.wm_name, atoms.string))
-        .unwrap_or_else(|_| "Unknown".to_string());
+    let title = fetch_title(conn, atoms, window);
 
     // Fetch Class (App Name)
     // WM_CLASS returns "InstanceName\0ClassName\0"
@@ -119,26 +629,138 @@ fn fetch_window_info(conn: &RustConnection, atoms: &Atoms, window: u32) -> Resul
         .unwrap_or(raw_class.split('\0').next().unwrap_or(""))
         .to_string();
 
-    let icon_path = lookup_icon(&class);
-    
+    let pid = fetch_pid(conn, atoms, window);
+    let (desktop_id, icon_path) = match_app(&class, pid, icon_size);
+
     // Fetch _NET_WM_ICON if path lookup failed
     let icon_data = if icon_path.is_none() {
-        get_net_wm_icon(conn, window, atoms.net_wm_icon).ok().flatten()
+        get_net_wm_icon(conn, window, atoms.net_wm_icon, icon_size).ok().flatten()
     } else {
         None
     };
 
-    Ok(WindowInfo {
+    let mut info = WindowInfo {
         xid: window,
         title,
         class,
         is_active: false,
         icon_path,
         icon_data,
+        pid,
+        geometry: fetch_geometry(conn, window),
+        frame_extents: fetch_frame_extents(conn, atoms, window),
+        desktop_id,
+        ..Default::default()
+    };
+    fetch_relations(conn, atoms, window).apply(&mut info);
+    Ok(info)
+}
+
+/// The desktop ID and theme icon of the app behind a window, found by its class, then
+/// by its executable; nothing until the app index has been read
+fn match_app(class: &str, pid: Option<u32>, icon_size: u32) -> (Option<String>, Option<PathBuf>) {
+    let exe = pid.and_then(|pid| ProcessInfo::read(pid).ok()).and_then(|process| process.exe_name());
+    let desktop_id = aura_assets::index::current()
+        .app_for_window(class, exe.as_deref())
+        .map(|app| app.desktop_id.clone());
+
+    // Some apps have a useless WM_CLASS, try their executable before the pixels
+    let icon_path = lookup_icon_sized(class, icon_size as u16)
+        .or_else(|| exe.as_deref().and_then(|exe| lookup_icon_sized(exe, icon_size as u16)));
+    (desktop_id, icon_path)
+}
+
+/// The client area in root coordinates; frames put clients at an offset from their parent
+fn fetch_geometry(conn: &RustConnection, window: u32) -> Option<Geometry> {
+    let geometry = conn.get_geometry(window).ok()?.reply().ok()?;
+    let origin = conn.translate_coordinates(window, geometry.root, 0, 0).ok()?.reply().ok()?;
+    Some(Geometry {
+        x: origin.dst_x.into(),
+        y: origin.dst_y.into(),
+        width: geometry.width.into(),
+        height: geometry.height.into(),
     })
 }
 
-fn get_net_wm_icon(conn: &RustConnection, window: u32, atom: x11rb::protocol::xproto::Atom) -> Result<Option<RawIcon>, Box<dyn Error>> {
+/// Reads `_NET_WM_PID`, ignoring windows of clients running on another host
+fn fetch_pid(conn: &RustConnection, atoms: &Atoms, window: u32) -> Option<u32> {
+    let pid = get_u32_vector_property(conn, window, atoms.net_wm_pid, AtomEnum::CARDINAL.into())
+        .ok()?
+        .first()
+        .cloned()
+        .filter(|&pid| pid != 0)?;
+
+    // Without WM_CLIENT_MACHINE there is nothing to check against
+    match get_string_property(conn, window, AtomEnum::WM_CLIENT_MACHINE.into(), AtomEnum::STRING.into()) {
+        Ok(machine) if !is_local_machine(machine.trim_end_matches('\0')) => None,
+        _ => Some(pid),
+    }
+}
+
+/// Reads `_NET_FRAME_EXTENTS`: [left, right, top, bottom]
+fn fetch_frame_extents(conn: &RustConnection, atoms: &Atoms, window: u32) -> Option<FrameExtents> {
+    let extents = get_u32_vector_property(conn, window, atoms.net_frame_extents, AtomEnum::CARDINAL.into()).ok()?;
+    let [left, right, top, bottom] = extents.get(..4)?.try_into().ok()?;
+    Some(FrameExtents { left, right, top, bottom })
+}
+
+/// How a window relates to the other windows of its application, plus its state flags
+struct Relations {
+    transient_for: Option<u32>,
+    leader: Option<u32>,
+    is_dialog: bool,
+    is_modal: bool,
+    is_fullscreen: bool,
+    is_hidden: bool,
+    is_maximized: bool,
+}
+
+impl Relations {
+    fn apply(self, info: &mut WindowInfo) {
+        info.transient_for = self.transient_for;
+        info.leader = self.leader;
+        info.is_dialog = self.is_dialog;
+        info.is_modal = self.is_modal;
+        info.is_fullscreen = self.is_fullscreen;
+        info.is_hidden = self.is_hidden;
+        info.is_maximized = self.is_maximized;
+    }
+}
+
+fn fetch_relations(conn: &RustConnection, atoms: &Atoms, window: u32) -> Relations {
+    let first_window = |property| {
+        get_u32_vector_property(conn, window, property, AtomEnum::WINDOW.into())
+            .ok()
+            .and_then(|ids| ids.first().cloned())
+            .filter(|&id| id != x11rb::NONE)
+    };
+
+    let transient_for = first_window(AtomEnum::WM_TRANSIENT_FOR.into());
+    // Toolkits set WM_CLIENT_LEADER; older clients only the ICCCM window group
+    let leader = first_window(atoms.wm_client_leader).or_else(|| {
+        WmHints::get(conn, window).ok()
+            .and_then(|cookie| cookie.reply().ok().flatten())
+            .and_then(|hints| hints.window_group)
+    });
+
+    let window_types = get_u32_vector_property(conn, window, atoms.net_wm_window_type, AtomEnum::ATOM.into())
+        .unwrap_or_default();
+    let states = get_u32_vector_property(conn, window, atoms.net_wm_state, AtomEnum::ATOM.into())
+        .unwrap_or_default();
+
+    Relations {
+        transient_for,
+        leader,
+        is_dialog: window_types.contains(&atoms.net_wm_window_type_dialog),
+        is_modal: states.contains(&atoms.net_wm_state_modal),
+        is_fullscreen: states.contains(&atoms.net_wm_state_fullscreen),
+        is_hidden: states.contains(&atoms.net_wm_state_hidden),
+        is_maximized: states.contains(&atoms.net_wm_state_maximized_vert)
+            && states.contains(&atoms.net_wm_state_maximized_horz),
+    }
+}
+
+fn get_net_wm_icon(conn: &RustConnection, window: u32, atom: x11rb::protocol::xproto::Atom, icon_size: u32) -> Result<Option<RawIcon>, Box<dyn Error>> {
     let reply = conn.get_property(
         false, 
         window, 
@@ -156,7 +778,7 @@ fn get_net_wm_icon(conn: &RustConnection, window: u32, atom: x11rb::protocol::xp
     let data: Vec<u32> = reply.value32().ok_or("Invalid value32")?.collect();
     
     // Format: width, height, pixels...
-    // We can have multiple icons. Let's find the biggest one or closest to 48x48.
+    // We can have multiple icons. Pick the one that scales best to icon_size.
     let mut cursor = 0;
     let mut best_icon: Option<RawIcon> = None;
     let mut best_score = 0;
@@ -169,9 +791,13 @@ fn get_net_wm_icon(conn: &RustConnection, window: u32, atom: x11rb::protocol::xp
         
         if cursor + 2 + size > data.len() { break; }
         
-        // Basic score: prefer larger icons, but maybe closest to 48?
-        // Let's just pick the largest one for now to ensure quality.
-        let score = width * height;
+        // Prefer the smallest icon that is at least icon_size wide (downscaling looks fine),
+        // otherwise the largest one available.
+        let score = if width >= icon_size {
+            u64::from(u32::MAX - width)
+        } else {
+            u64::from(width)
+        };
         if score > best_score {
             let pixels = &data[cursor+2 .. cursor+2+size];
             
@@ -198,11 +824,3 @@ fn get_net_wm_icon(conn: &RustConnection, window: u32, atom: x11rb::protocol::xp
 
     Ok(best_icon)
 }
-
-
-// pub struct WindowInfo {
-//     pub xid: u32,
-//     pub title: String,
-//     pub class: String, // for instance chrome or some window that is open.. 
-//     pub is_active: bool, 
-// }
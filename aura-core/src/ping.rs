@@ -0,0 +1,93 @@
+//! `_NET_WM_PING` bookkeeping for detecting windows that stopped processing events.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::{Duration, Instant};
+use x11rb::protocol::xproto::{ClientMessageEvent, ConnectionExt, EventMask, CLIENT_MESSAGE_EVENT};
+use x11rb::rust_connection::RustConnection;
+
+use crate::atoms::Atoms;
+
+/// How long a window may take to answer before it counts as not responding
+pub const PING_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Pings windows periodically and tracks the answers
+pub(crate) struct Pinger {
+    interval: Duration,
+    next_round: Instant,
+    // window -> when the unanswered ping was sent
+    pending: HashMap<u32, Instant>,
+}
+
+impl Pinger {
+    pub(crate) fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next_round: Instant::now() + interval,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// When `tick` has something to do next
+    pub(crate) fn deadline(&self) -> Instant {
+        self.pending.values()
+            .map(|sent| *sent + PING_TIMEOUT)
+            .fold(self.next_round, Instant::min)
+    }
+
+    /// Returns the windows whose ping timed out and starts a new round when one is due
+    pub(crate) fn tick(&mut self, conn: &RustConnection, atoms: &Atoms, windows: impl Iterator<Item = u32>) -> Vec<u32> {
+        let now = Instant::now();
+
+        let mut expired: Vec<u32> = self.pending.iter()
+            .filter(|(_, sent)| now.duration_since(**sent) >= PING_TIMEOUT)
+            .map(|(window, _)| *window)
+            .collect();
+        expired.sort_unstable();
+        for window in &expired {
+            self.pending.remove(window);
+        }
+
+        if now >= self.next_round {
+            self.next_round = now + self.interval;
+            for window in windows {
+                if self.pending.contains_key(&window) {
+                    continue;
+                }
+                if self.send(conn, atoms, window).is_ok() {
+                    self.pending.insert(window, now);
+                }
+            }
+        }
+
+        expired
+    }
+
+    /// Records an answer; any answer proves the window is alive, even a late one
+    pub(crate) fn pong(&mut self, window: u32) {
+        self.pending.remove(&window);
+    }
+
+    pub(crate) fn forget(&mut self, window: u32) {
+        self.pending.remove(&window);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    fn send(&self, conn: &RustConnection, atoms: &Atoms, window: u32) -> Result<(), Box<dyn Error>> {
+        // [protocol, timestamp, window, 0, 0]; the client sends it back to the root and
+        // answers are matched by window, so the timestamp is only there for the spec
+        let event = ClientMessageEvent {
+            response_type: CLIENT_MESSAGE_EVENT,
+            format: 32,
+            window,
+            type_: atoms.wm_protocols,
+            data: [atoms.net_wm_ping, x11rb::CURRENT_TIME, window, 0, 0].into(),
+            sequence: 0,
+        };
+        conn.send_event(false, window, EventMask::NO_EVENT, event)?;
+        Ok(())
+    }
+}
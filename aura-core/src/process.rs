@@ -0,0 +1,156 @@
+//! Details about the process behind a window, read from `/proc`.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// A snapshot of a local process
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    /// Target of `/proc/<pid>/exe`, unreadable for other users' processes
+    pub exe: Option<PathBuf>,
+    pub cmdline: Vec<String>,
+    /// The cgroup v2 path, e.g. `/user.slice/.../app-firefox-1234.scope`
+    pub cgroup: Option<String>,
+    /// Resident memory
+    pub rss_bytes: u64,
+    /// User plus system CPU time used so far
+    pub cpu_time: Duration,
+    /// When the process started, measured from boot
+    pub start_time: Duration,
+}
+
+impl ProcessInfo {
+    pub fn read(pid: u32) -> Result<Self, Box<dyn Error>> {
+        let dir = PathBuf::from(format!("/proc/{}", pid));
+
+        let stat = fs::read_to_string(dir.join("stat"))?;
+        // The command name is in parentheses and may itself contain spaces or ')'
+        let fields: Vec<&str> = stat.rsplit_once(')')
+            .ok_or("Malformed /proc stat")?
+            .1
+            .split_whitespace()
+            .collect();
+        // Field numbers from proc(5), counted from the state field (3)
+        let field = |number: usize| -> Result<u64, Box<dyn Error>> {
+            Ok(fields.get(number - 3).ok_or("Truncated /proc stat")?.parse()?)
+        };
+        let ticks = rustix::param::clock_ticks_per_second();
+        let cpu_time = ticks_to_duration(field(14)? + field(15)?, ticks);
+        let start_time = ticks_to_duration(field(22)?, ticks);
+
+        let rss_pages: u64 = fs::read_to_string(dir.join("statm"))?
+            .split_whitespace()
+            .nth(1)
+            .ok_or("Malformed /proc statm")?
+            .parse()?;
+
+        let cmdline = fs::read(dir.join("cmdline"))
+            .map(|raw| {
+                raw.split(|&b| b == 0)
+                    .filter(|arg| !arg.is_empty())
+                    .map(|arg| String::from_utf8_lossy(arg).to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            pid,
+            exe: fs::read_link(dir.join("exe")).ok(),
+            cmdline,
+            cgroup: fs::read_to_string(dir.join("cgroup")).ok().and_then(|raw| unified_cgroup(&raw)),
+            rss_bytes: rss_pages * rustix::param::page_size() as u64,
+            cpu_time,
+            start_time,
+        })
+    }
+
+    /// File name of the executable, falling back to the first command line argument
+    pub fn exe_name(&self) -> Option<String> {
+        self.exe.as_deref()
+            .or_else(|| self.cmdline.first().map(Path::new))
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+    }
+}
+
+/// Computes CPU usage from successive snapshots of the same processes
+#[derive(Debug, Default)]
+pub struct CpuSampler {
+    // pid -> (start time, cpu time, uptime) of the previous sample
+    previous: HashMap<u32, (Duration, Duration, Duration)>,
+}
+
+impl CpuSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// CPU usage in percent of one core since the previous sample of this process,
+    /// or averaged over its lifetime on the first sample
+    pub fn sample(&mut self, info: &ProcessInfo) -> Option<f32> {
+        let uptime = uptime()?;
+        let (cpu_before, uptime_before) = match self.previous.get(&info.pid) {
+            // A different start time means the PID was reused
+            Some(&(start, cpu, at)) if start == info.start_time => (cpu, at),
+            _ => (Duration::ZERO, info.start_time),
+        };
+        self.previous.insert(info.pid, (info.start_time, info.cpu_time, uptime));
+
+        let elapsed = uptime.checked_sub(uptime_before)?.as_secs_f32();
+        if elapsed <= 0.0 {
+            return None;
+        }
+        let used = info.cpu_time.saturating_sub(cpu_before).as_secs_f32();
+        Some(used / elapsed * 100.0)
+    }
+
+    /// Forgets processes that are no longer of interest
+    pub fn retain(&mut self, pids: impl Fn(u32) -> bool) {
+        self.previous.retain(|&pid, _| pids(pid));
+    }
+}
+
+/// Kills a process that stopped responding (`SIGKILL`, it gets no chance to clean up)
+pub fn force_quit(pid: u32) -> Result<(), Box<dyn Error>> {
+    let pid = i32::try_from(pid).ok()
+        .and_then(rustix::process::Pid::from_raw)
+        .ok_or("Invalid PID")?;
+    rustix::process::kill_process(pid, rustix::process::Signal::KILL)?;
+    Ok(())
+}
+
+/// Whether `WM_CLIENT_MACHINE` names this host, so `_NET_WM_PID` refers to a local process
+pub fn is_local_machine(machine: &str) -> bool {
+    static HOSTNAME: OnceLock<String> = OnceLock::new();
+    let hostname = HOSTNAME.get_or_init(|| {
+        fs::read_to_string("/proc/sys/kernel/hostname")
+            .map(|name| name.trim().to_string())
+            .unwrap_or_default()
+    });
+
+    machine == "localhost" || (!hostname.is_empty() && machine.eq_ignore_ascii_case(hostname))
+}
+
+fn ticks_to_duration(ticks: u64, per_second: u64) -> Duration {
+    Duration::from_secs_f64(ticks as f64 / per_second as f64)
+}
+
+fn uptime() -> Option<Duration> {
+    let raw = fs::read_to_string("/proc/uptime").ok()?;
+    let seconds: f64 = raw.split_whitespace().next()?.parse().ok()?;
+    Some(Duration::from_secs_f64(seconds))
+}
+
+/// The path of the cgroup v2 entry (`0::/path`), or the first listed hierarchy
+fn unified_cgroup(raw: &str) -> Option<String> {
+    let path_of = |line: &str| line.splitn(3, ':').nth(2).map(str::to_string);
+    raw.lines()
+        .find(|line| line.starts_with("0::"))
+        .and_then(path_of)
+        .or_else(|| raw.lines().next().and_then(path_of))
+}
@@ -0,0 +1,128 @@
+//! Recording sensor sessions to disk and playing them back (cargo feature `serde`).
+//!
+//! A recording is a JSON Lines file of `schema::EventRecord`s with `time_ms` set,
+//! so it can be inspected or edited with ordinary text tools.
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_stream::Stream;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::SensorEvent;
+use crate::schema::{self, EventRecord, IconEncoding};
+
+/// Writes sensor events with their offset from the start of the recording
+pub struct Recorder<W: Write> {
+    out: W,
+    start: Instant,
+    icons: IconEncoding,
+}
+
+impl Recorder<BufWriter<File>> {
+    /// Creates (or truncates) a recording file
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            start: Instant::now(),
+            icons: IconEncoding::default(),
+        }
+    }
+
+    /// Chooses how icon pixels are stored (full PNG by default)
+    pub fn icons(mut self, icons: IconEncoding) -> Self {
+        self.icons = icons;
+        self
+    }
+
+    /// Appends one event and flushes, so a crash still leaves a usable recording
+    pub fn record(&mut self, event: &SensorEvent) -> Result<(), Box<dyn Error>> {
+        let mut record = EventRecord::new(schema::strip_icons(event, self.icons));
+        record.time_ms = Some(self.start.elapsed().as_millis() as u64);
+
+        serde_json::to_writer(&mut self.out, &record)?;
+        self.out.write_all(b"\n")?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// A loaded recording that can stand in for the live sensor
+pub struct Replay {
+    records: Vec<EventRecord>,
+    speed: f64,
+}
+
+impl Replay {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a recording; a last line cut short by a crash while recording is dropped
+    pub fn from_reader(mut reader: impl BufRead) -> Result<Self, Box<dyn Error>> {
+        let mut records = Vec::new();
+        let mut line = String::new();
+        let mut number = 0;
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                break;
+            }
+            number += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match schema::from_json(&line) {
+                Ok(record) => records.push(record),
+                Err(_) if !line.ends_with('\n') => break,
+                Err(e) => return Err(format!("Line {}: {}", number, e).into()),
+            }
+        }
+
+        Ok(Self { records, speed: 1.0 })
+    }
+
+    /// Playback speed factor; `0.0` delivers every event immediately
+    pub fn speed(mut self, factor: f64) -> Self {
+        self.speed = factor.max(0.0);
+        self
+    }
+
+    /// The recorded events without their timing, for assertions in tests
+    pub fn into_events(self) -> Vec<SensorEvent> {
+        self.records.into_iter().map(|record| record.event).collect()
+    }
+
+    /// Plays the recording on a background thread, honouring the recorded timing
+    pub fn into_stream(self) -> impl Stream<Item = SensorEvent> + Unpin + Send + 'static {
+        let (tx, rx) = mpsc::channel(32);
+
+        thread::spawn(move || {
+            let start = Instant::now();
+            for record in self.records {
+                if self.speed > 0.0 {
+                    let offset = Duration::from_millis(record.time_ms.unwrap_or(0)).div_f64(self.speed);
+                    let elapsed = start.elapsed();
+                    if offset > elapsed {
+                        thread::sleep(offset - elapsed);
+                    }
+                }
+                if tx.blocking_send(record.event).is_err() {
+                    break;
+                }
+            }
+        });
+
+        ReceiverStream::new(rx)
+    }
+}
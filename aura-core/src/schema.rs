@@ -0,0 +1,195 @@
+//! Stable JSON representation of sensor events (cargo feature `serde`).
+//!
+//! Every event is wrapped in a record carrying the schema version:
+//!
+//! ```json
+//! {"version":1,"event":{"type":"window_open","data":{"xid":4194310,"title":"Terminal", ...}}}
+//! ```
+//!
+//! `event.type` is one of `full_scan` (`data` is an array of windows), `window_open`,
+//! `window_update` (`data` is a window), `window_close` or `focus_change` (`data` is the XID),
+//! or `capabilities` (`data` is `{"wm_name":"Mutter","supported":["client_list",...]}`).
+//!
+//! A window is an object with these fields:
+//!
+//! - `xid`: the X window ID
+//! - `title`: `_NET_WM_NAME`, or `WM_NAME` when that is missing
+//! - `class`: the class part of `WM_CLASS`
+//! - `is_active`: always `false`; focus is reported through `focus_change`
+//! - `icon_path`: a theme icon file for the window, or `null`
+//! - `icon_data`: the window's own `_NET_WM_ICON` when no theme icon was found, or `null`.
+//!   An icon is `{"width":48,"height":48,"png":"<base64>"}`; `png` is left out when
+//!   the pixels were omitted with `IconEncoding::Omit`.
+//! - `transient_for`: the XID from `WM_TRANSIENT_FOR`, the window this one is a dialog
+//!   of (may be the root window for group dialogs), or `null`
+//! - `leader`: the XID from `WM_CLIENT_LEADER` or the `WM_HINTS` window group, shared by
+//!   all windows of one app, or `null`
+//! - `is_dialog`: the window type is `_NET_WM_WINDOW_TYPE_DIALOG`
+//! - `is_modal`: `_NET_WM_STATE_MODAL`, it blocks input to `transient_for`
+//! - `pid`: `_NET_WM_PID`, or `null` unless the window belongs to a process on this machine
+//! - `unresponsive`: the window did not answer a `_NET_WM_PING` in time
+//! - `is_fullscreen`: `_NET_WM_STATE_FULLSCREEN`
+//! - `geometry`: `{"x":0,"y":0,"width":640,"height":480}`, the client area in root window
+//!   coordinates without the window manager's frame, or `null`
+//! - `frame_extents`: `{"left":1,"right":1,"top":24,"bottom":1}` from `_NET_FRAME_EXTENTS`,
+//!   how far the frame reaches past `geometry`, or `null`
+//! - `is_hidden`: `_NET_WM_STATE_HIDDEN`, minimized and not on screen despite its geometry
+//! - `desktop_id`: the desktop file ID of the installed app the window belongs to,
+//!   e.g. `org.gnome.Terminal.desktop`, or `null`
+//! - `is_maximized`: both `_NET_WM_STATE_MAXIMIZED_VERT` and `_NET_WM_STATE_MAXIMIZED_HORZ`
+//!
+//! Adding fields or event types keeps the version, so readers must ignore unknown
+//! fields, and fields added since a record was written are missing from it (read
+//! them as `null` or `false`). Renaming, removing or changing the meaning of
+//! anything bumps `SCHEMA_VERSION`.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::de::Error as _;
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::error::Error;
+
+use crate::{RawIcon, SensorEvent, WindowInfo};
+
+/// Version of the JSON representation described in the module docs
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// How icon pixels are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IconEncoding {
+    /// PNG, base64 encoded
+    #[default]
+    Png,
+    /// Keep the icon size but drop the pixels, for compact logs
+    Omit,
+}
+
+/// A versioned event as it appears on the wire
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventRecord {
+    pub version: u32,
+    /// Milliseconds since the start of a recording, when the record comes from one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_ms: Option<u64>,
+    pub event: SensorEvent,
+}
+
+impl EventRecord {
+    pub fn new(event: SensorEvent) -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            time_ms: None,
+            event,
+        }
+    }
+}
+
+/// Serializes an event as a single-line JSON record
+pub fn to_json(event: &SensorEvent, icons: IconEncoding) -> Result<String, Box<dyn Error>> {
+    let record = EventRecord::new(strip_icons(event, icons));
+    Ok(serde_json::to_string(&record)?)
+}
+
+/// Parses a JSON record, rejecting versions newer than this build understands
+pub fn from_json(json: &str) -> Result<EventRecord, Box<dyn Error>> {
+    let record: EventRecord = serde_json::from_str(json)?;
+    if record.version > SCHEMA_VERSION {
+        return Err(format!(
+            "Unsupported event schema version {} (newest known is {})",
+            record.version, SCHEMA_VERSION
+        ).into());
+    }
+    Ok(record)
+}
+
+/// Returns a copy of the event with icon pixels removed when `icons` asks for it
+pub fn strip_icons(event: &SensorEvent, icons: IconEncoding) -> SensorEvent {
+    let strip = |info: &WindowInfo| {
+        let mut info = info.clone();
+        if icons == IconEncoding::Omit
+            && let Some(icon) = &mut info.icon_data
+        {
+            icon.data.clear();
+        }
+        info
+    };
+
+    match event {
+        SensorEvent::FullScan(windows) => SensorEvent::FullScan(windows.iter().map(strip).collect()),
+        SensorEvent::WindowOpen(info) => SensorEvent::WindowOpen(strip(info)),
+        SensorEvent::WindowUpdate(info) => SensorEvent::WindowUpdate(strip(info)),
+        SensorEvent::WindowClose(id) => SensorEvent::WindowClose(*id),
+        SensorEvent::FocusChange(id) => SensorEvent::FocusChange(*id),
+        SensorEvent::Capabilities(capabilities) => SensorEvent::Capabilities(capabilities.clone()),
+    }
+}
+
+/// Wire form of `RawIcon`
+#[derive(Serialize, Deserialize)]
+struct IconRepr {
+    width: u32,
+    height: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    png: Option<String>,
+}
+
+impl Serialize for RawIcon {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let png = if self.data.is_empty() {
+            None
+        } else {
+            let bytes = encode_png(self).map_err(S::Error::custom)?;
+            Some(BASE64.encode(bytes))
+        };
+
+        IconRepr {
+            width: self.width,
+            height: self.height,
+            png,
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RawIcon {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = IconRepr::deserialize(deserializer)?;
+        let data = match repr.png {
+            Some(png) => {
+                let bytes = BASE64.decode(png).map_err(D::Error::custom)?;
+                decode_png(&bytes).map_err(D::Error::custom)?
+            }
+            None => Vec::new(),
+        };
+
+        Ok(RawIcon {
+            width: repr.width,
+            height: repr.height,
+            data,
+        })
+    }
+}
+
+fn encode_png(icon: &RawIcon) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, icon.width, icon.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&icon.data)?;
+    Ok(bytes)
+}
+
+/// Decodes a PNG into RGBA bytes, expanding palette, grayscale and RGB images
+fn decode_png(bytes: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8() | png::Transformations::ALPHA);
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buf)?;
+    buf.truncate(frame.buffer_size());
+
+    if frame.color_type != png::ColorType::Rgba {
+        return Err(format!("Unsupported icon color type {:?}", frame.color_type).into());
+    }
+    Ok(buf)
+}
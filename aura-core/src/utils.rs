@@ -1,6 +1,12 @@
 use x11rb::rust_connection::RustConnection;
 use x11rb::protocol::xproto::{Atom, ConnectionExt};
+use x11rb::connection::Connection;
+use x11rb::protocol::Event;
+use rustix::event::{poll, PollFd, PollFlags, Timespec};
+use rustix::io::Errno;
 use std::error::Error;
+use std::os::fd::BorrowedFd;
+use std::time::{Duration, Instant};
 
 pub fn get_string_property(conn: &RustConnection, window: u32, property: Atom, type_atom: Atom) -> Result<String, Box<dyn Error>> {
     let reply = conn.get_property(false, window, property, type_atom, 0, 4096)?.reply()?;
@@ -34,4 +40,40 @@ pub fn get_u32_vector_property(
     let list: Vec<u32> = reply.value32().ok_or("Failed to parse 32-bit values")?.collect();
 
     Ok(list)
-}
\ No newline at end of file
+}
+/// Waits for the next X event, giving up after `timeout` (or blocking forever when `None`).
+///
+/// Returns `None` on timeout, or as soon as one of the `wake` fds becomes readable.
+pub fn wait_for_event_timeout(conn: &RustConnection, wake: &[BorrowedFd<'_>], timeout: Option<Duration>) -> Result<Option<Event>, Box<dyn Error>> {
+    conn.flush()?;
+    let deadline = timeout.map(|t| Instant::now() + t);
+
+    loop {
+        // Events may already be buffered by x11rb, so always drain before polling the socket
+        if let Some(event) = conn.poll_for_event()? {
+            return Ok(Some(event));
+        }
+
+        let remaining = match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Ok(None);
+                }
+                Some(Timespec::try_from(deadline - now)?)
+            }
+            None => None,
+        };
+
+        let mut fds = vec![PollFd::new(conn.stream(), PollFlags::IN)];
+        fds.extend(wake.iter().map(|wake| PollFd::new(wake, PollFlags::IN)));
+        match poll(&mut fds, remaining.as_ref()) {
+            Ok(_) | Err(Errno::INTR) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        if fds[1..].iter().any(|fd| !fd.revents().is_empty()) {
+            return Ok(None);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use aura_core::coalesce::EventQueue;
+use aura_core::{SensorEvent, WindowInfo};
+use tokio::sync::mpsc;
+
+fn window(xid: u32, title: &str) -> WindowInfo {
+    WindowInfo {
+        xid,
+        title: title.to_string(),
+        class: "App".to_string(),
+        ..Default::default()
+    }
+}
+
+/// Pushes `events` and returns what a consumer would receive
+fn coalesce(events: Vec<SensorEvent>) -> Vec<SensorEvent> {
+    let mut queue = EventQueue::new();
+    for event in events {
+        queue.push(event);
+    }
+    let (tx, mut rx) = mpsc::channel(64);
+    assert!(queue.flush(&tx));
+    assert!(queue.is_empty());
+
+    let mut delivered = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        delivered.push(event);
+    }
+    delivered
+}
+
+#[test]
+fn drops_windows_that_open_and_close_before_delivery() {
+    let delivered = coalesce(vec![
+        SensorEvent::WindowOpen(window(1, "Splash")),
+        SensorEvent::WindowOpen(window(2, "Main")),
+        SensorEvent::WindowUpdate(window(1, "Splash 50%")),
+        SensorEvent::WindowClose(1),
+    ]);
+    assert_eq!(delivered, vec![SensorEvent::WindowOpen(window(2, "Main"))]);
+}
+
+#[test]
+fn folds_updates_into_the_latest_state() {
+    let delivered = coalesce(vec![
+        SensorEvent::WindowOpen(window(1, "Editor")),
+        SensorEvent::FocusChange(1),
+        SensorEvent::WindowUpdate(window(2, "Terminal - 1")),
+        SensorEvent::WindowUpdate(window(1, "Editor - notes.txt")),
+        SensorEvent::WindowUpdate(window(2, "Terminal - 2")),
+        SensorEvent::FocusChange(2),
+    ]);
+    assert_eq!(delivered, vec![
+        SensorEvent::WindowOpen(window(1, "Editor - notes.txt")),
+        SensorEvent::WindowUpdate(window(2, "Terminal - 2")),
+        SensorEvent::FocusChange(2),
+    ]);
+}
+
+#[test]
+fn clears_focus_of_closed_windows() {
+    let delivered = coalesce(vec![
+        SensorEvent::FocusChange(1),
+        SensorEvent::WindowUpdate(window(1, "Dialog")),
+        SensorEvent::WindowClose(1),
+    ]);
+    assert_eq!(delivered, vec![SensorEvent::FocusChange(0), SensorEvent::WindowClose(1)]);
+
+    // Focus of another window survives
+    let delivered = coalesce(vec![SensorEvent::FocusChange(2), SensorEvent::WindowClose(1)]);
+    assert_eq!(delivered, vec![SensorEvent::FocusChange(2), SensorEvent::WindowClose(1)]);
+}
+
+#[test]
+fn full_scans_replace_everything_queued() {
+    let delivered = coalesce(vec![
+        SensorEvent::WindowOpen(window(1, "Old")),
+        SensorEvent::FocusChange(1),
+        SensorEvent::FullScan(vec![window(2, "New")]),
+    ]);
+    assert_eq!(delivered, vec![SensorEvent::FullScan(vec![window(2, "New")])]);
+}
+
+#[test]
+fn gives_up_on_events_past_the_limit() {
+    let mut queue = EventQueue::with_limit(2);
+    queue.push(SensorEvent::WindowOpen(window(1, "One")));
+    queue.push(SensorEvent::WindowOpen(window(2, "Two")));
+    assert!(!queue.take_overflow());
+
+    queue.push(SensorEvent::WindowOpen(window(3, "Three")));
+    assert!(queue.is_empty());
+    assert!(queue.take_overflow());
+    assert!(!queue.take_overflow());
+
+    // The full scan the owner queues in response settles it
+    queue.push(SensorEvent::WindowOpen(window(4, "Four")));
+    queue.push(SensorEvent::WindowOpen(window(5, "Five")));
+    queue.push(SensorEvent::WindowOpen(window(6, "Six")));
+    queue.push(SensorEvent::FullScan(vec![window(6, "Six")]));
+    assert!(!queue.take_overflow());
+    assert!(!queue.is_empty());
+}
+
+#[test]
+fn keeps_events_the_channel_has_no_room_for() {
+    let mut queue = EventQueue::new();
+    queue.push(SensorEvent::WindowOpen(window(1, "One")));
+    queue.push(SensorEvent::WindowOpen(window(2, "Two")));
+
+    let (tx, mut rx) = mpsc::channel(1);
+    assert!(queue.flush(&tx));
+    assert!(!queue.is_empty());
+    assert_eq!(rx.try_recv().unwrap(), SensorEvent::WindowOpen(window(1, "One")));
+
+    assert!(queue.flush(&tx));
+    assert!(queue.is_empty());
+    assert_eq!(rx.try_recv().unwrap(), SensorEvent::WindowOpen(window(2, "Two")));
+
+    drop(rx);
+    queue.push(SensorEvent::FocusChange(2));
+    assert!(!queue.flush(&tx));
+}
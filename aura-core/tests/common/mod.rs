@@ -0,0 +1,502 @@
+//! Headless X11 test harness: an Xvfb server, a minimal EWMH window manager
+//! stand-in and helpers for creating client windows.
+//!
+//! Tests call `setup()` and return early when it yields `None`, so the suite
+//! still passes on machines without Xvfb installed.
+
+#![allow(dead_code)]
+
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use aura_core::{Capabilities, SensorBuilder, SensorEvent};
+use rustix::process::{kill_process, Pid, Signal};
+use tokio::sync::mpsc;
+use tokio_stream::StreamExt;
+use x11rb::connection::Connection;
+use x11rb::protocol::Event;
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ChangeWindowAttributesAux, ConfigureWindowAux, ConnectionExt, CreateWindowAux,
+    ClientMessageEvent, EventMask, PropMode, WindowClass,
+};
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+
+/// How long to wait for something to happen before failing a test
+pub const TIMEOUT: Duration = Duration::from_secs(3);
+/// How long to watch for unexpected events
+pub const QUIET_PERIOD: Duration = Duration::from_millis(300);
+
+/// Tests share the DISPLAY environment variable, so they run one at a time
+static SERIAL: Mutex<()> = Mutex::new(());
+
+/// Everything a test needs; dropping it tears the X server down
+pub struct TestEnv {
+    pub client: Client,
+    _server: XServer,
+    _guard: MutexGuard<'static, ()>,
+}
+
+/// Starts Xvfb with the stand-in window manager, or returns `None` if Xvfb is not installed
+pub fn setup() -> Option<TestEnv> {
+    setup_with(WmOptions::default())
+}
+
+pub fn setup_with(options: WmOptions) -> Option<TestEnv> {
+    let guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+
+    let Some(server) = XServer::start() else {
+        eprintln!("Xvfb not found, skipping X11 integration test");
+        return None;
+    };
+    start_window_manager(options);
+    let client = Client::connect();
+
+    Some(TestEnv {
+        client,
+        _server: server,
+        _guard: guard,
+    })
+}
+
+pub struct XServer {
+    process: Child,
+}
+
+impl XServer {
+    fn start() -> Option<Self> {
+        let display = (90..200)
+            .find(|n| !Path::new(&format!("/tmp/.X{}-lock", n)).exists())
+            .expect("No free X display number");
+
+        let process = Command::new("Xvfb")
+            .arg(format!(":{}", display))
+            .args(["-screen", "0", "1280x800x24", "-nolisten", "tcp"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+
+        // Safe enough here: tests are serialized and the sensor and helpers only read
+        // DISPLAY while connecting.
+        unsafe { std::env::set_var("DISPLAY", format!(":{}", display)) };
+
+        let server = Self { process };
+        let deadline = Instant::now() + TIMEOUT;
+        while RustConnection::connect(None).is_err() {
+            assert!(Instant::now() < deadline, "Xvfb did not come up on :{}", display);
+            thread::sleep(Duration::from_millis(20));
+        }
+        Some(server)
+    }
+}
+
+impl Drop for XServer {
+    fn drop(&mut self) {
+        // SIGTERM rather than SIGKILL, so Xvfb removes its lock file
+        let _ = kill_process(Pid::from_child(&self.process), Signal::TERM);
+        let _ = self.process.wait();
+    }
+}
+
+pub fn atom(conn: &RustConnection, name: &str) -> Atom {
+    conn.intern_atom(false, name.as_bytes()).unwrap().reply().unwrap().atom
+}
+
+/// What the stand-in window manager advertises and maintains
+#[derive(Clone)]
+pub struct WmOptions {
+    pub ewmh: bool,
+}
+
+impl Default for WmOptions {
+    fn default() -> Self {
+        Self { ewmh: true }
+    }
+}
+
+/// A tiny reparent-free window manager: it maps windows, keeps `_NET_CLIENT_LIST`
+/// and `WM_STATE` up to date and honours `_NET_ACTIVE_WINDOW` and `_NET_WM_STATE` requests.
+fn start_window_manager(options: WmOptions) {
+    let (conn, screen_num) = RustConnection::connect(None).unwrap();
+    let root = conn.setup().roots[screen_num].root;
+
+    conn.change_window_attributes(
+        root,
+        &ChangeWindowAttributesAux::new()
+            .event_mask(EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY),
+    ).unwrap().check().expect("Another window manager is running");
+
+    if options.ewmh {
+        let check = conn.generate_id().unwrap();
+        conn.create_window(0, check, root, -1, -1, 1, 1, 0, WindowClass::INPUT_ONLY, 0, &CreateWindowAux::new()).unwrap();
+
+        let supporting = atom(&conn, "_NET_SUPPORTING_WM_CHECK");
+        conn.change_property32(PropMode::REPLACE, root, supporting, AtomEnum::WINDOW, &[check]).unwrap();
+        conn.change_property32(PropMode::REPLACE, check, supporting, AtomEnum::WINDOW, &[check]).unwrap();
+        let utf8 = atom(&conn, "UTF8_STRING");
+        conn.change_property8(PropMode::REPLACE, check, atom(&conn, "_NET_WM_NAME"), utf8, b"aura-test-wm").unwrap();
+
+        let supported: Vec<Atom> = [
+            "_NET_SUPPORTED",
+            "_NET_SUPPORTING_WM_CHECK",
+            "_NET_CLIENT_LIST",
+            "_NET_ACTIVE_WINDOW",
+            "_NET_WM_NAME",
+            "_NET_WM_STATE",
+            "_NET_WM_STATE_ABOVE",
+            "_NET_WM_STATE_SKIP_TASKBAR",
+            "_NET_WM_STATE_SKIP_PAGER",
+            "_NET_WM_WINDOW_TYPE",
+        ].iter().map(|name| atom(&conn, name)).collect();
+        conn.change_property32(PropMode::REPLACE, root, atom(&conn, "_NET_SUPPORTED"), AtomEnum::ATOM, &supported).unwrap();
+        conn.change_property32(PropMode::REPLACE, root, atom(&conn, "_NET_CLIENT_LIST"), AtomEnum::WINDOW, &[]).unwrap();
+    }
+    conn.flush().unwrap();
+
+    thread::spawn(move || run_window_manager(conn, root, options));
+}
+
+fn run_window_manager(conn: RustConnection, root: u32, options: WmOptions) {
+    let client_list = atom(&conn, "_NET_CLIENT_LIST");
+    let active_window = atom(&conn, "_NET_ACTIVE_WINDOW");
+    let wm_state = atom(&conn, "WM_STATE");
+    let net_wm_state = atom(&conn, "_NET_WM_STATE");
+    let mut clients: Vec<u32> = Vec::new();
+
+    // Ends when the test drops the X server
+    while let Ok(event) = conn.wait_for_event() {
+        let before = clients.clone();
+
+        match event {
+            Event::MapRequest(e) => {
+                conn.change_property32(PropMode::REPLACE, e.window, wm_state, wm_state, &[1, 0]).ok();
+                conn.map_window(e.window).ok();
+                if !clients.contains(&e.window) {
+                    clients.push(e.window);
+                }
+            }
+            Event::ConfigureRequest(e) => {
+                conn.configure_window(e.window, &ConfigureWindowAux::from_configure_request(&e)).ok();
+            }
+            Event::UnmapNotify(e) if e.event == root => {
+                clients.retain(|&w| w != e.window);
+            }
+            Event::DestroyNotify(e) => {
+                clients.retain(|&w| w != e.window);
+            }
+            Event::ClientMessage(e) if options.ewmh && e.type_ == active_window => {
+                conn.change_property32(PropMode::REPLACE, root, active_window, AtomEnum::WINDOW, &[e.window]).ok();
+            }
+            Event::ClientMessage(e) if options.ewmh && e.type_ == net_wm_state => {
+                let [action, first, second, _, _] = e.data.as_data32();
+                let mut state = read_atoms(&conn, e.window, net_wm_state);
+                for property in [first, second].into_iter().filter(|&p| p != 0) {
+                    let present = state.contains(&property);
+                    // 0 = remove, 1 = add, 2 = toggle
+                    let want = match action {
+                        0 => false,
+                        1 => true,
+                        _ => !present,
+                    };
+                    if want && !present {
+                        state.push(property);
+                    } else if !want && present {
+                        state.retain(|&p| p != property);
+                    }
+                }
+                conn.change_property32(PropMode::REPLACE, e.window, net_wm_state, AtomEnum::ATOM, &state).ok();
+            }
+            _ => {}
+        }
+
+        if options.ewmh && clients != before {
+            conn.change_property32(PropMode::REPLACE, root, client_list, AtomEnum::WINDOW, &clients).ok();
+        }
+        if conn.flush().is_err() {
+            break;
+        }
+    }
+}
+
+fn read_atoms(conn: &RustConnection, window: u32, property: Atom) -> Vec<Atom> {
+    read_u32s(conn, window, property, AtomEnum::ATOM.into())
+}
+
+fn read_u32s(conn: &RustConnection, window: u32, property: Atom, type_: Atom) -> Vec<u32> {
+    conn.get_property(false, window, property, type_, 0, 1024)
+        .ok()
+        .and_then(|cookie| cookie.reply().ok())
+        .and_then(|reply| reply.value32().map(|values| values.collect()))
+        .unwrap_or_default()
+}
+
+/// An ordinary X client used to create and inspect windows
+pub struct Client {
+    pub conn: RustConnection,
+    pub root: u32,
+}
+
+impl Client {
+    pub fn connect() -> Self {
+        let (conn, screen_num) = RustConnection::connect(None).unwrap();
+        let root = conn.setup().roots[screen_num].root;
+        Self { conn, root }
+    }
+
+    pub fn atom(&self, name: &str) -> Atom {
+        atom(&self.conn, name)
+    }
+
+    /// Creates an unmapped top-level window with a title and WM_CLASS
+    pub fn create_window(&self, title: &str, instance: &str, class: &str) -> u32 {
+        let screen = &self.conn.setup().roots[0];
+        let window = self.conn.generate_id().unwrap();
+        self.conn.create_window(
+            screen.root_depth,
+            window,
+            self.root,
+            0,
+            0,
+            200,
+            100,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            screen.root_visual,
+            &CreateWindowAux::new(),
+        ).unwrap();
+
+        self.set_title(window, title);
+        let wm_class = format!("{}\0{}\0", instance, class);
+        self.conn.change_property8(PropMode::REPLACE, window, AtomEnum::WM_CLASS, AtomEnum::STRING, wm_class.as_bytes()).unwrap();
+        self.conn.flush().unwrap();
+        window
+    }
+
+    /// Sets both the legacy and the EWMH title
+    pub fn set_title(&self, window: u32, title: &str) {
+        let utf8 = self.atom("UTF8_STRING");
+        self.conn.change_property8(PropMode::REPLACE, window, AtomEnum::WM_NAME, AtomEnum::STRING, title.as_bytes()).unwrap();
+        self.conn.change_property8(PropMode::REPLACE, window, self.atom("_NET_WM_NAME"), utf8, title.as_bytes()).unwrap();
+        self.conn.flush().unwrap();
+    }
+
+    /// Replaces the title with only a legacy `WM_NAME` of the given type, e.g. `"COMPOUND_TEXT"`
+    pub fn set_legacy_title(&self, window: u32, title: &str, type_: &str) {
+        self.conn.delete_property(window, self.atom("_NET_WM_NAME")).unwrap();
+        self.conn.change_property8(PropMode::REPLACE, window, AtomEnum::WM_NAME, self.atom(type_), title.as_bytes()).unwrap();
+        self.conn.flush().unwrap();
+    }
+
+    /// Sets `_NET_WM_ICON` from ARGB pixels
+    pub fn set_icon(&self, window: u32, width: u32, height: u32, argb: &[u32]) {
+        let mut data = vec![width, height];
+        data.extend_from_slice(argb);
+        self.conn.change_property32(PropMode::REPLACE, window, self.atom("_NET_WM_ICON"), AtomEnum::CARDINAL, &data).unwrap();
+        self.conn.flush().unwrap();
+    }
+
+    /// Sets `_NET_WM_WINDOW_TYPE`, e.g. `"_NET_WM_WINDOW_TYPE_DIALOG"`
+    pub fn set_window_type(&self, window: u32, window_type: &str) {
+        let value = self.atom(window_type);
+        self.conn.change_property32(PropMode::REPLACE, window, self.atom("_NET_WM_WINDOW_TYPE"), AtomEnum::ATOM, &[value]).unwrap();
+        self.conn.flush().unwrap();
+    }
+
+    pub fn set_transient_for(&self, window: u32, parent: u32) {
+        self.conn.change_property32(PropMode::REPLACE, window, AtomEnum::WM_TRANSIENT_FOR, AtomEnum::WINDOW, &[parent]).unwrap();
+        self.conn.flush().unwrap();
+    }
+
+    pub fn set_client_leader(&self, window: u32, leader: u32) {
+        self.conn.change_property32(PropMode::REPLACE, window, self.atom("WM_CLIENT_LEADER"), AtomEnum::WINDOW, &[leader]).unwrap();
+        self.conn.flush().unwrap();
+    }
+
+    /// Asks for a new position and size, like a client resizing itself
+    pub fn move_resize(&self, window: u32, x: i32, y: i32, width: u32, height: u32) {
+        let aux = ConfigureWindowAux::new().x(x).y(y).width(width).height(height);
+        self.conn.configure_window(window, &aux).unwrap();
+        self.conn.flush().unwrap();
+    }
+
+    /// Sets `_NET_FRAME_EXTENTS` like a decorating window manager would
+    pub fn set_frame_extents(&self, window: u32, left: u32, right: u32, top: u32, bottom: u32) {
+        let extents = [left, right, top, bottom];
+        self.conn.change_property32(PropMode::REPLACE, window, self.atom("_NET_FRAME_EXTENTS"), AtomEnum::CARDINAL, &extents).unwrap();
+        self.conn.flush().unwrap();
+    }
+
+    /// Replaces `_NET_WM_STATE` directly, as a client may do before mapping
+    pub fn set_state(&self, window: u32, states: &[&str]) {
+        let values: Vec<Atom> = states.iter().map(|name| self.atom(name)).collect();
+        self.conn.change_property32(PropMode::REPLACE, window, self.atom("_NET_WM_STATE"), AtomEnum::ATOM, &values).unwrap();
+        self.conn.flush().unwrap();
+    }
+
+    /// Sets `_NET_WM_PID` and the host name the client claims to run on
+    pub fn set_pid(&self, window: u32, pid: u32, machine: &str) {
+        self.conn.change_property32(PropMode::REPLACE, window, self.atom("_NET_WM_PID"), AtomEnum::CARDINAL, &[pid]).unwrap();
+        self.conn.change_property8(PropMode::REPLACE, window, AtomEnum::WM_CLIENT_MACHINE, AtomEnum::STRING, machine.as_bytes()).unwrap();
+        self.conn.flush().unwrap();
+    }
+
+    pub fn set_protocols(&self, window: u32, protocols: &[&str]) {
+        let values: Vec<Atom> = protocols.iter().map(|name| self.atom(name)).collect();
+        self.conn.change_property32(PropMode::REPLACE, window, self.atom("WM_PROTOCOLS"), AtomEnum::ATOM, &values).unwrap();
+        self.conn.flush().unwrap();
+    }
+
+    /// Waits for a `_NET_WM_PING` and sends it back to the root like a live toolkit would
+    pub fn answer_ping(&self, timeout: Duration) -> bool {
+        let protocols = self.atom("WM_PROTOCOLS");
+        let ping = self.atom("_NET_WM_PING");
+        let deadline = Instant::now() + timeout;
+
+        while Instant::now() < deadline {
+            match self.conn.poll_for_event().unwrap() {
+                Some(Event::ClientMessage(e)) if e.type_ == protocols && e.data.as_data32()[0] == ping => {
+                    let pong = ClientMessageEvent { window: self.root, ..e };
+                    self.conn.send_event(
+                        false,
+                        self.root,
+                        EventMask::SUBSTRUCTURE_NOTIFY | EventMask::SUBSTRUCTURE_REDIRECT,
+                        pong,
+                    ).unwrap();
+                    self.conn.flush().unwrap();
+                    return true;
+                }
+                Some(_) => {}
+                None => thread::sleep(Duration::from_millis(5)),
+            }
+        }
+        false
+    }
+
+    pub fn map(&self, window: u32) {
+        self.conn.map_window(window).unwrap();
+        self.conn.flush().unwrap();
+    }
+
+    pub fn destroy(&self, window: u32) {
+        self.conn.destroy_window(window).unwrap();
+        self.conn.flush().unwrap();
+    }
+
+    /// Maps a window and waits until the window manager lists it
+    pub fn map_and_wait(&self, window: u32) {
+        self.map(window);
+        // WM_STATE is set with or without EWMH, _NET_CLIENT_LIST only exists with it
+        let wm_state = self.atom("WM_STATE");
+        let has_client_list = self.conn
+            .get_property(false, self.root, self.atom("_NET_CLIENT_LIST"), AtomEnum::ANY, 0, 0).unwrap()
+            .reply().unwrap()
+            .type_ != x11rb::NONE;
+        wait_until("window to be managed", || {
+            !read_u32s(&self.conn, window, wm_state, wm_state).is_empty()
+                && (!has_client_list || self.client_list().contains(&window))
+        });
+    }
+
+    pub fn client_list(&self) -> Vec<u32> {
+        read_u32s(&self.conn, self.root, self.atom("_NET_CLIENT_LIST"), AtomEnum::WINDOW.into())
+    }
+
+    pub fn active_window(&self) -> Option<u32> {
+        read_u32s(&self.conn, self.root, self.atom("_NET_ACTIVE_WINDOW"), AtomEnum::WINDOW.into())
+            .first()
+            .cloned()
+    }
+
+    /// Names of the atoms in a window's `_NET_WM_STATE`
+    pub fn wm_state(&self, window: u32) -> Vec<String> {
+        read_atoms(&self.conn, window, self.atom("_NET_WM_STATE"))
+            .into_iter()
+            .map(|a| {
+                let reply = self.conn.get_atom_name(a).unwrap().reply().unwrap();
+                String::from_utf8_lossy(&reply.name).to_string()
+            })
+            .collect()
+    }
+}
+
+/// Polls `condition` until it holds, panicking after `TIMEOUT`
+pub fn wait_until(what: &str, mut condition: impl FnMut() -> bool) {
+    let deadline = Instant::now() + TIMEOUT;
+    while !condition() {
+        assert!(Instant::now() < deadline, "Timed out waiting for {}", what);
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Runs `run_sensor_loop`, or a `SensorBuilder` sensor, on a thread and collects its events
+pub struct SensorProbe {
+    rx: mpsc::Receiver<SensorEvent>,
+    /// What the sensor reported about the WM before anything else
+    pub capabilities: Capabilities,
+}
+
+impl SensorProbe {
+    pub fn start() -> Self {
+        let (tx, rx) = mpsc::channel(32);
+        thread::spawn(move || {
+            aura_core::notification_loop::run_sensor_loop(tx).ok();
+        });
+        Self::receive(rx)
+    }
+
+    /// Starts the sensor `builder` configures
+    pub fn build(builder: SensorBuilder) -> Self {
+        let (mut events, handle) = builder.build().unwrap();
+        let (tx, rx) = mpsc::channel(32);
+        thread::spawn(move || {
+            let _handle = handle;
+            let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+            runtime.block_on(async {
+                while let Some(event) = events.next().await {
+                    if tx.send(event).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        });
+        Self::receive(rx)
+    }
+
+    fn receive(rx: mpsc::Receiver<SensorEvent>) -> Self {
+        let mut probe = Self { rx, capabilities: Capabilities::default() };
+        match probe.next() {
+            SensorEvent::Capabilities(capabilities) => probe.capabilities = capabilities,
+            other => panic!("Expected capabilities as the first event, got {:?}", other),
+        }
+        probe
+    }
+
+    /// Waits for the next event, panicking after `TIMEOUT`
+    pub fn next(&mut self) -> SensorEvent {
+        self.next_within(TIMEOUT).expect("Timed out waiting for a sensor event")
+    }
+
+    pub fn next_within(&mut self, timeout: Duration) -> Option<SensorEvent> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Ok(event) = self.rx.try_recv() {
+                return Some(event);
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// Asserts that no further events arrive for a while
+    pub fn assert_quiet(&mut self) {
+        if let Some(event) = self.next_within(QUIET_PERIOD) {
+            panic!("Unexpected sensor event: {:?}", event);
+        }
+    }
+}
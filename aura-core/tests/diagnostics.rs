@@ -0,0 +1,30 @@
+mod common;
+
+use aura_core::diagnostics;
+
+#[test]
+fn reports_wm_capabilities_and_filtered_windows() {
+    let Some(env) = common::setup() else { return };
+    let client = &env.client;
+
+    let editor = client.create_window("Editor", "editor", "AuraTestApp");
+    client.set_window_type(editor, "_NET_WM_WINDOW_TYPE_NORMAL");
+    client.map_and_wait(editor);
+    let dock = client.create_window("Aura Dock", "aura-ui", "aura-ui");
+    client.map_and_wait(dock);
+
+    let report = diagnostics::collect().unwrap();
+    assert_eq!(report.wm_name.as_deref(), Some("aura-test-wm"));
+    assert!(!report.wm_check_stale);
+    assert!(report.supported.contains(&"_NET_CLIENT_LIST".to_string()));
+
+    let editor_report = report.clients.iter().find(|c| c.xid == editor).unwrap();
+    assert_eq!(editor_report.instance, "editor");
+    assert_eq!(editor_report.class, "AuraTestApp");
+    assert_eq!(editor_report.window_types, vec!["_NET_WM_WINDOW_TYPE_NORMAL".to_string()]);
+    assert!(!editor_report.skipped);
+    assert_eq!(editor_report.icon_path, None);
+
+    let dock_report = report.clients.iter().find(|c| c.xid == dock).unwrap();
+    assert!(dock_report.skipped);
+}
@@ -0,0 +1,33 @@
+use aura_core::{FrameExtents, Geometry, WindowInfo};
+
+fn rect(x: i32, y: i32, width: u32, height: u32) -> Geometry {
+    Geometry { x, y, width, height }
+}
+
+#[test]
+fn detects_overlap_and_coverage() {
+    let monitor = rect(0, 0, 1920, 1080);
+    let dock = rect(600, 1000, 720, 80);
+
+    assert!(rect(0, 0, 1920, 1080).covers(&monitor));
+    assert!(rect(-10, -10, 1940, 1100).covers(&monitor));
+    assert!(!rect(0, 0, 1920, 1000).covers(&monitor));
+
+    assert!(rect(500, 900, 200, 101).intersects(&dock));
+    // Touching edges don't overlap
+    assert!(!rect(500, 900, 200, 100).intersects(&dock));
+    assert!(!rect(1320, 1000, 100, 80).intersects(&dock));
+    // A window on the monitor to the right
+    assert!(!rect(1920, 0, 1920, 1080).intersects(&monitor));
+}
+
+#[test]
+fn outer_geometry_includes_the_frame() {
+    let mut info = WindowInfo { geometry: Some(rect(100, 130, 800, 600)), ..Default::default() };
+    assert_eq!(info.outer_geometry(), Some(rect(100, 130, 800, 600)));
+
+    info.frame_extents = Some(FrameExtents { left: 2, right: 2, top: 30, bottom: 4 });
+    assert_eq!(info.outer_geometry(), Some(rect(98, 100, 804, 634)));
+
+    assert_eq!(WindowInfo::default().outer_geometry(), None);
+}
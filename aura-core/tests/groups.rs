@@ -0,0 +1,131 @@
+use aura_core::groups::WindowGroups;
+use aura_core::{SensorEvent, WindowInfo};
+
+fn window(xid: u32) -> WindowInfo {
+    WindowInfo {
+        xid,
+        title: format!("Window {}", xid),
+        class: "App".to_string(),
+        ..Default::default()
+    }
+}
+
+fn dialog(xid: u32, transient_for: Option<u32>, leader: Option<u32>) -> WindowInfo {
+    WindowInfo {
+        transient_for,
+        leader,
+        is_dialog: true,
+        ..window(xid)
+    }
+}
+
+fn item_ids(groups: &WindowGroups) -> Vec<u32> {
+    groups.items().iter().map(|info| info.xid).collect()
+}
+
+#[test]
+fn folds_transient_dialogs_under_their_parent() {
+    let mut groups = WindowGroups::new();
+    groups.insert(window(1));
+    groups.insert(dialog(2, Some(1), None));
+    // A dialog of a dialog still belongs to the main window
+    groups.insert(dialog(3, Some(2), None));
+
+    assert_eq!(item_ids(&groups), vec![1]);
+    assert_eq!(groups.owner(3), Some(1));
+    assert_eq!(groups.children(1).len(), 2);
+    assert_eq!(groups.item_for(2), 1);
+}
+
+#[test]
+fn folds_group_dialogs_by_client_leader() {
+    let mut groups = WindowGroups::new();
+    groups.insert(WindowInfo { leader: Some(10), ..window(1) });
+    groups.insert(WindowInfo { leader: Some(20), ..window(2) });
+    // Transient for the root window, which the model doesn't know
+    groups.insert(dialog(3, Some(0x100), Some(20)));
+
+    assert_eq!(item_ids(&groups), vec![1, 2]);
+    assert_eq!(groups.owner(3), Some(2));
+}
+
+#[test]
+fn orphaned_dialogs_become_items() {
+    let mut groups = WindowGroups::new();
+    groups.insert(window(1));
+    groups.insert(dialog(2, Some(1), None));
+    assert_eq!(item_ids(&groups), vec![1]);
+
+    groups.remove(1);
+    assert_eq!(item_ids(&groups), vec![2]);
+}
+
+#[test]
+fn activates_the_newest_modal_dialog() {
+    let mut groups = WindowGroups::new();
+    groups.insert(window(1));
+    assert_eq!(groups.activation_target(1), 1);
+
+    groups.insert(dialog(2, Some(1), None));
+    assert_eq!(groups.activation_target(1), 1);
+
+    groups.insert(WindowInfo { is_modal: true, ..dialog(3, Some(1), None) });
+    groups.insert(WindowInfo { is_modal: true, ..dialog(4, Some(1), None) });
+    assert_eq!(groups.activation_target(1), 4);
+}
+
+#[test]
+fn survives_transient_cycles() {
+    let mut groups = WindowGroups::new();
+    groups.insert(dialog(1, Some(2), None));
+    groups.insert(dialog(2, Some(1), None));
+
+    assert_eq!(groups.owner(1), None);
+    assert_eq!(item_ids(&groups), vec![1, 2]);
+}
+
+#[test]
+fn full_scan_keeps_known_order() {
+    let mut groups = WindowGroups::new();
+    groups.apply(&SensorEvent::WindowOpen(window(5)));
+    groups.apply(&SensorEvent::WindowOpen(window(3)));
+
+    groups.apply(&SensorEvent::FullScan(vec![window(3), window(4), window(5)]));
+    assert_eq!(item_ids(&groups), vec![5, 3, 4]);
+
+    assert!(!groups.apply(&SensorEvent::FocusChange(3)));
+    groups.apply(&SensorEvent::WindowClose(5));
+    assert_eq!(item_ids(&groups), vec![3, 4]);
+}
+
+#[test]
+fn orders_items_by_recent_focus() {
+    let mut groups = WindowGroups::new();
+    groups.insert(window(1));
+    groups.insert(window(2));
+    groups.insert(window(3));
+    groups.insert(dialog(4, Some(1), None));
+
+    groups.apply(&SensorEvent::FocusChange(2));
+    // Focusing a dialog counts for the item it is folded under
+    groups.apply(&SensorEvent::FocusChange(4));
+    let recent: Vec<u32> = groups.recent_items().iter().map(|info| info.xid).collect();
+    assert_eq!(recent, vec![1, 2, 3]);
+
+    groups.apply(&SensorEvent::WindowClose(1));
+    let recent: Vec<u32> = groups.recent_items().iter().map(|info| info.xid).collect();
+    assert_eq!(recent, vec![2, 3, 4]);
+}
+
+#[test]
+fn full_scan_forgets_focus_of_vanished_windows() {
+    let mut groups = WindowGroups::new();
+    groups.apply(&SensorEvent::FullScan(vec![window(1), window(2), window(3)]));
+    groups.apply(&SensorEvent::FocusChange(3));
+
+    groups.apply(&SensorEvent::FullScan(vec![window(1), window(2)]));
+    // X reuses window ids, a new window must not inherit the old one's place
+    groups.apply(&SensorEvent::WindowOpen(window(3)));
+    let recent: Vec<u32> = groups.recent_items().iter().map(|info| info.xid).collect();
+    assert_eq!(recent, vec![1, 2, 3]);
+}
@@ -0,0 +1,27 @@
+use aura_core::hotkeys::Hotkey;
+use x11rb::protocol::xproto::ModMask;
+
+#[test]
+fn parses_modifiers_and_keys() {
+    assert_eq!(Hotkey::parse("Super+1").unwrap(), Hotkey::new(ModMask::M4, 0x31));
+    assert_eq!(Hotkey::parse("super+A").unwrap(), Hotkey::new(ModMask::M4, 0x61));
+    assert_eq!(
+        Hotkey::parse("Ctrl + Alt + space").unwrap(),
+        Hotkey::new(ModMask::CONTROL | ModMask::M1, 0x20)
+    );
+    assert_eq!(Hotkey::parse("F12").unwrap(), Hotkey::new(ModMask::from(0u16), 0xffc9));
+}
+
+#[test]
+fn rejects_unknown_names() {
+    assert!(Hotkey::parse("Hyper+1").is_err());
+    assert!(Hotkey::parse("Super+NoSuchKey").is_err());
+    assert!(Hotkey::parse("Super+").is_err());
+}
+
+#[test]
+fn displays_like_it_parses() {
+    for spec in ["Super+1", "Ctrl+Alt+Tab", "Shift+Super+F3", "Super+S"] {
+        assert_eq!(Hotkey::parse(spec).unwrap().to_string(), spec);
+    }
+}
@@ -0,0 +1,32 @@
+use aura_core::process::{is_local_machine, CpuSampler, ProcessInfo};
+
+#[test]
+fn reads_the_current_process() {
+    let info = ProcessInfo::read(std::process::id()).unwrap();
+
+    assert_eq!(info.pid, std::process::id());
+    assert_eq!(info.exe.as_deref(), std::env::current_exe().ok().as_deref());
+    assert!(info.exe_name().is_some());
+    assert!(!info.cmdline.is_empty());
+    assert!(info.rss_bytes > 0);
+
+    // Let some uptime pass, /proc only counts in clock ticks
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    let info = ProcessInfo::read(std::process::id()).unwrap();
+    let mut sampler = CpuSampler::new();
+    assert!(sampler.sample(&info).is_some_and(|usage| usage >= 0.0));
+}
+
+#[test]
+fn missing_processes_are_errors() {
+    // Above the kernel's PID limit
+    assert!(ProcessInfo::read(u32::MAX).is_err());
+}
+
+#[test]
+fn recognises_the_local_machine() {
+    let hostname = std::fs::read_to_string("/proc/sys/kernel/hostname").unwrap();
+    assert!(is_local_machine(hostname.trim()));
+    assert!(is_local_machine("localhost"));
+    assert!(!is_local_machine("some-other-host.example"));
+}
@@ -0,0 +1,153 @@
+#![cfg(feature = "serde")]
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use aura_core::groups::WindowGroups;
+use aura_core::record::{Recorder, Replay};
+use aura_core::schema::{self, IconEncoding};
+use aura_core::{RawIcon, SensorEvent, WindowInfo};
+use tokio_stream::StreamExt;
+
+fn window(xid: u32) -> WindowInfo {
+    WindowInfo {
+        xid,
+        title: format!("Window {}", xid),
+        class: "App".to_string(),
+        icon_data: Some(RawIcon { width: 1, height: 1, data: vec![0x10, 0x20, 0x30, 0xFF] }),
+        ..Default::default()
+    }
+}
+
+fn session() -> Vec<SensorEvent> {
+    vec![
+        SensorEvent::WindowOpen(window(1)),
+        SensorEvent::FocusChange(1),
+        SensorEvent::WindowClose(1),
+    ]
+}
+
+/// A recording of `events` with the given offsets in milliseconds
+fn recording(events: &[(u64, SensorEvent)]) -> String {
+    events.iter()
+        .map(|(time_ms, event)| {
+            let mut record = schema::from_json(&schema::to_json(event, IconEncoding::Png).unwrap()).unwrap();
+            record.time_ms = Some(*time_ms);
+            serde_json::to_string(&record).unwrap() + "\n"
+        })
+        .collect()
+}
+
+/// Plays `replay` and returns each event with the time it arrived after starting
+fn play(replay: Replay) -> Vec<(Duration, SensorEvent)> {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(async {
+        let start = Instant::now();
+        let mut stream = replay.into_stream();
+        let mut played = Vec::new();
+        while let Some(event) = stream.next().await {
+            played.push((start.elapsed(), event));
+        }
+        played
+    })
+}
+
+#[test]
+fn replays_what_was_recorded() {
+    let mut out = Vec::new();
+    let mut recorder = Recorder::new(&mut out);
+    for event in session() {
+        recorder.record(&event).unwrap();
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    let text = String::from_utf8(out).unwrap();
+    let times: Vec<u64> = text.lines()
+        .map(|line| schema::from_json(line).unwrap().time_ms.expect("Recorded events carry their time"))
+        .collect();
+    assert_eq!(times.len(), 3);
+    assert!(times[0] < 20, "{:?}", times);
+    assert!(times.windows(2).all(|pair| pair[1] >= pair[0] + 20), "{:?}", times);
+
+    let replay = Replay::from_reader(text.as_bytes()).unwrap();
+    assert_eq!(replay.into_events(), session());
+}
+
+#[test]
+fn records_without_icon_pixels() {
+    let mut out = Vec::new();
+    Recorder::new(&mut out).icons(IconEncoding::Omit).record(&session()[0]).unwrap();
+
+    let events = Replay::from_reader(out.as_slice()).unwrap().into_events();
+    let SensorEvent::WindowOpen(info) = &events[0] else { panic!("Expected a window, got {:?}", events) };
+    let icon = info.icon_data.as_ref().unwrap();
+    assert_eq!((icon.width, icon.height), (1, 1));
+    assert!(!icon.has_pixels());
+    assert!(window(1).icon_data.unwrap().has_pixels());
+}
+
+#[test]
+fn replays_with_the_recorded_timing() {
+    let text = recording(&[(0, SensorEvent::FocusChange(1)), (200, SensorEvent::FocusChange(2))]);
+
+    let played = play(Replay::from_reader(text.as_bytes()).unwrap());
+    assert_eq!(played.len(), 2);
+    assert!(played[0].0 < Duration::from_millis(100), "{:?}", played);
+    assert!(played[1].0 >= Duration::from_millis(200), "{:?}", played);
+
+    let played = play(Replay::from_reader(text.as_bytes()).unwrap().speed(2.0));
+    assert!(played[1].0 >= Duration::from_millis(100), "{:?}", played);
+    assert!(played[1].0 < Duration::from_millis(200), "{:?}", played);
+}
+
+#[test]
+fn replays_at_speed_zero_without_delay() {
+    let text = recording(&[(0, SensorEvent::FocusChange(1)), (60_000, SensorEvent::FocusChange(2))]);
+
+    let played = play(Replay::from_reader(text.as_bytes()).unwrap().speed(0.0));
+    let events: Vec<SensorEvent> = played.iter().map(|(_, event)| event.clone()).collect();
+    assert_eq!(events, vec![SensorEvent::FocusChange(1), SensorEvent::FocusChange(2)]);
+    assert!(played[1].0 < Duration::from_secs(1), "{:?}", played);
+}
+
+#[test]
+fn rejects_malformed_lines() {
+    let good = recording(&[(0, SensorEvent::FocusChange(1))]);
+
+    let text = format!("{}\n{{\"version\":1,\"event\":\n", good);
+    let error = Replay::from_reader(text.as_bytes()).err().expect("A truncated line in the middle is an error");
+    assert!(error.to_string().starts_with("Line 3:"), "{}", error);
+
+    let text = format!("{}not json\n{}", good, good);
+    let error = Replay::from_reader(text.as_bytes()).err().expect("Garbage is an error");
+    assert!(error.to_string().starts_with("Line 2:"), "{}", error);
+}
+
+#[test]
+fn drops_a_last_line_cut_short_while_recording() {
+    let good = recording(&[(0, SensorEvent::FocusChange(1))]);
+    let text = format!("{}{}", good, &good[..good.len() / 2]);
+
+    let events = Replay::from_reader(text.as_bytes()).unwrap().into_events();
+    assert_eq!(events, vec![SensorEvent::FocusChange(1)]);
+}
+
+#[test]
+fn drives_the_dock_model_from_a_recording() {
+    let dialog = WindowInfo { transient_for: Some(1), is_dialog: true, ..window(3) };
+    let text = recording(&[
+        (0, SensorEvent::FullScan(vec![window(1), window(2)])),
+        (10, SensorEvent::WindowOpen(dialog)),
+        (20, SensorEvent::FocusChange(3)),
+        (30, SensorEvent::WindowClose(2)),
+    ]);
+
+    // What the dock's sensor loop does with each event
+    let mut groups = WindowGroups::new();
+    for event in Replay::from_reader(text.as_bytes()).unwrap().into_events() {
+        groups.apply(&event);
+    }
+    let items: Vec<u32> = groups.items().iter().map(|info| info.xid).collect();
+    assert_eq!(items, vec![1]);
+    assert_eq!(groups.item_for(3), 1);
+}
@@ -0,0 +1,161 @@
+#![cfg(feature = "serde")]
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use aura_core::schema::{from_json, to_json, IconEncoding, SCHEMA_VERSION};
+use aura_core::{Capabilities, Capability, FrameExtents, Geometry, RawIcon, SensorEvent, WindowInfo};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde_json::Value;
+
+/// Every field a window is written with, as listed in the `schema` module docs
+const DOCUMENTED_FIELDS: &[&str] = &[
+    "xid", "title", "class", "is_active", "icon_path", "icon_data", "transient_for", "leader",
+    "is_dialog", "is_modal", "pid", "unresponsive", "is_fullscreen", "geometry", "frame_extents",
+    "is_hidden", "desktop_id", "is_maximized",
+];
+
+fn icon() -> RawIcon {
+    // Half transparent red, opaque blue
+    RawIcon { width: 2, height: 1, data: vec![0xFF, 0x00, 0x00, 0x80, 0x00, 0x00, 0xFF, 0xFF] }
+}
+
+fn window() -> WindowInfo {
+    WindowInfo {
+        xid: 0x0040_0006,
+        title: "Terminal".to_string(),
+        class: "kitty".to_string(),
+        icon_path: Some(PathBuf::from("/usr/share/icons/hicolor/48x48/apps/kitty.png")),
+        icon_data: Some(icon()),
+        transient_for: Some(0x0040_0001),
+        leader: Some(0x0040_0001),
+        is_dialog: true,
+        is_modal: true,
+        pid: Some(4242),
+        unresponsive: true,
+        is_fullscreen: true,
+        geometry: Some(Geometry { x: -10, y: 20, width: 640, height: 480 }),
+        frame_extents: Some(FrameExtents { left: 1, right: 1, top: 24, bottom: 1 }),
+        is_hidden: true,
+        desktop_id: Some("kitty.desktop".to_string()),
+        is_maximized: true,
+        ..Default::default()
+    }
+}
+
+fn json(event: &SensorEvent, icons: IconEncoding) -> Value {
+    serde_json::from_str(&to_json(event, icons).unwrap()).unwrap()
+}
+
+#[test]
+fn round_trips_every_event() {
+    let capabilities = Capabilities {
+        wm_name: Some("Mutter".to_string()),
+        supported: BTreeSet::from([Capability::ClientList, Capability::ActiveWindow]),
+    };
+    let events = [
+        SensorEvent::FullScan(vec![window(), WindowInfo { xid: 7, ..Default::default() }]),
+        SensorEvent::WindowOpen(window()),
+        SensorEvent::WindowUpdate(window()),
+        SensorEvent::WindowClose(7),
+        SensorEvent::FocusChange(7),
+        SensorEvent::Capabilities(capabilities),
+    ];
+
+    for event in events {
+        let line = to_json(&event, IconEncoding::Png).unwrap();
+        assert!(!line.contains('\n'));
+        let record = from_json(&line).unwrap();
+        assert_eq!(record.version, SCHEMA_VERSION);
+        assert_eq!(record.time_ms, None);
+        assert_eq!(record.event, event);
+    }
+}
+
+#[test]
+fn writes_the_documented_shape() {
+    let value = json(&SensorEvent::WindowOpen(window()), IconEncoding::Png);
+    assert_eq!(value["version"], SCHEMA_VERSION);
+    assert_eq!(value["event"]["type"], "window_open");
+
+    let fields: BTreeSet<&str> = value["event"]["data"].as_object().unwrap().keys().map(String::as_str).collect();
+    assert_eq!(fields, DOCUMENTED_FIELDS.iter().cloned().collect());
+
+    let value = json(&SensorEvent::FocusChange(7), IconEncoding::Png);
+    assert_eq!(value["event"], serde_json::json!({"type": "focus_change", "data": 7}));
+}
+
+#[test]
+fn rejects_newer_versions() {
+    let line = format!(r#"{{"version":{},"event":{{"type":"focus_change","data":7}}}}"#, SCHEMA_VERSION + 1);
+    let error = from_json(&line).unwrap_err();
+    assert!(error.to_string().contains("Unsupported event schema version"), "{}", error);
+
+    assert!(from_json(r#"{"event":{"type":"focus_change","data":7}}"#).is_err());
+    assert!(from_json(r#"{"version":1,"event":{"type":"window_moved","data":7}}"#).is_err());
+}
+
+#[test]
+fn reads_records_with_unknown_and_missing_fields() {
+    let line = r#"{"version":1,"future":true,"event":{"type":"window_open","data":{
+        "xid":7,"title":"Old","class":"App","is_active":false,"icon_path":null,"icon_data":null,"color":"red"}}}"#;
+    let record = from_json(line).unwrap();
+    let expected = WindowInfo { xid: 7, title: "Old".to_string(), class: "App".to_string(), ..Default::default() };
+    assert_eq!(record.event, SensorEvent::WindowOpen(expected));
+}
+
+#[test]
+fn encodes_icons_as_base64_png() {
+    let value = json(&SensorEvent::WindowOpen(window()), IconEncoding::Png);
+    let written = &value["event"]["data"]["icon_data"];
+    assert_eq!(written["width"], 2);
+    assert_eq!(written["height"], 1);
+
+    let png = BASE64.decode(written["png"].as_str().unwrap()).unwrap();
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+    let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!((frame.width, frame.height, frame.color_type), (2, 1, png::ColorType::Rgba));
+    assert_eq!(&pixels[..frame.buffer_size()], icon().data.as_slice());
+}
+
+#[test]
+fn decodes_rgb_pngs_to_rgba() {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, 1, 1);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header().unwrap().write_image_data(&[0x10, 0x20, 0x30]).unwrap();
+
+    let line = format!(
+        r#"{{"version":1,"event":{{"type":"window_open","data":{{"xid":7,"title":"","class":"","is_active":false,"icon_path":null,"icon_data":{{"width":1,"height":1,"png":"{}"}}}}}}}}"#,
+        BASE64.encode(&png)
+    );
+    let SensorEvent::WindowOpen(info) = from_json(&line).unwrap().event else { panic!("Expected a window") };
+    assert_eq!(info.icon_data, Some(RawIcon { width: 1, height: 1, data: vec![0x10, 0x20, 0x30, 0xFF] }));
+}
+
+#[test]
+fn rejects_broken_icons() {
+    let line = r#"{"version":1,"event":{"type":"window_open","data":{"xid":7,"title":"","class":"","is_active":false,"icon_path":null,"icon_data":{"width":1,"height":1,"png":"not base64!"}}}}"#;
+    assert!(from_json(line).is_err());
+
+    let line = line.replace("not base64!", &BASE64.encode(b"not a png"));
+    assert!(from_json(&line).is_err());
+}
+
+#[test]
+fn omits_icon_pixels_but_keeps_their_size() {
+    let event = SensorEvent::FullScan(vec![window()]);
+    let value = json(&event, IconEncoding::Omit);
+    let icon = &value["event"]["data"][0]["icon_data"];
+    assert_eq!(icon, &serde_json::json!({"width": 2, "height": 1}));
+
+    let record = from_json(&value.to_string()).unwrap();
+    let SensorEvent::FullScan(windows) = record.event else { panic!("Expected a full scan") };
+    assert_eq!(windows[0].icon_data, Some(RawIcon { width: 2, height: 1, data: Vec::new() }));
+    assert_eq!(windows[0].icon_path, window().icon_path);
+}
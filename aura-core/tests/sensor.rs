@@ -0,0 +1,331 @@
+mod common;
+
+use aura_core::builder::SENSOR_THREAD_NAME;
+use aura_core::ping::PING_TIMEOUT;
+use aura_core::{Capability, FrameExtents, Geometry, RawIcon, SensorBuilder, SensorEvent, WindowInfo};
+use std::thread;
+use std::time::Duration;
+use common::SensorProbe;
+
+const TEST_CLASS: &str = "AuraTestApp";
+/// How often the pinging sensor in `marks_windows_that_stop_answering_pings` pings
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+/// Where `create_window` puts windows; the test window manager adds no frames
+const WINDOW_GEOMETRY: Geometry = Geometry { x: 0, y: 0, width: 200, height: 100 };
+
+fn expected(xid: u32, title: &str, class: &str) -> WindowInfo {
+    WindowInfo {
+        xid,
+        title: title.to_string(),
+        class: class.to_string(),
+        is_active: false,
+        icon_path: None,
+        icon_data: None,
+        geometry: Some(WINDOW_GEOMETRY),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn reports_existing_windows_and_skips_the_dock() {
+    let Some(env) = common::setup() else { return };
+    let client = &env.client;
+
+    let editor = client.create_window("Editor", "editor", TEST_CLASS);
+    client.map_and_wait(editor);
+    let dock = client.create_window("Aura Dock", "aura-ui", "aura-ui");
+    client.map_and_wait(dock);
+
+    let mut sensor = SensorProbe::start();
+    assert_eq!(sensor.next(), SensorEvent::WindowOpen(expected(editor, "Editor", TEST_CLASS)));
+    sensor.assert_quiet();
+
+    // Closing a filtered window is not reported either
+    client.destroy(dock);
+    sensor.assert_quiet();
+}
+
+#[test]
+fn follows_window_lifecycle() {
+    let Some(env) = common::setup() else { return };
+    let client = &env.client;
+    let mut sensor = SensorProbe::start();
+    sensor.assert_quiet();
+
+    let dialog = client.create_window("Save As", "save", TEST_CLASS);
+    client.set_window_type(dialog, "_NET_WM_WINDOW_TYPE_DIALOG");
+    client.map(dialog);
+    let dialog_info = |title| WindowInfo { is_dialog: true, ..expected(dialog, title, TEST_CLASS) };
+    assert_eq!(sensor.next(), SensorEvent::WindowOpen(dialog_info("Save As")));
+
+    aura_core::activate_window(dialog).unwrap();
+    assert_eq!(sensor.next(), SensorEvent::FocusChange(dialog));
+
+    client.set_title(dialog, "Save As - report.txt");
+    assert_eq!(sensor.next(), SensorEvent::WindowUpdate(dialog_info("Save As - report.txt")));
+
+    client.destroy(dialog);
+    assert_eq!(sensor.next(), SensorEvent::WindowClose(dialog));
+    sensor.assert_quiet();
+}
+
+#[test]
+fn reads_legacy_titles_of_any_type() {
+    let Some(env) = common::setup() else { return };
+    let client = &env.client;
+    let mut sensor = SensorProbe::start();
+
+    for (type_, title) in [("STRING", "xterm"), ("UTF8_STRING", "Fenêtre"), ("COMPOUND_TEXT", "emacs@host")] {
+        let window = client.create_window(title, "legacy", TEST_CLASS);
+        client.set_legacy_title(window, title, type_);
+        client.map(window);
+        assert_eq!(sensor.next(), SensorEvent::WindowOpen(expected(window, title, TEST_CLASS)), "WM_NAME of type {}", type_);
+    }
+}
+
+#[test]
+fn converts_net_wm_icon_to_rgba() {
+    let Some(env) = common::setup() else { return };
+    let client = &env.client;
+    let mut sensor = SensorProbe::start();
+
+    let window = client.create_window("Painter", "painter", TEST_CLASS);
+    // ARGB: half transparent red, opaque blue
+    client.set_icon(window, 2, 1, &[0x80FF_0000, 0xFF00_00FF]);
+    client.map(window);
+
+    let mut info = expected(window, "Painter", TEST_CLASS);
+    info.icon_data = Some(RawIcon {
+        width: 2,
+        height: 1,
+        data: vec![0xFF, 0x00, 0x00, 0x80, 0x00, 0x00, 0xFF, 0xFF],
+    });
+    assert_eq!(sensor.next(), SensorEvent::WindowOpen(info));
+}
+
+#[test]
+fn coalesces_windows_that_open_and_close_in_a_burst() {
+    let Some(env) = common::setup() else { return };
+    let client = &env.client;
+    let mut sensor = SensorProbe::start();
+    sensor.assert_quiet();
+
+    let flash = client.create_window("Splash", "splash", TEST_CLASS);
+    let keep = client.create_window("Main", "main", TEST_CLASS);
+    client.map(flash);
+    client.map(keep);
+    client.destroy(flash);
+
+    assert_eq!(sensor.next(), SensorEvent::WindowOpen(expected(keep, "Main", TEST_CLASS)));
+    sensor.assert_quiet();
+}
+
+#[test]
+fn reports_wm_capabilities_first() {
+    let Some(_env) = common::setup() else { return };
+    let sensor = SensorProbe::start();
+
+    let capabilities = &sensor.capabilities;
+    assert_eq!(capabilities.wm_name.as_deref(), Some("aura-test-wm"));
+    assert!(capabilities.supports(Capability::ClientList));
+    assert!(capabilities.supports(Capability::ActiveWindow));
+    assert!(!capabilities.supports(Capability::WmPing));
+}
+
+#[test]
+fn reports_missing_ewmh_support() {
+    let Some(_env) = common::setup_with(common::WmOptions { ewmh: false }) else { return };
+    let mut sensor = SensorProbe::start();
+
+    assert!(!sensor.capabilities.is_ewmh());
+    assert_eq!(sensor.capabilities.wm_name, None);
+    sensor.assert_quiet();
+}
+
+#[test]
+fn tracks_clients_through_wm_state_without_ewmh() {
+    let Some(env) = common::setup_with(common::WmOptions { ewmh: false }) else { return };
+    let client = &env.client;
+
+    let existing = client.create_window("Terminal", "xterm", TEST_CLASS);
+    client.map_and_wait(existing);
+
+    let mut sensor = SensorProbe::start();
+    assert_eq!(sensor.next(), SensorEvent::WindowOpen(expected(existing, "Terminal", TEST_CLASS)));
+
+    let window = client.create_window("Editor", "editor", TEST_CLASS);
+    client.map(window);
+    assert_eq!(sensor.next(), SensorEvent::WindowOpen(expected(window, "Editor", TEST_CLASS)));
+
+    client.destroy(window);
+    assert_eq!(sensor.next(), SensorEvent::WindowClose(window));
+    sensor.assert_quiet();
+}
+
+#[test]
+fn reports_transient_and_group_relations() {
+    let Some(env) = common::setup() else { return };
+    let client = &env.client;
+
+    let main = client.create_window("Document", "writer", TEST_CLASS);
+    client.set_client_leader(main, main);
+    client.map_and_wait(main);
+    let mut sensor = SensorProbe::start();
+    let main_info = WindowInfo { leader: Some(main), ..expected(main, "Document", TEST_CLASS) };
+    assert_eq!(sensor.next(), SensorEvent::WindowOpen(main_info));
+
+    let dialog = client.create_window("Print", "writer", TEST_CLASS);
+    client.set_client_leader(dialog, main);
+    client.set_transient_for(dialog, main);
+    client.set_window_type(dialog, "_NET_WM_WINDOW_TYPE_DIALOG");
+    client.set_state(dialog, &["_NET_WM_STATE_MODAL"]);
+    client.map(dialog);
+
+    let mut dialog_info = WindowInfo {
+        transient_for: Some(main),
+        leader: Some(main),
+        is_dialog: true,
+        is_modal: true,
+        ..expected(dialog, "Print", TEST_CLASS)
+    };
+    assert_eq!(sensor.next(), SensorEvent::WindowOpen(dialog_info.clone()));
+
+    // Dropping the modal state is reported like any other change
+    client.set_state(dialog, &[]);
+    dialog_info.is_modal = false;
+    assert_eq!(sensor.next(), SensorEvent::WindowUpdate(dialog_info));
+    sensor.assert_quiet();
+}
+
+#[test]
+fn reports_pids_of_local_clients_only() {
+    let Some(env) = common::setup() else { return };
+    let client = &env.client;
+    let mut sensor = SensorProbe::start();
+    let hostname = std::fs::read_to_string("/proc/sys/kernel/hostname").unwrap();
+
+    let local = client.create_window("Local", "local", TEST_CLASS);
+    client.set_pid(local, std::process::id(), hostname.trim());
+    client.map(local);
+    let info = WindowInfo { pid: Some(std::process::id()), ..expected(local, "Local", TEST_CLASS) };
+    assert_eq!(sensor.next(), SensorEvent::WindowOpen(info));
+
+    let remote = client.create_window("Remote", "remote", TEST_CLASS);
+    client.set_pid(remote, 4242, "far-away.example");
+    client.map(remote);
+    assert_eq!(sensor.next(), SensorEvent::WindowOpen(expected(remote, "Remote", TEST_CLASS)));
+}
+
+#[test]
+fn marks_windows_that_stop_answering_pings() {
+    let Some(env) = common::setup() else { return };
+    let client = &env.client;
+    let mut sensor = SensorProbe::build(SensorBuilder::new().ping_interval(Some(PING_INTERVAL)));
+
+    let window = client.create_window("Busy", "busy", TEST_CLASS);
+    client.set_protocols(window, &["_NET_WM_PING"]);
+    client.map(window);
+    let mut info = expected(window, "Busy", TEST_CLASS);
+    assert_eq!(sensor.next(), SensorEvent::WindowOpen(info.clone()));
+
+    // Ignore the first ping: one interval plus the timeout
+    let hang_timeout = PING_INTERVAL + PING_TIMEOUT + Duration::from_secs(2);
+    info.unresponsive = true;
+    assert_eq!(sensor.next_within(hang_timeout), Some(SensorEvent::WindowUpdate(info.clone())));
+
+    assert!(client.answer_ping(hang_timeout));
+    info.unresponsive = false;
+    assert_eq!(sensor.next(), SensorEvent::WindowUpdate(info));
+}
+
+#[test]
+fn does_not_ping_unless_asked_to() {
+    let Some(env) = common::setup() else { return };
+    let client = &env.client;
+    let mut sensor = SensorProbe::start();
+
+    let window = client.create_window("Quiet", "quiet", TEST_CLASS);
+    client.set_protocols(window, &["_NET_WM_PING"]);
+    client.map(window);
+    assert_eq!(sensor.next(), SensorEvent::WindowOpen(expected(window, "Quiet", TEST_CLASS)));
+
+    assert!(!client.answer_ping(PING_INTERVAL + Duration::from_secs(1)));
+}
+
+#[test]
+fn reports_fullscreen_state_and_geometry() {
+    let Some(env) = common::setup() else { return };
+    let client = &env.client;
+    let mut sensor = SensorProbe::start();
+
+    let player = client.create_window("Player", "player", TEST_CLASS);
+    client.map(player);
+    let mut info = expected(player, "Player", TEST_CLASS);
+    assert_eq!(sensor.next(), SensorEvent::WindowOpen(info.clone()));
+
+    client.set_state(player, &["_NET_WM_STATE_FULLSCREEN"]);
+    info.is_fullscreen = true;
+    assert_eq!(sensor.next(), SensorEvent::WindowUpdate(info.clone()));
+
+    client.move_resize(player, 10, 20, 640, 480);
+    info.geometry = Some(Geometry { x: 10, y: 20, width: 640, height: 480 });
+    assert_eq!(sensor.next(), SensorEvent::WindowUpdate(info));
+    sensor.assert_quiet();
+}
+
+#[test]
+fn reports_frame_extents_and_maximized_and_minimized_windows() {
+    let Some(env) = common::setup() else { return };
+    let client = &env.client;
+    let mut sensor = SensorProbe::start();
+
+    let editor = client.create_window("Editor", "editor", TEST_CLASS);
+    client.map(editor);
+    let mut info = expected(editor, "Editor", TEST_CLASS);
+    assert_eq!(sensor.next(), SensorEvent::WindowOpen(info.clone()));
+
+    client.set_frame_extents(editor, 1, 1, 24, 1);
+    info.frame_extents = Some(FrameExtents { left: 1, right: 1, top: 24, bottom: 1 });
+    assert_eq!(sensor.next(), SensorEvent::WindowUpdate(info.clone()));
+
+    client.set_state(editor, &["_NET_WM_STATE_MAXIMIZED_VERT", "_NET_WM_STATE_MAXIMIZED_HORZ"]);
+    info.is_maximized = true;
+    assert_eq!(sensor.next(), SensorEvent::WindowUpdate(info.clone()));
+
+    client.set_state(editor, &["_NET_WM_STATE_HIDDEN"]);
+    info.is_maximized = false;
+    info.is_hidden = true;
+    assert_eq!(sensor.next(), SensorEvent::WindowUpdate(info));
+    sensor.assert_quiet();
+}
+
+/// Clock ticks the `SensorBuilder` threads of this process have run for
+fn sensor_cpu_ticks() -> u64 {
+    let mut ticks = 0;
+    for task in std::fs::read_dir("/proc/self/task").unwrap().filter_map(|entry| entry.ok()) {
+        let comm = std::fs::read_to_string(task.path().join("comm")).unwrap_or_default();
+        if comm.trim_end() != SENSOR_THREAD_NAME {
+            continue;
+        }
+        let stat = std::fs::read_to_string(task.path().join("stat")).unwrap_or_default();
+        // utime and stime are the 14th and 15th fields, the name in parentheses is the 2nd
+        let fields: Vec<&str> = stat.rsplit_once(')').map(|(_, rest)| rest).unwrap_or("").split_whitespace().collect();
+        ticks += fields.get(11..13).map_or(0, |times| times.iter().map(|t| t.parse::<u64>().unwrap_or(0)).sum());
+    }
+    ticks
+}
+
+#[test]
+fn idles_after_every_handle_is_dropped() {
+    let Some(_env) = common::setup() else { return };
+    let (_events, handle) = SensorBuilder::new().build().unwrap();
+    drop(handle);
+
+    // Let the sensor notice the closed handle, then watch it for a while
+    thread::sleep(Duration::from_millis(100));
+    let before = sensor_cpu_ticks();
+    thread::sleep(Duration::from_millis(500));
+    let spent = sensor_cpu_ticks() - before;
+    // Spinning on the closed wake-up socket takes the whole half second, ~50 ticks
+    assert!(spent < 10, "Sensor kept running after its handle was dropped: {} ticks", spent);
+}
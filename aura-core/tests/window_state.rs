@@ -0,0 +1,63 @@
+mod common;
+
+use common::wait_until;
+
+#[test]
+fn activate_window_sets_active_window() {
+    let Some(env) = common::setup() else { return };
+    let client = &env.client;
+
+    let first = client.create_window("First", "first", "AuraTestApp");
+    client.map_and_wait(first);
+    let second = client.create_window("Second", "second", "AuraTestApp");
+    client.map_and_wait(second);
+
+    aura_core::activate_window(second).unwrap();
+    wait_until("second window to be active", || client.active_window() == Some(second));
+
+    aura_core::activate_window(first).unwrap();
+    wait_until("first window to be active", || client.active_window() == Some(first));
+}
+
+#[test]
+fn state_helpers_update_net_wm_state() {
+    let Some(env) = common::setup() else { return };
+    let client = &env.client;
+
+    let window = client.create_window("Panel", "panel", "AuraTestApp");
+    client.map_and_wait(window);
+
+    aura_core::set_always_on_top(window).unwrap();
+    wait_until("_NET_WM_STATE_ABOVE", || {
+        client.wm_state(window).contains(&"_NET_WM_STATE_ABOVE".to_string())
+    });
+
+    aura_core::set_skip_taskbar(window).unwrap();
+    wait_until("skip taskbar and pager", || {
+        let state = client.wm_state(window);
+        state.contains(&"_NET_WM_STATE_SKIP_TASKBAR".to_string())
+            && state.contains(&"_NET_WM_STATE_SKIP_PAGER".to_string())
+    });
+}
+
+#[test]
+fn by_name_helpers_find_windows_by_title() {
+    let Some(env) = common::setup() else { return };
+    let client = &env.client;
+
+    let other = client.create_window("Other", "other", "AuraTestApp");
+    client.map_and_wait(other);
+    let dock = client.create_window("Aura Dock", "aura-ui", "aura-ui");
+    client.map_and_wait(dock);
+
+    aura_core::set_always_on_top_by_name("Aura Dock").unwrap();
+    aura_core::set_skip_taskbar_by_name("Aura Dock").unwrap();
+    wait_until("dock state", || {
+        let state = client.wm_state(dock);
+        state.contains(&"_NET_WM_STATE_ABOVE".to_string())
+            && state.contains(&"_NET_WM_STATE_SKIP_TASKBAR".to_string())
+    });
+    assert!(client.wm_state(other).is_empty());
+
+    assert!(aura_core::set_always_on_top_by_name("No Such Window").is_err());
+}
@@ -1,10 +1,13 @@
 use gtk::prelude::*;
 use gtk::{Button, Image, Box, Orientation, ScrolledWindow, FlowBox, FlowBoxChild, SearchEntry, Label, Window};
 use std::cell::RefCell;
+use std::error::Error;
 use std::rc::Rc;
-use std::process::Command;
+use std::sync::Arc;
 
+use aura_assets::launch::Launcher;
 use aura_assets::{get_all_apps, AppInfo};
+use crate::app_index::AppIndexUpdates;
 
 /// Creates the app grid button for the dock
 pub fn create_app_grid_button(grid_window: Rc<RefCell<Option<Window>>>) -> Button {
@@ -35,7 +38,7 @@ pub fn create_app_grid_button(grid_window: Rc<RefCell<Option<Window>>>) -> Butto
 }
 
 /// Creates the app grid overlay window
-pub fn create_app_grid_window() -> Window {
+pub fn create_app_grid_window(app_updates: &AppIndexUpdates) -> Window {
     let window = Window::builder()
         .title("Applications")
         .default_width(800)
@@ -61,6 +64,13 @@ pub fn create_app_grid_window() -> Window {
     search_entry.add_css_class("app-grid-search");
     main_box.append(&search_entry);
 
+    // Why the last launch failed
+    let status = Label::new(None);
+    status.add_css_class("app-grid-error");
+    status.set_wrap(true);
+    status.set_visible(false);
+    main_box.append(&status);
+
     // Scrolled window for the grid
     let scrolled = ScrolledWindow::builder()
         .hscrollbar_policy(gtk::PolicyType::Never)
@@ -79,48 +89,37 @@ pub fn create_app_grid_window() -> Window {
     flow_box.set_column_spacing(8);
     flow_box.add_css_class("app-grid-flow");
 
-    // Load all apps
-    let apps = get_all_apps();
-    let apps_rc = Rc::new(apps);
-
-    // Clone for search filter
-    let flow_box_weak = flow_box.downgrade();
-    let apps_for_search = apps_rc.clone();
-    let window_weak = window.downgrade();
+    // The apps known so far, none until the index is read; the list is replaced once it
+    // is and whenever software is installed or removed
+    let apps = Rc::new(RefCell::new(get_all_apps()));
+    let grid_launcher = GridLauncher {
+        launcher: Arc::new(Launcher::new()),
+        window_weak: window.downgrade(),
+        status_weak: status.downgrade(),
+    };
 
     // Populate the grid
-    populate_app_grid(&flow_box, &apps_rc, window_weak.clone());
+    fill_app_grid(&flow_box, &apps.borrow(), "", &grid_launcher);
 
     // Search filter
+    let flow_box_weak = flow_box.downgrade();
+    let apps_for_search = apps.clone();
+    let launcher_for_search = grid_launcher.clone();
     search_entry.connect_search_changed(move |entry| {
-        let query = entry.text().to_lowercase();
-        
         if let Some(flow_box) = flow_box_weak.upgrade() {
-            // Remove all children
-            while let Some(child) = flow_box.first_child() {
-                flow_box.remove(&child);
-            }
-
-            // Filter and re-populate
-            let filtered: Vec<&AppInfo> = apps_for_search
-                .iter()
-                .filter(|app| {
-                    if query.is_empty() {
-                        true
-                    } else {
-                        app.name.to_lowercase().contains(&query) ||
-                        app.categories.iter().any(|c| c.to_lowercase().contains(&query))
-                    }
-                })
-                .collect();
-
-            for app in filtered {
-                let child = create_app_item(app, window_weak.clone());
-                flow_box.insert(&child, -1);
-            }
+            fill_app_grid(&flow_box, &apps_for_search.borrow(), &entry.text(), &launcher_for_search);
         }
     });
 
+    // Keep the search while the list changes underneath it
+    let flow_box_weak = flow_box.downgrade();
+    let search_weak = search_entry.downgrade();
+    app_updates.connect(move |index| {
+        let (Some(flow_box), Some(search_entry)) = (flow_box_weak.upgrade(), search_weak.upgrade()) else { return };
+        *apps.borrow_mut() = index.apps().cloned().collect();
+        fill_app_grid(&flow_box, &apps.borrow(), &search_entry.text(), &grid_launcher);
+    });
+
     scrolled.set_child(Some(&flow_box));
     main_box.append(&scrolled);
 
@@ -157,16 +156,131 @@ pub fn create_app_grid_window() -> Window {
     window
 }
 
-/// Populates the app grid with app items
-fn populate_app_grid(flow_box: &FlowBox, apps: &[AppInfo], window_weak: glib::WeakRef<Window>) {
-    for app in apps {
-        let child = create_app_item(app, window_weak.clone());
+/// Launches apps from the grid, closing it or showing what went wrong
+#[derive(Clone)]
+struct GridLauncher {
+    launcher: Arc<Launcher>,
+    window_weak: glib::WeakRef<Window>,
+    status_weak: glib::WeakRef<Label>,
+}
+
+impl GridLauncher {
+    fn launch(&self, app: &AppInfo) {
+        let launcher = self.launcher.clone();
+        let reporter = self.clone();
+        launch_in_background(app, move |app| launcher.launch(app, &[]), move |app, result| reporter.report(app, result));
+    }
+
+    fn report(&self, app: &AppInfo, result: Result<(), Box<dyn Error>>) {
+        if let Some(status) = self.status_weak.upgrade() {
+            match &result {
+                Ok(()) => status.set_visible(false),
+                Err(e) => {
+                    status.set_text(&format!("Could not start {}: {}", app.localized_name, e));
+                    status.set_visible(true);
+                }
+            }
+        }
+        if let Err(e) = &result {
+            eprintln!("Failed to launch {}: {}", app.localized_name, e);
+            return;
+        }
+
+        // Close the grid after launching
+        if let Some(window) = self.window_weak.upgrade() {
+            window.set_visible(false);
+        }
+    }
+}
+
+/// Starts `app` on a worker thread, as D-Bus activation waits for the app to answer,
+/// and hands the result to `on_result` back on the main loop
+fn launch_in_background(
+    app: &AppInfo,
+    start: impl FnOnce(&AppInfo) -> Result<(), Box<dyn Error>> + Send + 'static,
+    on_result: impl FnOnce(&AppInfo, Result<(), Box<dyn Error>>) + 'static,
+) {
+    let app = app.clone();
+    let worker_app = app.clone();
+    // Errors cross threads as text, `Box<dyn Error>` is not `Send`
+    let worker = gtk::gio::spawn_blocking(move || start(&worker_app).map_err(|e| e.to_string()));
+    glib::MainContext::default().spawn_local(async move {
+        let result = match worker.await {
+            Ok(result) => result.map_err(Box::<dyn Error>::from),
+            Err(_) => Err("the launcher thread panicked".into()),
+        };
+        on_result(&app, result);
+    });
+}
+
+/// Pops up the app's desktop actions above `anchor`; `on_result` is told how starting one went
+pub fn show_actions_menu(
+    anchor: &impl IsA<gtk::Widget>,
+    app: &AppInfo,
+    launcher: &Arc<Launcher>,
+    on_result: impl Fn(&AppInfo, Result<(), Box<dyn Error>>) + Clone + 'static,
+) {
+    if app.actions.is_empty() {
+        return;
+    }
+
+    let content = Box::new(Orientation::Vertical, 0);
+    content.add_css_class("app-actions");
+
+    let popover = gtk::Popover::builder()
+        .child(&content)
+        .position(gtk::PositionType::Top)
+        .build();
+    popover.set_parent(anchor);
+    popover.connect_closed(|popover| popover.unparent());
+
+    for action in &app.actions {
+        let row = Box::new(Orientation::Horizontal, 8);
+        if let Some(icon) = &action.icon {
+            let image = if icon.starts_with('/') { Image::from_file(icon) } else { Image::from_icon_name(icon) };
+            image.set_pixel_size(16);
+            row.append(&image);
+        }
+        row.append(&Label::new(Some(&action.localized_name)));
+
+        let button = Button::builder()
+            .child(&row)
+            .has_frame(false)
+            .css_classes(["app-action"])
+            .build();
+
+        let app = app.clone();
+        let action = action.clone();
+        let launcher = launcher.clone();
+        let on_result = on_result.clone();
+        let popover_weak = popover.downgrade();
+        button.connect_clicked(move |_| {
+            if let Some(popover) = popover_weak.upgrade() {
+                popover.popdown();
+            }
+            let launcher = launcher.clone();
+            let action = action.clone();
+            launch_in_background(&app, move |app| launcher.launch_action(app, &action, &[]), on_result.clone());
+        });
+        content.append(&button);
+    }
+
+    popover.popup();
+}
+
+/// Replaces the grid's items with the apps matching `query`
+fn fill_app_grid(flow_box: &FlowBox, apps: &[AppInfo], query: &str, grid_launcher: &GridLauncher) {
+    while let Some(child) = flow_box.first_child() {
+        flow_box.remove(&child);
+    }
+    for app in apps.iter().filter(|app| app.matches(query)) {
+        let child = create_app_item(app, grid_launcher);
         flow_box.insert(&child, -1);
     }
 }
 
 /// Creates a single app item widget
-fn create_app_item(app: &AppInfo, window_weak: glib::WeakRef<Window>) -> FlowBoxChild {
+fn create_app_item(app: &AppInfo, grid_launcher: &GridLauncher) -> FlowBoxChild {
     let item_box = Box::new(Orientation::Vertical, 4);
     item_box.set_halign(gtk::Align::Center);
     item_box.set_valign(gtk::Align::Start);
@@ -182,7 +296,7 @@ fn create_app_item(app: &AppInfo, window_weak: glib::WeakRef<Window>) -> FlowBox
     icon.add_css_class("app-grid-icon");
 
     // Name label
-    let label = Label::new(Some(&app.name));
+    let label = Label::new(Some(&app.localized_name));
     label.set_max_width_chars(12);
     label.set_ellipsize(gtk::pango::EllipsizeMode::End);
     label.set_wrap(true);
@@ -198,35 +312,43 @@ fn create_app_item(app: &AppInfo, window_weak: glib::WeakRef<Window>) -> FlowBox
         .child(&item_box)
         .has_frame(false)
         .css_classes(["app-grid-button-item"])
-        .tooltip_text(&app.name)
+        .tooltip_text(app_tooltip(app))
         .build();
 
-    let exec = app.exec.clone();
-    button.connect_clicked(move |_| {
-        launch_app(&exec);
-        // Close the grid after launching
-        if let Some(window) = window_weak.upgrade() {
-            window.set_visible(false);
-        }
+    let app_for_click = app.clone();
+    let grid_launcher_click = grid_launcher.clone();
+    button.connect_clicked(move |_| grid_launcher_click.launch(&app_for_click));
+
+    // Right click lists the desktop actions
+    let menu_gesture = gtk::GestureClick::new();
+    menu_gesture.set_button(gtk::gdk::BUTTON_SECONDARY);
+    let app = app.clone();
+    let grid_launcher = grid_launcher.clone();
+    let button_weak = button.downgrade();
+    menu_gesture.connect_pressed(move |gesture, _n, _x, _y| {
+        let Some(button) = button_weak.upgrade() else { return };
+        gesture.set_state(gtk::EventSequenceState::Claimed);
+        let reporter = grid_launcher.clone();
+        show_actions_menu(&button, &app, &grid_launcher.launcher, move |app, result| reporter.report(app, result));
     });
+    button.add_controller(menu_gesture);
 
     let child = FlowBoxChild::new();
     child.set_child(Some(&button));
     child
 }
 
-/// Launches an application from its exec command
-fn launch_app(exec: &str) {
-    let parts: Vec<&str> = exec.split_whitespace().collect();
-    if parts.is_empty() {
-        return;
+/// The name, plus the generic name and comment when the entry has them
+fn app_tooltip(app: &AppInfo) -> String {
+    let mut tooltip = app.localized_name.clone();
+    if let Some(generic_name) = &app.generic_name
+        && generic_name != &app.localized_name
+    {
+        tooltip.push_str(&format!(" ({})", generic_name));
     }
-
-    let program = parts[0];
-    let args = &parts[1..];
-
-    match Command::new(program).args(args).spawn() {
-        Ok(_) => {}
-        Err(e) => eprintln!("Failed to launch {}: {}", exec, e),
+    if let Some(comment) = &app.comment {
+        tooltip.push('\n');
+        tooltip.push_str(comment);
     }
+    tooltip
 }
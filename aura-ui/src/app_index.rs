@@ -0,0 +1,47 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use aura_assets::index::{self, AppIndex};
+
+type Handler = std::boxed::Box<dyn Fn(&AppIndex)>;
+
+/// Tells the grid and the dock on the main loop when apps were installed or removed
+#[derive(Clone, Default)]
+pub struct AppIndexUpdates {
+    handlers: Rc<RefCell<Vec<Handler>>>,
+}
+
+impl AppIndexUpdates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls `handler` with every reloaded index
+    pub fn connect(&self, handler: impl Fn(&AppIndex) + 'static) {
+        self.handlers.borrow_mut().push(std::boxed::Box::new(handler));
+    }
+
+    /// Hands over the first index once it is read, and starts watching the application
+    /// and icon directories
+    pub fn start(&self) {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let first = tx.clone();
+        index::subscribe(move |index| tx.send(index.clone()).is_ok());
+        // It may have been read before we subscribed
+        if index::is_loaded() {
+            let _ = first.send(index::current());
+        }
+        if let Err(e) = index::watch() {
+            eprintln!("Failed to watch for installed apps, restart the dock to see new ones: {}", e);
+        }
+
+        let handlers = self.handlers.clone();
+        glib::MainContext::default().spawn_local(async move {
+            while let Some(index) = rx.recv().await {
+                for handler in handlers.borrow().iter() {
+                    handler(&index);
+                }
+            }
+        });
+    }
+}
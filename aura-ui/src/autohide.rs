@@ -4,20 +4,92 @@ use std::cell::Cell;
 use std::rc::Rc;
 use std::time::Duration;
 
+use aura_core::groups::WindowGroups;
+use aura_core::Geometry;
+use crate::window::ScreenGeometry;
+
 const HIDE_THRESHOLD: i32 = 100;  // Pixels from bottom to trigger show
 const HIDE_DELAY_MS: u64 = 500;   // Delay before hiding
 
+/// When the dock gets out of the way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HidePolicy {
+    /// Hide whenever the pointer leaves the bottom of the screen
+    Pointer,
+    /// Stay visible until a window overlaps the dock, then hide like `Pointer`
+    Intellihide(OverlapScope),
+}
+
+/// Which windows intellihide makes way for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlapScope {
+    AnyWindow,
+    FocusedWindow,
+    /// Windows sharing the focused window's class
+    FocusedApp,
+}
+
 /// State for auto-hide functionality
+#[derive(Clone)]
 pub struct AutoHideState {
     pub is_visible: Rc<Cell<bool>>,
     pub should_hide: Rc<Cell<bool>>,
+    policy: HidePolicy,
+    monitor: Geometry,
+    // a window (that the policy cares about) overlaps the dock
+    overlapped: Rc<Cell<bool>>,
+    pointer_near: Rc<Cell<bool>>,
 }
 
 impl AutoHideState {
-    pub fn new() -> Self {
+    pub fn new(policy: HidePolicy, screen: &ScreenGeometry) -> Self {
         Self {
             is_visible: Rc::new(Cell::new(true)),
             should_hide: Rc::new(Cell::new(false)),
+            policy,
+            monitor: Geometry {
+                x: screen.x,
+                y: screen.y,
+                width: screen.width.max(0) as u32,
+                height: screen.height.max(0) as u32,
+            },
+            overlapped: Rc::new(Cell::new(false)),
+            pointer_near: Rc::new(Cell::new(false)),
+        }
+    }
+
+    /// Whether the policy lets the dock hide right now
+    pub fn may_hide(&self) -> bool {
+        match self.policy {
+            HidePolicy::Pointer => true,
+            HidePolicy::Intellihide(_) => self.overlapped.get(),
+        }
+    }
+
+    /// Re-evaluates intellihide after windows moved or the focus changed
+    pub fn update_overlap(&self, hbox: &Box, groups: &WindowGroups, focused: Option<u32>) {
+        let HidePolicy::Intellihide(scope) = self.policy else { return };
+        let Some(area) = dock_area(hbox, &self.monitor) else { return };
+
+        let overlapped = overlaps_dock(scope, groups, focused, &area);
+        if overlapped == self.overlapped.get() {
+            return;
+        }
+        self.overlapped.set(overlapped);
+
+        if !overlapped {
+            self.reveal(hbox);
+        } else if !self.pointer_near.get() {
+            self.should_hide.set(true);
+        }
+    }
+
+    /// Shows the dock without waiting for the pointer, e.g. for a keyboard shortcut
+    pub fn reveal(&self, hbox: &Box) {
+        self.should_hide.set(false);
+        if !self.is_visible.get() {
+            hbox.remove_css_class("dock-hidden");
+            self.is_visible.set(true);
         }
     }
 }
@@ -25,10 +97,15 @@ impl AutoHideState {
 /// Sets up the periodic hide checker
 pub fn setup_hide_checker(hbox: &Box, state: &AutoHideState) {
     let hbox_weak = hbox.downgrade();
+    let state = state.clone();
     let is_visible = state.is_visible.clone();
     let should_hide = state.should_hide.clone();
     
     glib::timeout_add_local(Duration::from_millis(100), move || {
+        // A hide scheduled before a window moved away may no longer be wanted
+        if should_hide.get() && !state.may_hide() {
+            should_hide.set(false);
+        }
         if should_hide.get() && is_visible.get() {
             if let Some(hbox) = hbox_weak.upgrade() {
                 hbox.add_css_class("dock-hidden");
@@ -47,9 +124,26 @@ pub fn setup_motion_controller(window: &ApplicationWindow, hbox: &Box, state: &A
     let hbox_weak = hbox.downgrade();
     let is_visible = state.is_visible.clone();
     let should_hide = state.should_hide.clone();
+    let pointer_near = state.pointer_near.clone();
+
+    // Leaving the input region doesn't send a motion event, hide like moving away
+    let leave_visible = is_visible.clone();
+    let leave_should_hide = should_hide.clone();
+    let leave_near = pointer_near.clone();
+    motion_controller.connect_leave(move |_ctrl| {
+        leave_near.set(false);
+        if leave_visible.get() {
+            let should_hide_delay = leave_should_hide.clone();
+            glib::timeout_add_local(Duration::from_millis(HIDE_DELAY_MS), move || {
+                should_hide_delay.set(true);
+                glib::ControlFlow::Break
+            });
+        }
+    });
 
     motion_controller.connect_motion(move |_ctrl, _x, y| {
         let near_bottom = y > (screen_height - HIDE_THRESHOLD) as f64;
+        pointer_near.set(near_bottom);
 
         if let Some(hbox) = hbox_weak.upgrade() {
             if near_bottom {
@@ -72,3 +166,32 @@ pub fn setup_motion_controller(window: &ApplicationWindow, hbox: &Box, state: &A
 
     window.add_controller(motion_controller);
 }
+
+/// The strip from the top of the shown dock down to the screen edge, in root coordinates.
+/// Measured from the bottom since the hide transform moves the box itself.
+fn dock_area(hbox: &Box, monitor: &Geometry) -> Option<Geometry> {
+    let root = hbox.root()?;
+    let (x, _) = hbox.translate_coordinates(&root, 0.0, 0.0)?;
+    let height = (hbox.height() + hbox.margin_bottom()).max(0) as u32;
+    Some(Geometry {
+        x: monitor.x + x as i32,
+        y: monitor.y + monitor.height as i32 - height as i32,
+        width: hbox.width().max(0) as u32,
+        height,
+    })
+}
+
+/// Whether a window the scope cares about covers part of `area`; minimized windows don't count
+fn overlaps_dock(scope: OverlapScope, groups: &WindowGroups, focused: Option<u32>, area: &Geometry) -> bool {
+    let focused_info = focused.and_then(|id| groups.get(id));
+    groups.windows()
+        .filter(|info| !info.is_hidden)
+        .filter(|info| match scope {
+            OverlapScope::AnyWindow => true,
+            OverlapScope::FocusedWindow => Some(info.xid) == focused,
+            OverlapScope::FocusedApp => focused_info.is_some_and(|app| app.class == info.class),
+        })
+        .filter_map(|info| info.outer_geometry())
+        .any(|geometry| geometry.intersects(area))
+}
+
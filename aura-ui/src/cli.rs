@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+/// Command line options handled by the dock itself (GTK never sees them)
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    /// Write every sensor event to this file
+    pub record: Option<PathBuf>,
+    /// Feed events from this recording instead of the live X11 sensor
+    pub replay: Option<PathBuf>,
+    /// Print window manager and window diagnostics, then exit
+    pub diagnose: bool,
+}
+
+impl Options {
+    /// Splits our options out of `args`, returning the remaining arguments for GTK
+    pub fn parse(args: impl IntoIterator<Item = String>) -> (Self, Vec<String>) {
+        let mut options = Self::default();
+        let mut rest = Vec::new();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--record" => options.record = args.next().map(PathBuf::from),
+                "--replay" => options.replay = args.next().map(PathBuf::from),
+                "--diagnose" => options.diagnose = true,
+                _ => {
+                    if let Some(path) = arg.strip_prefix("--record=") {
+                        options.record = Some(PathBuf::from(path));
+                    } else if let Some(path) = arg.strip_prefix("--replay=") {
+                        options.replay = Some(PathBuf::from(path));
+                    } else {
+                        rest.push(arg);
+                    }
+                }
+            }
+        }
+
+        (options, rest)
+    }
+}
@@ -0,0 +1,56 @@
+use std::env;
+
+use crate::autohide::{HidePolicy, OverlapScope};
+
+const DEFAULT_SEARCH_HOTKEY: &str = "Super+S";
+const DEFAULT_SWITCHER_HOTKEY: &str = "Alt+Tab";
+
+/// User settings, read from the environment at startup
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Shortcut that focuses the dock search (`AURA_SEARCH_HOTKEY`, e.g. `Super+slash`)
+    pub search_hotkey: String,
+    /// Shortcut that opens the window switcher (`AURA_SWITCHER_HOTKEY`); with Shift it cycles backwards
+    pub switcher_hotkey: String,
+    /// `AURA_AUTOHIDE`: `intellihide` (default) or `pointer`; for intellihide,
+    /// `AURA_INTELLIHIDE_SCOPE` picks `any` window (default), the `focused` one or the focused `app`
+    pub hide_policy: HidePolicy,
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        Self {
+            search_hotkey: hotkey_var("AURA_SEARCH_HOTKEY", DEFAULT_SEARCH_HOTKEY),
+            switcher_hotkey: hotkey_var("AURA_SWITCHER_HOTKEY", DEFAULT_SWITCHER_HOTKEY),
+            hide_policy: hide_policy(),
+        }
+    }
+}
+
+fn hide_policy() -> HidePolicy {
+    let scope = match env::var("AURA_INTELLIHIDE_SCOPE").unwrap_or_default().trim() {
+        "" | "any" => OverlapScope::AnyWindow,
+        "focused" => OverlapScope::FocusedWindow,
+        "app" => OverlapScope::FocusedApp,
+        other => {
+            eprintln!("Unknown AURA_INTELLIHIDE_SCOPE '{}', using 'any'", other);
+            OverlapScope::AnyWindow
+        }
+    };
+
+    match env::var("AURA_AUTOHIDE").unwrap_or_default().trim() {
+        "" | "intellihide" => HidePolicy::Intellihide(scope),
+        "pointer" => HidePolicy::Pointer,
+        other => {
+            eprintln!("Unknown AURA_AUTOHIDE '{}', using 'intellihide'", other);
+            HidePolicy::Intellihide(scope)
+        }
+    }
+}
+
+fn hotkey_var(name: &str, default: &str) -> String {
+    env::var(name)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| default.to_string())
+}
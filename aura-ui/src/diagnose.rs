@@ -0,0 +1,71 @@
+use aura_assets::get_current_icon_theme;
+use aura_core::diagnostics::{self, ClientReport};
+
+/// Prints what the dock sees of the window manager and every client window (`--diagnose`)
+pub fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let report = diagnostics::collect()?;
+
+    println!("Aura Dock diagnostics");
+    println!();
+
+    match (&report.wm_name, report.wm_check_window) {
+        (Some(name), Some(check)) => println!("Window manager: {} (check window 0x{:08x})", name, check),
+        (None, Some(check)) => println!("Window manager: unnamed (check window 0x{:08x})", check),
+        _ => println!("Window manager: not advertised (_NET_SUPPORTING_WM_CHECK missing)"),
+    }
+    if report.wm_check_stale {
+        println!("  warning: check window does not point back to itself, WM info may be stale");
+    }
+    println!("Icon theme: {}", get_current_icon_theme());
+    println!("Desktop: {}", std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_else(|_| "unknown".into()));
+    println!();
+
+    println!("Supported EWMH atoms ({}):", report.supported.len());
+    if report.supported.is_empty() {
+        println!("  none, _NET_SUPPORTED is missing");
+    }
+    for name in &report.supported {
+        println!("  {}", name);
+    }
+    println!();
+
+    println!("Client windows ({}):", report.clients.len());
+    if report.clients.is_empty() {
+        println!("  none, neither _NET_CLIENT_LIST nor WM_STATE list any windows");
+    }
+    for client in &report.clients {
+        print_client(client);
+    }
+
+    Ok(())
+}
+
+fn print_client(client: &ClientReport) {
+    println!("  0x{:08x} \"{}\"", client.xid, client.title);
+    println!("    class: {}  instance: {}", client.class, client.instance);
+    println!("    type: {}", join_or_none(&client.window_types));
+    println!("    state: {}", join_or_none(&client.states));
+    match (client.pid, &client.exe) {
+        (Some(pid), Some(exe)) => println!("    process: {} ({})", pid, exe.display()),
+        (Some(pid), None) => println!("    process: {}", pid),
+        (None, _) => println!("    process: unknown (no local _NET_WM_PID)"),
+    }
+    println!("    dock: {}", if client.skipped { "skipped by should_skip_window" } else { "shown" });
+
+    match &client.icon_path {
+        Some(path) => println!("    icon: {}", path.display()),
+        None => {
+            println!("    icon: not found (tried {})", join_or_none(&client.icon_candidates));
+            let fallback = if client.has_net_wm_icon { "available" } else { "missing" };
+            println!("    _NET_WM_ICON: {}", fallback);
+        }
+    }
+}
+
+fn join_or_none(items: &[String]) -> String {
+    if items.is_empty() {
+        "none".to_string()
+    } else {
+        items.join(", ")
+    }
+}
@@ -1,15 +1,21 @@
 use gtk::prelude::*;
 use gtk::{Box, Orientation, Image, Button, Window};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::sync::Arc;
 
-use aura_core::WindowInfo;
+use aura_core::groups::WindowGroups;
+use aura_core::process::{force_quit, CpuSampler, ProcessInfo};
+use aura_core::{Capabilities, Capability, WindowInfo};
+use crate::app_index::AppIndexUpdates;
 use crate::search::create_search_bar;
-use crate::app_grid::{create_app_grid_button, create_app_grid_window};
+use crate::app_grid::{create_app_grid_button, create_app_grid_window, show_actions_menu};
+use aura_assets::launch::Launcher;
 
 /// Creates the dock container with search bar and app grid button
-pub fn create_dock_container() -> (Box, Window) {
+pub fn create_dock_container(app_updates: &AppIndexUpdates) -> (Box, Window) {
     let hbox = Box::new(Orientation::Horizontal, 8);
     hbox.set_widget_name("dock-container");
     hbox.set_halign(gtk::Align::Center);
@@ -17,7 +23,7 @@ pub fn create_dock_container() -> (Box, Window) {
     hbox.set_margin_bottom(10);
 
     // Create app grid window
-    let app_grid_window = create_app_grid_window();
+    let app_grid_window = create_app_grid_window(app_updates);
     let grid_window_rc = Rc::new(RefCell::new(Some(app_grid_window.clone())));
 
     // Add app grid button
@@ -32,10 +38,12 @@ pub fn create_dock_container() -> (Box, Window) {
 }
 
 /// Creates an icon widget from window info
-fn create_icon_widget(info: &WindowInfo) -> Image {
+pub fn create_icon_widget(info: &WindowInfo) -> Image {
     let icon_widget = if let Some(path) = &info.icon_path {
         Image::from_file(path)
-    } else if let Some(raw) = &info.icon_data {
+    } else if let Some(raw) = &info.icon_data
+        && raw.has_pixels()
+    {
         let bytes = glib::Bytes::from(&raw.data);
         let pixbuf = gtk::gdk_pixbuf::Pixbuf::from_bytes(
             &bytes,
@@ -57,32 +65,242 @@ fn create_icon_widget(info: &WindowInfo) -> Image {
 }
 
 /// Adds a new window item to the dock
-pub fn add_window_item(widgets: &mut HashMap<u32, gtk::Widget>, hbox: &Box, info: WindowInfo) {
+pub fn add_window_item(
+    widgets: &mut HashMap<u32, gtk::Widget>,
+    hbox: &Box,
+    info: &WindowInfo,
+    groups: &Rc<RefCell<WindowGroups>>,
+) {
     let xid = info.xid;
-    let icon_widget = create_icon_widget(&info);
-
-    let tooltip = format!(
-        "Title: {}\nClass: {}\nIcon: {:?}",
-        info.title, info.class, info.icon_path
-    );
+    let icon_widget = create_icon_widget(info);
 
     let button = Button::builder()
         .child(&icon_widget)
         .has_frame(false)
         .css_classes(["dock-item"])
-        .tooltip_text(&tooltip)
         .build();
 
-    button.connect_clicked(move |_| {
-        if let Err(e) = aura_core::activate_window(xid) {
+    // Built on hover so resource usage is current
+    button.set_has_tooltip(true);
+    let model = groups.clone();
+    let cpu = RefCell::new(CpuSampler::new());
+    button.connect_query_tooltip(move |_, _, _, _, tooltip| {
+        let model = model.borrow();
+        let Some(info) = model.get(xid) else { return false };
+        let usage = info.pid.and_then(|pid| ProcessInfo::read(pid).ok()).map(|process| {
+            let cpu = cpu.borrow_mut().sample(&process).unwrap_or(0.0);
+            format!("CPU: {:.1}%  Memory: {:.0} MB", cpu, process.rss_bytes as f64 / 1_048_576.0)
+        });
+        tooltip.set_text(Some(&window_tooltip(info, &model.children(xid), usage)));
+        true
+    });
+
+    let groups = groups.clone();
+    button.connect_clicked(move |button| {
+        // A modal dialog blocks its parent, so bring the dialog forward instead
+        let model = groups.borrow();
+        let target = model.activation_target(xid);
+
+        // A hung app can't raise itself, offer to end it instead
+        if let Some(info) = model.get(target)
+            && info.unresponsive
+            && let Some(pid) = info.pid
+        {
+            show_force_quit(button, &info.title, pid);
+            return;
+        }
+
+        if let Err(e) = aura_core::activate_window(target) {
             eprintln!("Failed to activate window: {}", e);
         }
     });
 
+    // Right click lists the desktop actions of the window's app
+    let menu_gesture = gtk::GestureClick::new();
+    menu_gesture.set_button(gtk::gdk::BUTTON_SECONDARY);
+    let model = groups.clone();
+    let button_weak = button.downgrade();
+    menu_gesture.connect_pressed(move |gesture, _n, _x, _y| {
+        let Some(button) = button_weak.upgrade() else { return };
+        let index = aura_assets::index::current();
+        let Some(app) = model.borrow().get(xid).and_then(|info| match &info.desktop_id {
+            Some(desktop_id) => index.by_desktop_id(desktop_id).cloned(),
+            None => index.app_for_window(&info.class, None).cloned(),
+        }) else { return };
+        gesture.set_state(gtk::EventSequenceState::Claimed);
+        show_actions_menu(&button, &app, &Arc::new(Launcher::new()), |app, result| {
+            if let Err(e) = result {
+                eprintln!("Failed to launch {}: {}", app.localized_name, e);
+            }
+        });
+    });
+    button.add_controller(menu_gesture);
+
     hbox.append(&button);
     widgets.insert(xid, button.upcast());
 }
 
+/// Makes the dock items match the windows that aren't folded under another one.
+/// Returns whether items were added or removed.
+pub fn sync_window_items(
+    widgets: &mut HashMap<u32, gtk::Widget>,
+    hbox: &Box,
+    groups: &Rc<RefCell<WindowGroups>>,
+) -> bool {
+    let model = groups.borrow();
+    let items = model.items();
+    let mut changed = false;
+
+    let stale: Vec<u32> = widgets.keys()
+        .filter(|id| !items.iter().any(|info| info.xid == **id))
+        .cloned()
+        .collect();
+    for id in stale {
+        changed |= remove_window_item(widgets, hbox, id);
+    }
+
+    for info in items {
+        if !widgets.contains_key(&info.xid) {
+            add_window_item(widgets, hbox, info, groups);
+            changed = true;
+        }
+        if let Some(widget) = widgets.get(&info.xid) {
+            let children = model.children(info.xid);
+            if info.unresponsive || children.iter().any(|child| child.unresponsive) {
+                widget.add_css_class("not-responding");
+            } else {
+                widget.remove_css_class("not-responding");
+            }
+            if children.is_empty() {
+                widget.remove_css_class("has-dialogs");
+            } else {
+                widget.add_css_class("has-dialogs");
+            }
+        }
+    }
+    changed
+}
+
+/// Matches windows to apps again after apps were installed or removed, and updates
+/// the items of windows that now have a theme icon or a different one. Reading `/proc`
+/// and the icon themes happens on a worker thread.
+pub fn refresh_app_data(widgets: &Rc<RefCell<HashMap<u32, gtk::Widget>>>, groups: &Rc<RefCell<WindowGroups>>) {
+    let windows: Vec<(u32, String, Option<u32>)> = groups.borrow()
+        .windows()
+        .map(|info| (info.xid, info.class.clone(), info.pid))
+        .collect();
+    let worker = gtk::gio::spawn_blocking(move || match_apps(windows));
+
+    let widgets = widgets.clone();
+    let groups = groups.clone();
+    glib::MainContext::default().spawn_local(async move {
+        let Ok(matches) = worker.await else { return };
+        let mut model = groups.borrow_mut();
+        for (xid, desktop_id, icon_path) in matches {
+            // Windows may have closed or changed while the worker ran
+            let Some(info) = model.get(xid) else { continue };
+            let mut updated = WindowInfo { desktop_id, ..info.clone() };
+            let icon_changed = icon_path.is_some() && icon_path != info.icon_path;
+            if icon_changed {
+                updated.icon_path = icon_path;
+                updated.icon_data = None;
+            }
+            if updated == *info {
+                continue;
+            }
+
+            if icon_changed
+                && let Some(button) = widgets.borrow().get(&xid).and_then(|widget| widget.downcast_ref::<Button>())
+            {
+                button.set_child(Some(&create_icon_widget(&updated)));
+            }
+            model.insert(updated);
+        }
+    });
+}
+
+/// The desktop ID and theme icon of each `(xid, class, pid)`, found the same way as
+/// the sensor does: by the class, then by the executable
+fn match_apps(windows: Vec<(u32, String, Option<u32>)>) -> Vec<(u32, Option<String>, Option<PathBuf>)> {
+    let index = aura_assets::index::current();
+    windows.into_iter()
+        .map(|(xid, class, pid)| {
+            let exe = pid.and_then(|pid| ProcessInfo::read(pid).ok()).and_then(|process| process.exe_name());
+            let desktop_id = index.app_for_window(&class, exe.as_deref()).map(|app| app.desktop_id.clone());
+            let icon_path = aura_assets::lookup_icon(&class)
+                .or_else(|| exe.as_deref().and_then(aura_assets::lookup_icon));
+            (xid, desktop_id, icon_path)
+        })
+        .collect()
+}
+
+/// Builds the tooltip text for a window item and the dialogs folded under it
+fn window_tooltip(info: &WindowInfo, children: &[&WindowInfo], usage: Option<String>) -> String {
+    let mut tooltip = format!(
+        "Title: {}\nClass: {}\nIcon: {:?}",
+        info.title, info.class, info.icon_path
+    );
+    if info.unresponsive {
+        tooltip.push_str("\nNot responding");
+    }
+    if let Some(usage) = usage {
+        tooltip.push('\n');
+        tooltip.push_str(&usage);
+    }
+    for child in children {
+        let kind = if child.is_modal { "Modal dialog" } else { "Dialog" };
+        tooltip.push_str(&format!("\n{}: {}", kind, child.title));
+    }
+    tooltip
+}
+
+/// Asks for confirmation before killing the process of a window that stopped responding
+fn show_force_quit(anchor: &Button, title: &str, pid: u32) {
+    let content = Box::new(Orientation::Vertical, 8);
+    content.set_margin_top(8);
+    content.set_margin_bottom(8);
+    content.set_margin_start(8);
+    content.set_margin_end(8);
+
+    let message = gtk::Label::new(Some(&format!("\"{}\" is not responding.", title)));
+    content.append(&message);
+
+    let buttons = Box::new(Orientation::Horizontal, 8);
+    buttons.set_halign(gtk::Align::End);
+    let wait = Button::with_label("Wait");
+    let quit = Button::with_label("Force Quit");
+    quit.add_css_class("destructive-action");
+    buttons.append(&wait);
+    buttons.append(&quit);
+    content.append(&buttons);
+
+    let popover = gtk::Popover::builder()
+        .child(&content)
+        .position(gtk::PositionType::Top)
+        .build();
+    popover.set_parent(anchor);
+    popover.connect_closed(|popover| popover.unparent());
+
+    let popover_weak = popover.downgrade();
+    wait.connect_clicked(move |_| {
+        if let Some(popover) = popover_weak.upgrade() {
+            popover.popdown();
+        }
+    });
+
+    let popover_weak = popover.downgrade();
+    quit.connect_clicked(move |_| {
+        if let Err(e) = force_quit(pid) {
+            eprintln!("Failed to force quit process {}: {}", pid, e);
+        }
+        if let Some(popover) = popover_weak.upgrade() {
+            popover.popdown();
+        }
+    });
+
+    popover.popup();
+}
+
 /// Removes a window item from the dock
 pub fn remove_window_item(widgets: &mut HashMap<u32, gtk::Widget>, hbox: &Box, id: u32) -> bool {
     if let Some(widget) = widgets.remove(&id) {
@@ -103,3 +321,48 @@ pub fn update_focus(widgets: &HashMap<u32, gtk::Widget>, focused_id: u32) {
         }
     }
 }
+
+/// Shows a warning item listing the dock features the window manager does not support,
+/// or removes it once everything is available again. Returns whether the dock changed.
+pub fn update_capability_notice(notice: &mut Option<gtk::Widget>, hbox: &Box, capabilities: &Capabilities) -> bool {
+    let missing = missing_features(capabilities);
+    let had_notice = notice.is_some();
+
+    if missing.is_empty() {
+        if let Some(widget) = notice.take() {
+            hbox.remove(&widget);
+        }
+        return had_notice;
+    }
+
+    let wm_name = capabilities.wm_name.as_deref().unwrap_or("The window manager");
+    let tooltip = format!("{} does not support:\n{}", wm_name, missing.join("\n"));
+
+    match notice {
+        Some(widget) => widget.set_tooltip_text(Some(&tooltip)),
+        None => {
+            let icon = Image::from_icon_name("dialog-warning-symbolic");
+            icon.set_pixel_size(24);
+            icon.add_css_class("dock-notice");
+            icon.set_tooltip_text(Some(&tooltip));
+            hbox.append(&icon);
+            *notice = Some(icon.upcast());
+        }
+    }
+    !had_notice
+}
+
+/// Describes what is degraded for every feature the dock relies on
+fn missing_features(capabilities: &Capabilities) -> Vec<&'static str> {
+    // Missing _NET_CLIENT_LIST is covered by the sensor's WM_STATE tracking
+    let features = [
+        (Capability::ActiveWindow, "highlighting the focused window"),
+        (Capability::StateAbove, "keeping the dock above other windows"),
+        (Capability::StateSkipTaskbar, "hiding the dock from taskbars"),
+    ];
+
+    features.iter()
+        .filter(|(capability, _)| !capabilities.supports(*capability))
+        .map(|(_, description)| *description)
+        .collect()
+}
@@ -1,23 +1,48 @@
 mod app_grid;
+mod app_index;
 mod autohide;
+mod cli;
+mod config;
+mod diagnose;
 mod dock;
 mod search;
 mod sensor;
+mod shortcuts;
 mod style;
+mod switcher;
 mod window;
 
 use gtk::prelude::*;
 use gtk::Application;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use aura_core::groups::WindowGroups;
 
 const APP_ID: &str = "com.vladimir.aura";
 
 fn main() {
+    let (options, gtk_args) = cli::Options::parse(std::env::args());
+
+    if options.diagnose {
+        if let Err(e) = diagnose::run() {
+            eprintln!("Diagnostics failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Parse the desktop entries while GTK starts, not on the sensor's or the UI's thread
+    aura_assets::index::preload();
+
+    let config = config::Config::from_env();
+
     let app = Application::builder().application_id(APP_ID).build();
-    app.connect_activate(build_ui);
-    app.run();
+    app.connect_activate(move |app| build_ui(app, &options, &config));
+    app.run_with_args(&gtk_args);
 }
 
-fn build_ui(app: &Application) {
+fn build_ui(app: &Application, options: &cli::Options, config: &config::Config) {
     // Get screen geometry
     let geometry = window::get_screen_geometry();
 
@@ -27,8 +52,11 @@ fn build_ui(app: &Application) {
     // Create main window
     let window = window::create_dock_window(app, &geometry);
     
+    // Installing or removing software updates the grid and the dock icons
+    let app_updates = app_index::AppIndexUpdates::new();
+
     // Create dock container and app grid
-    let (hbox, app_grid_window) = dock::create_dock_container();
+    let (hbox, app_grid_window) = dock::create_dock_container(&app_updates);
     window.set_child(Some(&hbox));
     
     // Position app grid window (will be shown/hidden by button)
@@ -38,15 +66,26 @@ fn build_ui(app: &Application) {
     window::setup_window_hints(&window);
 
     // Setup auto-hide behavior
-    let autohide_state = autohide::AutoHideState::new();
+    let autohide_state = autohide::AutoHideState::new(config.hide_policy, &geometry);
     autohide::setup_hide_checker(&hbox, &autohide_state);
     autohide::setup_motion_controller(&window, &hbox, &autohide_state, geometry.height);
 
     // Create input region updater for click-through
     let region_updater = window::InputRegionUpdater::new(&window, &hbox, geometry.height);
 
+    // Get out of the way of fullscreen video players and presentations
+    let fullscreen = window::FullscreenHider::new(&window, &geometry);
+
+    // The sensor keeps the window model current, the switcher reads it
+    let groups = Rc::new(RefCell::new(WindowGroups::new()));
+    let switcher = switcher::Switcher::new(&window, &groups);
+
     // Start sensor and event loop
-    sensor::start_sensor_loop(&hbox, region_updater);
+    sensor::start_sensor_loop(&hbox, region_updater, fullscreen, &autohide_state, &groups, &app_updates, options);
+    app_updates.start();
+
+    // Super+1..9, Super+A, the search shortcut and Alt+Tab
+    shortcuts::setup_shortcuts(&window, &hbox, &autohide_state, &switcher, config);
 
     // Show window
     window.present();
@@ -2,6 +2,8 @@ use gtk::prelude::*;
 use gtk::SearchEntry;
 use std::process::Command;
 
+use aura_assets::launch::{log_path, spawn_detached};
+
 /// Creates and configures the search bar widget
 pub fn create_search_bar() -> SearchEntry {
     let search_entry = SearchEntry::new();
@@ -40,7 +42,7 @@ fn open_perplexity_search(query: &str) {
     let encoded = url_encode(query);
     let url = format!("https://www.perplexity.ai/search?q={}", encoded);
     
-    if let Err(e) = Command::new("xdg-open").arg(&url).spawn() {
+    if let Err(e) = spawn_detached(Command::new("xdg-open").arg(&url), log_path("xdg-open").as_deref()) {
         eprintln!("Failed to open browser: {}", e);
     }
 }
@@ -1,53 +1,135 @@
 use gtk::prelude::*;
 use gtk::Box;
-use tokio::sync::mpsc;
+use tokio_stream::{Stream, StreamExt};
 use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::pin::Pin;
+use std::rc::Rc;
 use std::time::Duration;
 
-use aura_core::{Sensor, SensorEvent};
-use crate::dock::{add_window_item, remove_window_item, update_focus};
-use crate::window::InputRegionUpdater;
+use aura_core::groups::WindowGroups;
+use aura_core::{SensorBuilder, SensorEvent, SensorHandle};
+use aura_core::record::{Recorder, Replay};
+use crate::app_index::AppIndexUpdates;
+use crate::autohide::AutoHideState;
+use crate::cli::Options;
+use crate::dock::{refresh_app_data, sync_window_items, update_capability_notice, update_focus};
+use crate::window::{FullscreenHider, InputRegionUpdater};
+
+/// Delay before re-applying the input region, so a burst of changes shares one update
+const REGION_UPDATE_DELAY_MS: u64 = 50;
+
+/// How often the dock pings windows to mark hung applications
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+
+type EventStream = Pin<std::boxed::Box<dyn Stream<Item = SensorEvent>>>;
+
+/// Opens the live sensor, or a recording when `--replay` was given. The handle of a
+/// live sensor must outlive the stream, it is how the sensor is woken for commands.
+fn open_event_source(options: &Options) -> Option<(EventStream, Option<SensorHandle>)> {
+    if let Some(path) = &options.replay {
+        return match Replay::open(path) {
+            Ok(replay) => Some((std::boxed::Box::pin(replay.into_stream()), None)),
+            Err(e) => {
+                eprintln!("CRITICAL: Could not load recording {}: {}", path.display(), e);
+                None
+            }
+        };
+    }
+
+    match SensorBuilder::new().ping_interval(Some(PING_INTERVAL)).build() {
+        Ok((events, handle)) => Some((std::boxed::Box::pin(events), Some(handle))),
+        Err(e) => {
+            eprintln!("CRITICAL: Could not start Aura Sensor: {}", e);
+            None
+        }
+    }
+}
 
 /// Starts the sensor and spawns the event handling loop
-pub fn start_sensor_loop(hbox: &Box, region_updater: InputRegionUpdater) {
-    let (tx, mut rx) = mpsc::channel(32);
-    Sensor::spawn(tx);
+pub fn start_sensor_loop(
+    hbox: &Box,
+    region_updater: InputRegionUpdater,
+    fullscreen: FullscreenHider,
+    autohide: &AutoHideState,
+    groups: &Rc<RefCell<WindowGroups>>,
+    app_updates: &AppIndexUpdates,
+    options: &Options,
+) {
+    let Some((mut events, handle)) = open_event_source(options) else { return };
+
+    let mut recorder = options.record.as_ref().and_then(|path| {
+        Recorder::create(path)
+            .map_err(|e| eprintln!("Failed to start recording to {}: {}", path.display(), e))
+            .ok()
+    });
 
     let hbox_weak = hbox.downgrade();
+    let groups = groups.clone();
+    let autohide = autohide.clone();
+
+    // Newly installed apps can give open windows a better icon and their desktop actions
+    let widgets: Rc<RefCell<HashMap<u32, gtk::Widget>>> = Rc::new(RefCell::new(HashMap::new()));
+    let widgets_for_icons = widgets.clone();
+    let groups_for_icons = groups.clone();
+    app_updates.connect(move |_| refresh_app_data(&widgets_for_icons, &groups_for_icons));
 
     glib::MainContext::default().spawn_local(async move {
-        let mut widgets: HashMap<u32, gtk::Widget> = HashMap::new();
+        let _handle = handle;
+        let mut focused: Option<u32> = None;
+        let mut notice: Option<gtk::Widget> = None;
+        let update_pending = Rc::new(Cell::new(false));
 
-        while let Some(event) = rx.recv().await {
+        while let Some(event) = events.next().await {
             let Some(hbox) = hbox_weak.upgrade() else { break };
+            let mut widgets = widgets.borrow_mut();
+
+            if let Some(rec) = &mut recorder
+                && let Err(e) = rec.record(&event)
+            {
+                eprintln!("Failed to record sensor event, recording stopped: {}", e);
+                recorder = None;
+            }
+
             let mut changed = false;
 
-            match event {
-                SensorEvent::FullScan(windows) => {
-                    for info in windows {
-                        if !widgets.contains_key(&info.xid) {
-                            add_window_item(&mut widgets, &hbox, info);
-                            changed = true;
-                        }
-                    }
-                }
-                SensorEvent::WindowOpen(info) => {
-                    add_window_item(&mut widgets, &hbox, info);
-                    changed = true;
+            match &event {
+                SensorEvent::FocusChange(id) => {
+                    focused = Some(*id);
+                    // Keeps the focus order for the switcher
+                    groups.borrow_mut().apply(&event);
                 }
-                SensorEvent::WindowClose(id) => {
-                    if remove_window_item(&mut widgets, &hbox, id) {
-                        changed = true;
-                    }
+                SensorEvent::Capabilities(capabilities) => {
+                    changed = update_capability_notice(&mut notice, &hbox, capabilities);
                 }
-                SensorEvent::FocusChange(id) => {
-                    update_focus(&widgets, id);
+                // A full scan is authoritative, the model drops windows that are gone
+                _ => {
+                    groups.borrow_mut().apply(&event);
+                    changed = sync_window_items(&mut widgets, &hbox, &groups);
                 }
             }
 
-            if changed {
+            // A focused dialog highlights the item it is folded under
+            if let Some(id) = focused {
+                update_focus(&widgets, groups.borrow().item_for(id));
+            }
+
+            // Entering or leaving fullscreen arrives as a focus change or a window update
+            let model = groups.borrow();
+            fullscreen.update(focused.and_then(|id| model.get(id)));
+
+            // Geometry changes arrive as window updates too
+            autohide.update_overlap(&hbox, &model, focused);
+
+            // One deferred update covers every change that arrives before it fires
+            if changed && !update_pending.get() {
+                update_pending.set(true);
                 let updater = region_updater.clone();
-                glib::timeout_add_local(Duration::from_millis(50), move || updater.update());
+                let update_pending = update_pending.clone();
+                glib::timeout_add_local(Duration::from_millis(REGION_UPDATE_DELAY_MS), move || {
+                    update_pending.set(false);
+                    updater.update()
+                });
             }
         }
     });
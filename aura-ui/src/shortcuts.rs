@@ -0,0 +1,120 @@
+use gtk::prelude::*;
+use gtk::{ApplicationWindow, Box, Button};
+use tokio_stream::StreamExt;
+
+use aura_core::hotkeys::{self, Hotkey, HotkeyEvent};
+use crate::autohide::AutoHideState;
+use crate::config::Config;
+use crate::switcher::Switcher;
+
+/// What a global shortcut does in the dock
+#[derive(Debug, Clone, Copy)]
+enum Action {
+    /// Activate the nth window item (Super+1..9)
+    ActivateItem(usize),
+    ToggleAppGrid,
+    FocusSearch,
+    /// Open the switcher or move to the next (or previous) window while the modifiers are held
+    Switch { forward: bool },
+}
+
+/// Grabs the global shortcuts and performs their actions on the dock
+pub fn setup_shortcuts(
+    window: &ApplicationWindow,
+    hbox: &Box,
+    autohide: &AutoHideState,
+    switcher: &Switcher,
+    config: &Config,
+) {
+    let mut bindings = Vec::new();
+    for n in 1..=9u32 {
+        let digit = char::from_digit(n, 10).unwrap();
+        bindings.push((format!("Super+{}", digit), Action::ActivateItem(n as usize - 1)));
+    }
+    bindings.push(("Super+A".to_string(), Action::ToggleAppGrid));
+    bindings.push((config.search_hotkey.clone(), Action::FocusSearch));
+    bindings.push((config.switcher_hotkey.clone(), Action::Switch { forward: true }));
+    bindings.push((format!("Shift+{}", config.switcher_hotkey), Action::Switch { forward: false }));
+
+    let mut hotkeys = Vec::new();
+    let mut actions = Vec::new();
+    for (spec, action) in bindings {
+        match Hotkey::parse(&spec) {
+            Ok(hotkey) => {
+                // The switcher stays open until the modifiers are released
+                let hotkey = if matches!(action, Action::Switch { .. }) { hotkey.held() } else { hotkey };
+                hotkeys.push(hotkey);
+                actions.push(action);
+            }
+            Err(e) => eprintln!("Ignoring shortcut: {}", e),
+        }
+    }
+
+    let mut events = match hotkeys::listen(hotkeys) {
+        Ok(events) => events,
+        Err(e) => {
+            eprintln!("Global shortcuts unavailable: {}", e);
+            return;
+        }
+    };
+
+    let window_weak = window.downgrade();
+    let hbox_weak = hbox.downgrade();
+    let autohide = autohide.clone();
+    let switcher = switcher.clone();
+
+    glib::MainContext::default().spawn_local(async move {
+        while let Some(event) = events.next().await {
+            let index = match event {
+                HotkeyEvent::Pressed(index) => index,
+                HotkeyEvent::HoldEnded(_) => {
+                    switcher.finish();
+                    continue;
+                }
+                HotkeyEvent::HoldCancelled(_) => {
+                    switcher.cancel();
+                    continue;
+                }
+                HotkeyEvent::Released(_) => continue,
+            };
+            let (Some(window), Some(hbox)) = (window_weak.upgrade(), hbox_weak.upgrade()) else { break };
+
+            match actions[index] {
+                Action::ActivateItem(n) => {
+                    if let Some(button) = window_items(&hbox).into_iter().nth(n) {
+                        button.emit_clicked();
+                    }
+                }
+                Action::ToggleAppGrid => {
+                    if let Some(button) = find_child(&hbox, "app-grid-button").and_then(|w| w.downcast::<Button>().ok()) {
+                        button.emit_clicked();
+                    }
+                }
+                Action::FocusSearch => {
+                    if let Some(entry) = find_child(&hbox, "dock-search") {
+                        autohide.reveal(&hbox);
+                        window.present();
+                        entry.grab_focus();
+                    }
+                }
+                Action::Switch { forward } => switcher.step(forward),
+            }
+        }
+    });
+}
+
+/// The dock's window items, left to right
+fn window_items(hbox: &Box) -> Vec<Button> {
+    children(hbox)
+        .filter(|child| child.has_css_class("dock-item") && !child.has_css_class("app-grid-button"))
+        .filter_map(|child| child.downcast::<Button>().ok())
+        .collect()
+}
+
+fn find_child(hbox: &Box, css_class: &str) -> Option<gtk::Widget> {
+    children(hbox).find(|child| child.has_css_class(css_class))
+}
+
+fn children(hbox: &Box) -> impl Iterator<Item = gtk::Widget> {
+    std::iter::successors(hbox.first_child(), |child| child.next_sibling())
+}
@@ -28,6 +28,16 @@ const CSS: &str = r#"
         background-color: rgba(255, 255, 255, 0.15);
         box-shadow: inset 0 -2px 0 0 rgba(100, 200, 255, 0.8);
     }
+    .has-dialogs:not(.active-window) {
+        box-shadow: inset 0 -2px 0 0 rgba(255, 190, 80, 0.6);
+    }
+    .not-responding {
+        opacity: 0.5;
+    }
+    .dock-notice {
+        color: rgba(255, 190, 80, 0.9);
+        margin: 0 4px;
+    }
     .dock-search {
         background-color: rgba(255, 255, 255, 0.1);
         border: 1px solid rgba(255, 255, 255, 0.15);
@@ -110,6 +120,40 @@ const CSS: &str = r#"
     .app-grid-button {
         padding: 4px;
     }
+
+    .app-grid-error {
+        color: rgba(255, 120, 100, 0.95);
+        font-size: 12px;
+    }
+
+    .app-action {
+        padding: 4px 8px;
+        border-radius: 6px;
+    }
+
+    .app-action:hover {
+        background-color: rgba(255, 255, 255, 0.1);
+    }
+
+    .switcher {
+        background-color: rgba(30, 30, 30, 0.9);
+        border-radius: 16px;
+        padding: 12px;
+    }
+
+    .switcher-item {
+        border-radius: 12px;
+        padding: 8px;
+    }
+
+    .switcher-item.selected {
+        background-color: rgba(255, 255, 255, 0.15);
+    }
+
+    .switcher-label {
+        color: rgba(255, 255, 255, 0.9);
+        font-size: 11px;
+    }
 "#;
 
 pub fn load_css() {
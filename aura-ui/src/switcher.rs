@@ -0,0 +1,128 @@
+use gtk::prelude::*;
+use gtk::{ApplicationWindow, Box, Label, Orientation, Window};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use aura_core::groups::WindowGroups;
+use crate::dock::create_icon_widget;
+
+/// Longest title shown under a switcher icon, in characters
+const TITLE_WIDTH_CHARS: i32 = 14;
+
+/// The Alt+Tab overlay, listing the dock's windows by recent focus
+#[derive(Clone)]
+pub struct Switcher {
+    window: Window,
+    row: Box,
+    groups: Rc<RefCell<WindowGroups>>,
+    // item windows in the order shown, and which one is selected
+    items: Rc<RefCell<Vec<u32>>>,
+    selected: Rc<Cell<usize>>,
+}
+
+impl Switcher {
+    /// Creates the (hidden) overlay; the window list comes from the sensor's model
+    pub fn new(parent: &ApplicationWindow, groups: &Rc<RefCell<WindowGroups>>) -> Self {
+        let window = Window::builder()
+            .title("Switch Windows")
+            .decorated(false)
+            .resizable(false)
+            .modal(false)
+            .transient_for(parent)
+            .build();
+        window.set_widget_name("switcher-window");
+
+        let row = Box::new(Orientation::Horizontal, 8);
+        row.add_css_class("switcher");
+        window.set_child(Some(&row));
+
+        Self {
+            window,
+            row,
+            groups: groups.clone(),
+            items: Rc::new(RefCell::new(Vec::new())),
+            selected: Rc::new(Cell::new(0)),
+        }
+    }
+
+    /// Opens the overlay or moves the selection while it is open
+    pub fn step(&self, forward: bool) {
+        if !self.window.is_visible() {
+            self.populate();
+            let count = self.items.borrow().len();
+            if count == 0 {
+                return;
+            }
+            // The first window is the focused one, so start at the one before it
+            self.select(if forward { 1 % count } else { count - 1 });
+            self.window.present();
+            return;
+        }
+
+        let count = self.items.borrow().len();
+        if count == 0 {
+            return;
+        }
+        let current = self.selected.get();
+        self.select(if forward { (current + 1) % count } else { (current + count - 1) % count });
+    }
+
+    /// Activates the selected window and closes the overlay
+    pub fn finish(&self) {
+        if !self.window.is_visible() {
+            return;
+        }
+        self.window.set_visible(false);
+
+        let Some(&xid) = self.items.borrow().get(self.selected.get()) else { return };
+        // Same as clicking the dock item: a modal dialog comes forward instead
+        let target = self.groups.borrow().activation_target(xid);
+        if let Err(e) = aura_core::activate_window(target) {
+            eprintln!("Failed to activate window: {}", e);
+        }
+    }
+
+    /// Closes the overlay without switching
+    pub fn cancel(&self) {
+        self.window.set_visible(false);
+    }
+
+    fn populate(&self) {
+        while let Some(child) = self.row.first_child() {
+            self.row.remove(&child);
+        }
+
+        let groups = self.groups.borrow();
+        let mut items = self.items.borrow_mut();
+        items.clear();
+        for info in groups.recent_items() {
+            let item = Box::new(Orientation::Vertical, 4);
+            item.add_css_class("switcher-item");
+            item.append(&create_icon_widget(info));
+
+            let label = Label::new(Some(&info.title));
+            label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+            label.set_max_width_chars(TITLE_WIDTH_CHARS);
+            label.add_css_class("switcher-label");
+            item.append(&label);
+
+            self.row.append(&item);
+            items.push(info.xid);
+        }
+    }
+
+    fn select(&self, index: usize) {
+        self.selected.set(index);
+        let mut child = self.row.first_child();
+        let mut position = 0;
+        while let Some(widget) = child {
+            if position == index {
+                widget.add_css_class("selected");
+            } else {
+                widget.remove_css_class("selected");
+            }
+            position += 1;
+            child = widget.next_sibling();
+        }
+    }
+}
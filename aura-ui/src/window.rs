@@ -4,9 +4,14 @@ use std::cell::Cell;
 use std::rc::Rc;
 use std::time::Duration;
 
-use aura_core::{set_always_on_top_by_name, set_skip_taskbar_by_name};
+use aura_core::{
+    remove_always_on_top_by_name, set_always_on_top_by_name, set_skip_taskbar_by_name, Capabilities, Capability,
+    FrameExtents, Geometry, WindowInfo,
+};
 
 pub struct ScreenGeometry {
+    pub x: i32,
+    pub y: i32,
     pub width: i32,
     pub height: i32,
 }
@@ -19,6 +24,8 @@ pub fn get_screen_geometry() -> ScreenGeometry {
     let geometry = monitor.geometry();
     
     ScreenGeometry {
+        x: geometry.x(),
+        y: geometry.y(),
         width: geometry.width(),
         height: geometry.height(),
     }
@@ -38,45 +45,61 @@ pub fn create_dock_window(app: &Application, geometry: &ScreenGeometry) -> Appli
 
 /// Sets up X11 window hints (always-on-top, skip-taskbar) after window is realized
 pub fn setup_window_hints(window: &ApplicationWindow) {
-    window.connect_realize(|_win| {
-        let retry_count = Rc::new(Cell::new(0));
-        let skip_taskbar_done = Rc::new(Cell::new(false));
-        let always_on_top_done = Rc::new(Cell::new(false));
-        
-        glib::timeout_add_local(Duration::from_millis(500), move || {
-            // Try to set skip taskbar
-            if !skip_taskbar_done.get() {
-                if set_skip_taskbar_by_name("Aura Dock").is_ok() {
-                    skip_taskbar_done.set(true);
-                }
+    window.connect_realize(|_win| apply_window_hints());
+}
+
+/// Asks the window manager for the dock's states, retrying until its window shows up.
+/// Needed again after the dock was unmapped, since the window manager forgets them.
+pub fn apply_window_hints() {
+    let retry_count = Rc::new(Cell::new(0));
+    let skip_taskbar_done = Rc::new(Cell::new(false));
+    let always_on_top_done = Rc::new(Cell::new(false));
+
+    // Don't keep asking for states the window manager has said it ignores
+    if let Ok(capabilities) = Capabilities::query() {
+        if !capabilities.supports(Capability::StateSkipTaskbar) {
+            eprintln!("Note: window manager does not support skip-taskbar, not setting it");
+            skip_taskbar_done.set(true);
+        }
+        if !capabilities.supports(Capability::StateAbove) {
+            eprintln!("Note: window manager does not support always-on-top, not setting it");
+            always_on_top_done.set(true);
+        }
+    }
+
+    glib::timeout_add_local(Duration::from_millis(500), move || {
+        // Try to set skip taskbar
+        if !skip_taskbar_done.get() {
+            if set_skip_taskbar_by_name("Aura Dock").is_ok() {
+                skip_taskbar_done.set(true);
             }
-            
-            // Try to set always on top
-            if !always_on_top_done.get() {
-                if set_always_on_top_by_name("Aura Dock").is_ok() {
-                    always_on_top_done.set(true);
-                }
+        }
+        
+        // Try to set always on top
+        if !always_on_top_done.get() {
+            if set_always_on_top_by_name("Aura Dock").is_ok() {
+                always_on_top_done.set(true);
             }
-            
-            // Check if both done or max retries reached
-            if skip_taskbar_done.get() && always_on_top_done.get() {
-                glib::ControlFlow::Break
+        }
+        
+        // Check if both done or max retries reached
+        if skip_taskbar_done.get() && always_on_top_done.get() {
+            glib::ControlFlow::Break
+        } else {
+            let count = retry_count.get();
+            if count < 5 {
+                retry_count.set(count + 1);
+                glib::ControlFlow::Continue
             } else {
-                let count = retry_count.get();
-                if count < 5 {
-                    retry_count.set(count + 1);
-                    glib::ControlFlow::Continue
-                } else {
-                    if !skip_taskbar_done.get() {
-                        eprintln!("Warning: Could not set skip-taskbar after retries");
-                    }
-                    if !always_on_top_done.get() {
-                        eprintln!("Warning: Could not set always-on-top after retries");
-                    }
-                    glib::ControlFlow::Break
+                if !skip_taskbar_done.get() {
+                    eprintln!("Warning: Could not set skip-taskbar after retries");
                 }
+                if !always_on_top_done.get() {
+                    eprintln!("Warning: Could not set always-on-top after retries");
+                }
+                glib::ControlFlow::Break
             }
-        });
+        }
     });
 }
 
@@ -123,3 +146,58 @@ impl InputRegionUpdater {
         glib::ControlFlow::Break
     }
 }
+
+/// Takes the dock out of the way while the focused window on its monitor is fullscreen
+#[derive(Clone)]
+pub struct FullscreenHider {
+    window_weak: glib::WeakRef<ApplicationWindow>,
+    monitor: Geometry,
+    hidden: Rc<Cell<bool>>,
+}
+
+impl FullscreenHider {
+    pub fn new(window: &ApplicationWindow, screen: &ScreenGeometry) -> Self {
+        Self {
+            window_weak: window.downgrade(),
+            monitor: Geometry {
+                x: screen.x,
+                y: screen.y,
+                width: screen.width.max(0) as u32,
+                height: screen.height.max(0) as u32,
+            },
+            hidden: Rc::new(Cell::new(false)),
+        }
+    }
+
+    /// Hides or restores the dock for the window that has the focus now
+    pub fn update(&self, focused: Option<&WindowInfo>) {
+        let fullscreen = focused.is_some_and(|info| self.is_fullscreen(info));
+        if fullscreen == self.hidden.get() {
+            return;
+        }
+        let Some(window) = self.window_weak.upgrade() else { return };
+        self.hidden.set(fullscreen);
+
+        if fullscreen {
+            // Drop the above state first, or the window manager may keep stacking the dock on top
+            if let Err(e) = remove_always_on_top_by_name("Aura Dock") {
+                eprintln!("Failed to lower the dock: {}", e);
+            }
+            window.set_visible(false);
+        } else {
+            window.set_visible(true);
+            apply_window_hints();
+        }
+    }
+
+    /// Fullscreen by state, or by covering the whole monitor without a frame (games and
+    /// players that skip the hint); maximized and decorated windows only count by state
+    fn is_fullscreen(&self, info: &WindowInfo) -> bool {
+        let frameless = info.frame_extents.is_none_or(|extents| extents == FrameExtents::default());
+        match info.geometry {
+            Some(geometry) => geometry.intersects(&self.monitor)
+                && (info.is_fullscreen || (frameless && !info.is_maximized && geometry.covers(&self.monitor))),
+            None => info.is_fullscreen,
+        }
+    }
+}